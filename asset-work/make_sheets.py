@@ -0,0 +1,210 @@
+"""Builds the animated sprite sheets in assets/ from the single frame sprites.
+
+Each frame is the source sprite with a tint, an offset or a scale applied, laid out in one
+row. The frame ranges match the clips in src/sprite_animation.rs. Run it again after
+exporting a changed sprite from its .aseprite file:
+
+    python3 asset-work/make_sheets.py
+
+Only the standard library is used, the PNGs are read and written as 8 bit RGBA.
+"""
+
+import struct
+import zlib
+from pathlib import Path
+
+ASSETS = Path(__file__).resolve().parent.parent / "assets"
+
+WHITE = (255, 255, 255)
+BLACK = (0, 0, 0)
+RED = (255, 40, 40)
+CLEAR = (0, 0, 0, 0)
+
+
+def read_png(path):
+    data = path.read_bytes()
+    pos = 8
+    idat = b""
+    while pos < len(data):
+        (length,) = struct.unpack(">I", data[pos : pos + 4])
+        kind = data[pos + 4 : pos + 8]
+        body = data[pos + 8 : pos + 8 + length]
+        if kind == b"IHDR":
+            width, height, depth, color, _, _, interlace = struct.unpack(">IIBBBBB", body)
+            assert (depth, color, interlace) == (8, 6, 0), f"{path} is not 8 bit RGBA"
+        elif kind == b"IDAT":
+            idat += body
+        pos += 12 + length
+
+    raw = zlib.decompress(idat)
+    stride = width * 4
+    rows = []
+    prev = bytearray(stride)
+    i = 0
+    for _ in range(height):
+        filter_type = raw[i]
+        line = bytearray(raw[i + 1 : i + 1 + stride])
+        i += 1 + stride
+        for x in range(stride):
+            a = line[x - 4] if x >= 4 else 0
+            b = prev[x]
+            c = prev[x - 4] if x >= 4 else 0
+            if filter_type == 1:
+                line[x] = (line[x] + a) & 255
+            elif filter_type == 2:
+                line[x] = (line[x] + b) & 255
+            elif filter_type == 3:
+                line[x] = (line[x] + (a + b) // 2) & 255
+            elif filter_type == 4:
+                p = a + b - c
+                pa, pb, pc = abs(p - a), abs(p - b), abs(p - c)
+                predictor = a if pa <= pb and pa <= pc else b if pb <= pc else c
+                line[x] = (line[x] + predictor) & 255
+        rows.append(line)
+        prev = line
+    return [[tuple(row[x * 4 : x * 4 + 4]) for x in range(width)] for row in rows]
+
+
+def write_png(path, pixels):
+    height = len(pixels)
+    width = len(pixels[0])
+    raw = b"".join(b"\0" + bytes(v for pixel in row for v in pixel) for row in pixels)
+
+    def chunk(kind, body):
+        crc = zlib.crc32(kind + body) & 0xFFFFFFFF
+        return struct.pack(">I", len(body)) + kind + body + struct.pack(">I", crc)
+
+    header = struct.pack(">IIBBBBB", width, height, 8, 6, 0, 0, 0)
+    path.write_bytes(
+        b"\x89PNG\r\n\x1a\n"
+        + chunk(b"IHDR", header)
+        + chunk(b"IDAT", zlib.compress(raw, 9))
+        + chunk(b"IEND", b"")
+    )
+
+
+def tint(color, amount, alpha=1.0):
+    """Moves every pixel `amount` of the way to `color`, and scales its alpha."""
+
+    def apply(img):
+        return [
+            [
+                (
+                    round(r + (color[0] - r) * amount),
+                    round(g + (color[1] - g) * amount),
+                    round(b + (color[2] - b) * amount),
+                    round(a * alpha),
+                )
+                for r, g, b, a in row
+            ]
+            for row in img
+        ]
+
+    return apply
+
+
+def shift(dy):
+    """Moves the sprite `dy` pixels down."""
+
+    def apply(img):
+        empty = [CLEAR] * len(img[0])
+        return [img[y - dy] if 0 <= y - dy < len(img) else empty for y in range(len(img))]
+
+    return apply
+
+
+def scale(factor):
+    """Scales the sprite around its center, nearest neighbour."""
+
+    def apply(img):
+        height = len(img)
+        width = len(img[0])
+        cx, cy = (width - 1) / 2, (height - 1) / 2
+        out = []
+        for y in range(height):
+            row = []
+            for x in range(width):
+                sx = round(cx + (x - cx) / factor)
+                sy = round(cy + (y - cy) / factor)
+                inside = 0 <= sx < width and 0 <= sy < height
+                row.append(img[sy][sx] if inside else CLEAR)
+            out.append(row)
+        return out
+
+    return apply
+
+
+def same(img):
+    return img
+
+
+def then(*steps):
+    def apply(img):
+        for step in steps:
+            img = step(img)
+        return img
+
+    return apply
+
+
+def sheet(name, frames):
+    img = read_png(ASSETS / f"{name}.png")
+    images = [frame(img) for frame in frames]
+    pixels = [sum((image[y] for image in images), []) for y in range(len(img))]
+    write_png(ASSETS / f"{name}_sheet.png", pixels)
+    print(f"{name}_sheet.png: {len(images)} frames")
+
+
+ROBOT_FRAMES = [
+    # ROBOT_IDLE
+    same,
+    tint(WHITE, 0.12),
+    # ROBOT_MOVE
+    same,
+    shift(-1),
+    same,
+    shift(1),
+    # ROBOT_FIRE
+    tint(WHITE, 0.7),
+    tint(WHITE, 0.4),
+    tint(WHITE, 0.15),
+    # ROBOT_DEATH
+    tint(WHITE, 0.5),
+    then(scale(0.9), tint(BLACK, 0.4, 0.85)),
+    then(scale(0.75), tint(BLACK, 0.65, 0.6)),
+    then(scale(0.55), tint(BLACK, 0.85, 0.3)),
+]
+
+PORTAL_FRAMES = [
+    # PORTAL_IDLE
+    same,
+    tint(WHITE, 0.08),
+    tint(WHITE, 0.16),
+    tint(WHITE, 0.08),
+    # PORTAL_SPAWN
+    tint(WHITE, 0.7),
+    then(scale(1.1), tint(WHITE, 0.5)),
+    tint(WHITE, 0.3),
+    tint(WHITE, 0.1),
+    # PORTAL_DAMAGED
+    tint(RED, 0.6),
+    tint(RED, 0.25),
+]
+
+TOOL_FRAMES = [
+    # TOOL_IDLE
+    same,
+    # TOOL_PULSE
+    then(scale(1.15), tint(WHITE, 0.6)),
+    then(scale(1.1), tint(WHITE, 0.4)),
+    then(scale(1.05), tint(WHITE, 0.2)),
+    tint(WHITE, 0.08),
+]
+
+
+if __name__ == "__main__":
+    sheet("robot_body", ROBOT_FRAMES)
+    for portal in ["player_portal", "enemy_portal"]:
+        sheet(portal, PORTAL_FRAMES)
+    for tool in ["arrow", "fork", "grouper", "defender"]:
+        sheet(tool, TOOL_FRAMES)
@@ -0,0 +1,119 @@
+"""Draws the status effect icons into assets/status_icons.png.
+
+One 8x8 icon per effect, laid out in one row in the order of `StatusKind::icon` in
+src/status_effect.rs, each with a dark outline so it reads over any sprite:
+
+    python3 asset-work/make_status_icons.py
+"""
+
+from make_sheets import ASSETS, CLEAR, write_png
+
+SIZE = 8
+OUTLINE = (20, 20, 20, 200)
+
+ICONS = [
+    # slow
+    (
+        (90, 150, 255),
+        [
+            "........",
+            ".#....#.",
+            "..#..#..",
+            "...##...",
+            ".#....#.",
+            "..#..#..",
+            "...##...",
+            "........",
+        ],
+    ),
+    # stun
+    (
+        (255, 230, 60),
+        [
+            "....#...",
+            "...##...",
+            "..##....",
+            ".######.",
+            "....##..",
+            "...##...",
+            "...#....",
+            "........",
+        ],
+    ),
+    # burn
+    (
+        (255, 130, 30),
+        [
+            "...#....",
+            "...##...",
+            "..###.#.",
+            ".#####..",
+            ".######.",
+            ".######.",
+            "..####..",
+            "........",
+        ],
+    ),
+    # poison
+    (
+        (110, 220, 70),
+        [
+            "...#....",
+            "...#....",
+            "..###...",
+            ".#####..",
+            ".#####..",
+            ".#####..",
+            "..###...",
+            "........",
+        ],
+    ),
+    # shield
+    (
+        (80, 230, 240),
+        [
+            ".######.",
+            ".######.",
+            ".######.",
+            ".######.",
+            "..####..",
+            "..####..",
+            "...##...",
+            "........",
+        ],
+    ),
+    # haste
+    (
+        (240, 240, 240),
+        [
+            "...##...",
+            "..#..#..",
+            ".#....#.",
+            "...##...",
+            "..#..#..",
+            ".#....#.",
+            "........",
+            "........",
+        ],
+    ),
+]
+
+
+def icon(color, rows):
+    def filled(x, y):
+        return 0 <= x < SIZE and 0 <= y < SIZE and rows[y][x] == "#"
+
+    def pixel(x, y):
+        if filled(x, y):
+            return color + (255,)
+        near = any(filled(x + dx, y + dy) for dy in (-1, 0, 1) for dx in (-1, 0, 1))
+        return OUTLINE if near else CLEAR
+
+    return [[pixel(x, y) for x in range(SIZE)] for y in range(SIZE)]
+
+
+if __name__ == "__main__":
+    images = [icon(color, rows) for color, rows in ICONS]
+    pixels = [sum((image[y] for image in images), []) for y in range(SIZE)]
+    write_png(ASSETS / "status_icons.png", pixels)
+    print(f"status_icons.png: {len(images)} icons")
@@ -13,14 +13,19 @@ use bevy_rapier2d::{
 };
 
 use crate::{
-    draggable::{drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag},
+    draggable::{
+        drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag,
+        TOOL_SENSOR_RADIUS,
+    },
     game::GameState,
+    gameplay_events::{ToolKind, ToolPlaced, ToolRemoved},
     inventory::Inventory,
     levels::{LevelLoadedEvent, LevelSize, NoPlacingHere},
     load::TextureAssets,
     mouse::{ClickSensor, ClickSensorEvent, Drag, DragDropConfirm, DragDropRequest, DragPos},
     physics::{coll_groups, ObjectGroup, Team},
-    robot::{EngineDir, Robot},
+    robot::{EngineDir, LastRedirect, Robot},
+    sprite_animation::{SpriteAnimation, TOOL_IDLE},
 };
 
 pub struct ArrowPlugin;
@@ -69,7 +74,7 @@ pub struct DraggedArrow;
 
 #[derive(Component)]
 pub struct Arrow {
-    dir: Vec2,
+    pub dir: Vec2,
 }
 
 #[derive(Component)]
@@ -106,22 +111,23 @@ fn fixup_enemy_arrow(
     }
 }
 
-fn spawn_arrow(
+pub fn spawn_arrow(
     cmd: &mut Commands,
     tr: Transform,
     dir: Vec2,
     team: Team,
-    texture: Option<Handle<Image>>,
+    texture: Option<Handle<TextureAtlas>>,
     grid_coords: GridCoords,
 ) -> Entity {
     let arrow_entity = cmd.spawn((Arrow { dir }, team, grid_coords)).id();
     if team == Team::Player {
         cmd.entity(arrow_entity).insert((
-            SpriteBundle {
-                texture: texture.unwrap(),
+            SpriteSheetBundle {
+                texture_atlas: texture.unwrap(),
                 transform: tr,
                 ..Default::default()
             },
+            SpriteAnimation::new(TOOL_IDLE),
             NoPlacingHere,
         ));
     } else {
@@ -141,7 +147,7 @@ fn spawn_arrow(
         cmd.spawn((
             ArrowRobotSensor,
             Sensor,
-            Collider::ball(96.),
+            Collider::ball(TOOL_SENSOR_RADIUS),
             match team {
                 Team::Player => {
                     coll_groups(ObjectGroup::PLAYER_ARROW_SENSOR, ObjectGroup::PLAYER_ROBOT)
@@ -172,6 +178,7 @@ fn drop_request(
     assets: Res<TextureAssets>,
     mut inventory: ResMut<Inventory>,
     q_level: Query<(Entity, &GlobalTransform), With<LevelIid>>,
+    mut ev_tool_placed: EventWriter<ToolPlaced>,
 ) {
     for (entity, drag_tr, maybe_valid, mut drag_state, grid_coords) in &mut q_drag {
         if maybe_valid.is_some() {
@@ -195,6 +202,12 @@ fn drop_request(
                         *grid_coords,
                     );
                     cmd.entity(level_entity).add_child(arrow);
+                    ev_tool_placed.send(ToolPlaced {
+                        tool: arrow,
+                        kind: ToolKind::Arrow,
+                        team: Team::Player,
+                        grid_coords: *grid_coords,
+                    });
                 }
             }
         } else {
@@ -207,11 +220,12 @@ fn check_click(
     mut cmd: Commands,
     mut ev_click_sensor: EventReader<ClickSensorEvent>,
     q_sensor: Query<&Parent, With<ArrowClickSensor>>,
-    q_arrow: Query<(Entity, &GlobalTransform, &Team), With<Arrow>>,
+    q_arrow: Query<(Entity, &GlobalTransform, &Team, &GridCoords), With<Arrow>>,
     mut inventory: ResMut<Inventory>,
+    mut ev_tool_removed: EventWriter<ToolRemoved>,
 ) {
     for ClickSensorEvent(sensor_entity) in ev_click_sensor.read() {
-        if let Ok((arrow_entity, arrow_gtr, team)) = q_sensor
+        if let Ok((arrow_entity, arrow_gtr, team, grid_coords)) = q_sensor
             .get(*sensor_entity)
             .map(|parent| parent.get())
             .and_then(|arrow_entity| q_arrow.get(arrow_entity))
@@ -224,6 +238,11 @@ fn check_click(
                     DraggedArrow,
                 ));
                 cmd.entity(arrow_entity).despawn_recursive();
+                ev_tool_removed.send(ToolRemoved {
+                    kind: ToolKind::Arrow,
+                    team: *team,
+                    grid_coords: *grid_coords,
+                });
             }
         }
     }
@@ -233,7 +252,7 @@ fn update_robot_motors(
     mut collision_events: EventReader<CollisionEvent>,
     q_robot_sensor: Query<&Parent, With<ArrowRobotSensor>>,
     q_arrow: Query<(&Arrow, &Team)>,
-    mut q_robot: Query<(&mut EngineDir, &Team), With<Robot>>,
+    mut q_robot: Query<(&mut EngineDir, &mut LastRedirect, &Team), With<Robot>>,
 ) {
     for ev in collision_events.read() {
         if let CollisionEvent::Started(e1, e2, _) = ev {
@@ -243,14 +262,15 @@ fn update_robot_motors(
                 (false, true) => (*e2, *e1),
                 _ => continue,
             };
-            if let (Ok((mut engine_dir, robot_team)), Ok((arrow, arrow_team))) = (
-                q_robot.get_mut(other),
-                q_robot_sensor
-                    .get(sensor)
-                    .and_then(|parent| q_arrow.get(parent.get())),
-            ) {
+            let Ok(e_arrow) = q_robot_sensor.get(sensor).map(|parent| parent.get()) else {
+                continue;
+            };
+            if let (Ok((mut engine_dir, mut last_redirect, robot_team)), Ok((arrow, arrow_team))) =
+                (q_robot.get_mut(other), q_arrow.get(e_arrow))
+            {
                 if robot_team == arrow_team {
                     engine_dir.0 = arrow.dir;
+                    last_redirect.0 = Some((ToolKind::Arrow, e_arrow));
                 }
             }
         }
@@ -0,0 +1,332 @@
+use bevy::{audio::Volume, prelude::*};
+use bevy_ecs_ldtk::LevelIid;
+
+use crate::{
+    game::{GameState, LevelState},
+    game_camera::MainCamera,
+    gameplay_events::{
+        BulletHit, HitSource, PortalDestroyed, RobotKilled, RobotSpawned, ToolPlaced, ToolRemoved,
+    },
+    load::AudioAssets,
+    storage,
+    weapon::FireEvent,
+};
+
+const MAX_VOICES_PER_FRAME: usize = 12;
+const MIN_AUDIBLE_VOLUME: f32 = 0.02;
+const SFX_FALLOFF_DISTANCE: f32 = 200.;
+const DEFAULT_MUSIC_VOLUME: f32 = 0.4;
+const DEFAULT_SFX_VOLUME: f32 = 0.8;
+const AUDIO_SETTINGS_FILE: &str = "audio_settings.txt";
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioSettings::load())
+            .init_resource::<SfxQueue>()
+            .add_systems(
+                Update,
+                (
+                    queue_fire_sfx,
+                    queue_bullet_hit_sfx,
+                    queue_robot_killed_sfx,
+                    queue_portal_destroyed_sfx,
+                    queue_robot_spawned_sfx,
+                    queue_tool_sfx,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(LevelState::Win), queue_jingle(Sfx::Win))
+            .add_systems(OnEnter(LevelState::Loss), queue_jingle(Sfx::Loss))
+            .add_systems(
+                PostUpdate,
+                (
+                    play_queued_sfx,
+                    change_music.run_if(state_changed::<GameState>()),
+                    apply_music_volume,
+                    save_audio_settings,
+                ),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct AudioSettings {
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            music_volume: DEFAULT_MUSIC_VOLUME,
+            sfx_volume: DEFAULT_SFX_VOLUME,
+        }
+    }
+}
+
+// One setting per line:
+//   music <volume>
+//   sfx <volume>
+impl AudioSettings {
+    fn load() -> Self {
+        let mut settings = Self::default();
+        for line in storage::read(AUDIO_SETTINGS_FILE)
+            .unwrap_or_default()
+            .lines()
+        {
+            let words: Vec<_> = line.split_whitespace().collect();
+            let (name, volume) = match words[..] {
+                [name, volume] => (name, volume.parse::<f32>()),
+                _ => continue,
+            };
+            let Ok(volume) = volume else {
+                continue;
+            };
+            match name {
+                "music" => settings.music_volume = volume.clamp(0., 1.),
+                "sfx" => settings.sfx_volume = volume.clamp(0., 1.),
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        format!("music {}\nsfx {}\n", self.music_volume, self.sfx_volume)
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Sfx {
+    Fire,
+    Hit,
+    Explosion,
+    BigExplosion,
+    PortalSpawn,
+    ToolPlace,
+    ToolPickup,
+    Win,
+    Loss,
+}
+
+impl Sfx {
+    fn voice_limit(self) -> usize {
+        match self {
+            Sfx::Fire | Sfx::Hit | Sfx::Explosion => 4,
+            _ => 2,
+        }
+    }
+
+    fn volume(self) -> f32 {
+        match self {
+            Sfx::Fire => 0.3,
+            Sfx::Hit => 0.4,
+            _ => 1.0,
+        }
+    }
+
+    fn handle(self, assets: &AudioAssets) -> Handle<AudioSource> {
+        match self {
+            Sfx::Fire => assets.fire.clone(),
+            Sfx::Hit => assets.hit.clone(),
+            Sfx::Explosion => assets.explosion.clone(),
+            Sfx::BigExplosion => assets.big_explosion.clone(),
+            Sfx::PortalSpawn => assets.portal_spawn.clone(),
+            Sfx::ToolPlace => assets.tool_place.clone(),
+            Sfx::ToolPickup => assets.tool_pickup.clone(),
+            Sfx::Win => assets.win.clone(),
+            Sfx::Loss => assets.loss.clone(),
+        }
+    }
+}
+
+struct SfxRequest {
+    sfx: Sfx,
+    // world position, none for sounds that are not positional
+    position: Option<Vec2>,
+}
+
+#[derive(Resource, Default)]
+pub struct SfxQueue(Vec<SfxRequest>);
+
+impl SfxQueue {
+    pub fn push(&mut self, sfx: Sfx, position: Option<Vec2>) {
+        self.0.push(SfxRequest { sfx, position });
+    }
+}
+
+#[derive(Component)]
+struct Music;
+
+fn level_offset(q_level: &Query<&GlobalTransform, With<LevelIid>>) -> Vec2 {
+    q_level
+        .get_single()
+        .map(|gtr| gtr.translation().truncate())
+        .unwrap_or_default()
+}
+
+fn queue_fire_sfx(
+    mut ev_fire: EventReader<FireEvent>,
+    mut queue: ResMut<SfxQueue>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+) {
+    let offset = level_offset(&q_level);
+    for ev in ev_fire.read() {
+        queue.push(Sfx::Fire, Some(ev.from_pos.truncate() + offset));
+    }
+}
+
+fn queue_bullet_hit_sfx(
+    mut ev_bullet_hit: EventReader<BulletHit>,
+    mut queue: ResMut<SfxQueue>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+) {
+    let offset = level_offset(&q_level);
+    // the explosion is already heard, one hit per robot caught in it would drown it
+    for ev in ev_bullet_hit.read() {
+        if ev.source != HitSource::Splash {
+            queue.push(Sfx::Hit, Some(ev.position + offset));
+        }
+    }
+}
+
+fn queue_robot_killed_sfx(
+    mut ev_robot_killed: EventReader<RobotKilled>,
+    mut queue: ResMut<SfxQueue>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+) {
+    let offset = level_offset(&q_level);
+    for ev in ev_robot_killed.read() {
+        queue.push(Sfx::Explosion, Some(ev.position + offset));
+    }
+}
+
+fn queue_portal_destroyed_sfx(
+    mut ev_portal_destroyed: EventReader<PortalDestroyed>,
+    mut queue: ResMut<SfxQueue>,
+) {
+    for _ev in ev_portal_destroyed.read() {
+        queue.push(Sfx::BigExplosion, None);
+    }
+}
+
+fn queue_robot_spawned_sfx(
+    mut ev_robot_spawned: EventReader<RobotSpawned>,
+    mut queue: ResMut<SfxQueue>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+) {
+    let offset = level_offset(&q_level);
+    for ev in ev_robot_spawned.read() {
+        queue.push(Sfx::PortalSpawn, Some(ev.position + offset));
+    }
+}
+
+fn queue_tool_sfx(
+    mut ev_tool_placed: EventReader<ToolPlaced>,
+    mut ev_tool_removed: EventReader<ToolRemoved>,
+    mut queue: ResMut<SfxQueue>,
+) {
+    for _ev in ev_tool_placed.read() {
+        queue.push(Sfx::ToolPlace, None);
+    }
+    for _ev in ev_tool_removed.read() {
+        queue.push(Sfx::ToolPickup, None);
+    }
+}
+
+fn queue_jingle(sfx: Sfx) -> impl Fn(ResMut<SfxQueue>) {
+    move |mut queue: ResMut<SfxQueue>| queue.push(sfx, None)
+}
+
+fn play_queued_sfx(
+    mut cmd: Commands,
+    mut queue: ResMut<SfxQueue>,
+    settings: Res<AudioSettings>,
+    assets: Option<Res<AudioAssets>>,
+    q_camera: Query<&GlobalTransform, With<MainCamera>>,
+) {
+    let Some(assets) = assets else {
+        queue.0.clear();
+        return;
+    };
+    let camera_pos = q_camera
+        .get_single()
+        .map(|gtr| gtr.translation().truncate())
+        .unwrap_or_default();
+    let mut voices: Vec<(Sfx, f32)> = queue
+        .0
+        .drain(..)
+        .map(|request| {
+            let falloff = request.position.map_or(1.0, |pos| {
+                let d = pos.distance(camera_pos) / SFX_FALLOFF_DISTANCE;
+                1.0 / (1.0 + d * d)
+            });
+            (
+                request.sfx,
+                request.sfx.volume() * falloff * settings.sfx_volume,
+            )
+        })
+        .filter(|(_, volume)| *volume >= MIN_AUDIBLE_VOLUME)
+        .collect();
+    voices.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let mut played: Vec<Sfx> = Vec::with_capacity(MAX_VOICES_PER_FRAME);
+    for (sfx, volume) in voices {
+        if played.len() >= MAX_VOICES_PER_FRAME {
+            break;
+        }
+        if played.iter().filter(|other| **other == sfx).count() >= sfx.voice_limit() {
+            continue;
+        }
+        played.push(sfx);
+        cmd.spawn(AudioBundle {
+            source: sfx.handle(&assets),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+        });
+    }
+}
+
+fn change_music(
+    mut cmd: Commands,
+    game_state: Res<State<GameState>>,
+    settings: Res<AudioSettings>,
+    assets: Option<Res<AudioAssets>>,
+    q_music: Query<Entity, With<Music>>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+    let track = match game_state.get() {
+        GameState::Menu => assets.music_menu.clone(),
+        GameState::Playing => assets.music_playing.clone(),
+        _ => return,
+    };
+    for entity in &q_music {
+        cmd.entity(entity).despawn_recursive();
+    }
+    cmd.spawn((
+        Music,
+        AudioBundle {
+            source: track,
+            settings: PlaybackSettings::LOOP
+                .with_volume(Volume::new_relative(settings.music_volume)),
+        },
+    ));
+}
+
+fn save_audio_settings(settings: Res<AudioSettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        if let Err(err) = storage::write(AUDIO_SETTINGS_FILE, &settings.to_text()) {
+            warn!("could not save audio settings: {}", err);
+        }
+    }
+}
+
+fn apply_music_volume(settings: Res<AudioSettings>, q_music: Query<&AudioSink, With<Music>>) {
+    if settings.is_changed() {
+        for sink in &q_music {
+            sink.set_volume(settings.music_volume);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::LevelIid;
+
+use crate::{
+    console::{Console, ConsoleCommand},
+    game::GameState,
+    levels::LevelSize,
+    physics::Team,
+    robot::{Robot, SpawnRobotEvent},
+    storage,
+};
+
+// every run is appended, to compare builds before and after a change
+const BENCHMARK_FILE: &str = "benchmarks.txt";
+
+// frames right after spawning are skewed by the spawn itself
+const WARMUP: Duration = Duration::from_secs(1);
+const MEASURE: Duration = Duration::from_secs(5);
+const BENCH_SPACING: f32 = 12.;
+
+pub struct BenchmarkPlugin;
+
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Benchmark::default()).add_systems(
+            Update,
+            (start_benchmark, measure_frames)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Frame time measurement started with the `bench <robots>` console command.
+#[derive(Resource, Default)]
+struct Benchmark(Option<BenchmarkRun>);
+
+struct BenchmarkRun {
+    robots: u32,
+    elapsed: Duration,
+    frames: u32,
+    total: Duration,
+    worst: Duration,
+}
+
+fn start_benchmark(
+    mut ev_command: EventReader<ConsoleCommand>,
+    mut benchmark: ResMut<Benchmark>,
+    mut console: ResMut<Console>,
+    mut ev_spawn_robot: EventWriter<SpawnRobotEvent>,
+    q_level: Query<(), With<LevelIid>>,
+    level_size: Res<LevelSize>,
+) {
+    for command in ev_command.read() {
+        let ConsoleCommand::Bench(robots) = command else {
+            continue;
+        };
+        let (Ok(_), Some(size_info)) = (q_level.get_single(), level_size.0) else {
+            console.log("no level loaded");
+            continue;
+        };
+        // a single team so robots don't destroy each other during the run
+        let center = size_info.pixel_size().as_vec2() / 2.;
+        for i in 0..*robots {
+            let offset = Vec2::from_angle(i as f32 * 2.4) * (i as f32).sqrt() * BENCH_SPACING;
+            ev_spawn_robot.send(SpawnRobotEvent {
+                pos: center + offset,
+                dir: Vec2::X,
+                team: Team::Player,
+                weapons: vec![],
+            });
+        }
+        benchmark.0 = Some(BenchmarkRun {
+            robots: *robots,
+            elapsed: Duration::ZERO,
+            frames: 0,
+            total: Duration::ZERO,
+            worst: Duration::ZERO,
+        });
+        console.log(format!(
+            "benchmarking {} robots for {}s",
+            robots,
+            (WARMUP + MEASURE).as_secs()
+        ));
+    }
+}
+
+fn measure_frames(
+    mut benchmark: ResMut<Benchmark>,
+    mut console: ResMut<Console>,
+    time: Res<Time<Real>>,
+    q_robot: Query<(), With<Robot>>,
+) {
+    let Some(run) = benchmark.0.as_mut() else {
+        return;
+    };
+    run.elapsed += time.delta();
+    if run.elapsed < WARMUP {
+        return;
+    }
+    run.frames += 1;
+    run.total += time.delta();
+    run.worst = run.worst.max(time.delta());
+    if run.elapsed < WARMUP + MEASURE {
+        return;
+    }
+    let average = run.total.as_secs_f64() * 1000. / run.frames as f64;
+    let report = format!(
+        "bench {} robots ({} alive): {:.2} ms/frame average, {:.2} ms worst, {} frames",
+        run.robots,
+        q_robot.iter().count(),
+        average,
+        run.worst.as_secs_f64() * 1000.,
+        run.frames
+    );
+    info!("{}", report);
+    let results = storage::read(BENCHMARK_FILE).unwrap_or_default() + &report + "\n";
+    if let Err(err) = storage::write(BENCHMARK_FILE, &results) {
+        warn!("could not save benchmark results: {}", err);
+    }
+    console.log(report);
+    benchmark.0 = None;
+}
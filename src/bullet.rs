@@ -1,19 +1,20 @@
 use std::time::Duration;
 
-use bevy::{
-    math::{vec2, vec3},
-    prelude::*,
-};
+use bevy::{math::vec2, prelude::*};
 use bevy_ecs_ldtk::LevelIid;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    explosion::ExplosionEvent,
+    explosion::{ExplosionEvent, Falloff, SplashDamage, BULLET_IMPACT_EXPLOSION, SHELL_EXPLOSION},
     game::GameState,
-    hp::Life,
+    gameplay_events::{BulletHit, HitSource, PortalDamaged},
+    hp::{LastHitBy, Life},
     load::TextureAssets,
     physics::{coll_groups, ObjectGroup, Team},
-    robot::FireEvent,
+    pool::{self, Pooled},
+    portal::Portal,
+    status_effect::{deal_damage, ApplyStatusEvent, StatusEffect, StatusEffects},
+    weapon::{FireEvent, Weapon, WeaponKind},
 };
 
 pub struct BulletPlugin;
@@ -28,14 +29,63 @@ impl Plugin for BulletPlugin {
     }
 }
 
-const BULLET_LIFETIME: Duration = Duration::from_millis(2000);
-const BULLET_SPEED: f32 = 4.;
+pub const BULLET_LIFETIME: Duration = Duration::from_millis(2000);
 
 #[derive(Component)]
 pub struct Bullet {
-    timer: Timer,
-    direction: Vec2,
-    angle: f32,
+    pub timer: Timer,
+    pub direction: Vec2,
+    pub angle: f32,
+    pub shooter: Entity,
+    pub weapon: &'static Weapon,
+    /// Given to what the bullet hits.
+    pub effect: Option<StatusEffect>,
+}
+
+impl Bullet {
+    /// Shells explode when `lifetime` ends, other bullets just disappear.
+    pub fn new(angle: f32, shooter: Entity, weapon: &'static Weapon, lifetime: Duration) -> Self {
+        Self {
+            timer: Timer::new(lifetime, TimerMode::Once),
+            direction: Vec2::from_angle(angle),
+            angle,
+            shooter,
+            weapon,
+            effect: weapon.effect,
+        }
+    }
+}
+
+/// Groups of a query that hits robots and portals of the team `team` is shooting at.
+pub fn bullet_groups(team: Team) -> CollisionGroups {
+    match team {
+        Team::Player => coll_groups(
+            ObjectGroup::PLAYER_BULLET,
+            ObjectGroup::ENEMY_ROBOT | ObjectGroup::ENEMY_PORTAL,
+        ),
+        Team::Enemy => coll_groups(
+            ObjectGroup::ENEMY_BULLET,
+            ObjectGroup::PLAYER_ROBOT | ObjectGroup::PLAYER_PORTAL,
+        ),
+    }
+}
+
+/// Bullet sprite at `pos` in level coordinates, to be parented to the level.
+pub fn bullet_bundle(assets: &TextureAssets, bullet: Bullet, pos: Vec2, team: Team) -> impl Bundle {
+    let quat = Quat::from_rotation_z(bullet.angle);
+    (
+        SpriteBundle {
+            texture: assets.bullet.clone(),
+            transform: Transform::from_scale(Vec3::splat(0.5))
+                .with_rotation(quat)
+                .with_translation(pos.extend(3.)),
+            ..Default::default()
+        },
+        bullet,
+        // Collider::capsule_x(6.0, 2.0),
+        // Sensor,
+        team,
+    )
 }
 
 fn spawn_bullet(
@@ -43,47 +93,67 @@ fn spawn_bullet(
     assets: Res<TextureAssets>,
     mut ev_fire: EventReader<FireEvent>,
     q_level: Query<Entity, With<LevelIid>>,
+    mut q_free: Query<(Entity, &mut Bullet, &mut Transform, &mut Team), With<Pooled>>,
 ) {
+    let mut free = q_free.iter_mut();
     for ev in ev_fire.read() {
+        if ev.weapon.kind == WeaponKind::Beam {
+            continue;
+        }
         if let Ok(e_level) = q_level.get_single() {
-            let angle =
-                vec2(1.0, 0.0).angle_between(ev.to_target.truncate() - ev.from_pos.truncate());
-            let quat = Quat::from_rotation_z(angle);
-            let e_bullet = cmd
-                .spawn((
-                    Bullet {
-                        timer: Timer::new(BULLET_LIFETIME, TimerMode::Once),
-                        direction: quat.mul_vec3(vec3(1.0, 0.0, 0.0)).truncate(),
-                        angle,
-                    },
-                    SpriteBundle {
-                        texture: assets.bullet.clone(),
-                        transform: Transform::from_scale(Vec3::splat(0.5))
-                            .with_rotation(quat)
-                            .with_translation(vec3(ev.from_pos.x, ev.from_pos.y, 3.)),
-                        ..Default::default()
-                    },
-                    // Collider::capsule_x(6.0, 2.0),
-                    // Sensor,
-                    ev.team,
-                ))
-                .id();
-            cmd.entity(e_level).add_child(e_bullet);
+            let to_target = ev.to_target.truncate() - ev.from_pos.truncate();
+            let angle = vec2(1.0, 0.0).angle_between(to_target);
+            // shells come down where the target was
+            let lifetime = match ev.weapon.kind {
+                WeaponKind::Shell => {
+                    Duration::from_secs_f32(to_target.length() / ev.weapon.projectile_speed)
+                }
+                _ => BULLET_LIFETIME,
+            };
+            let bullet = Bullet::new(angle, ev.shooter, ev.weapon, lifetime);
+            let pos = ev.from_pos.truncate();
+            if let Some((e_bullet, mut pooled_bullet, mut tr, mut team)) = free.next() {
+                *pooled_bullet = bullet;
+                *team = ev.team;
+                tr.translation = pos.extend(3.);
+                tr.rotation = Quat::from_rotation_z(angle);
+                pool::reuse(&mut cmd, e_bullet);
+            } else {
+                let e_bullet = cmd.spawn(bullet_bundle(&assets, bullet, pos, ev.team)).id();
+                cmd.entity(e_level).add_child(e_bullet);
+            }
         }
     }
 }
 
 fn move_bullet(
     mut cmd: Commands,
-    mut q_bullet: Query<(Entity, &mut Bullet, &mut Transform)>,
+    mut q_bullet: Query<(Entity, &mut Bullet, &mut Transform, &Team), Without<Pooled>>,
+    mut ev_explosion: EventWriter<ExplosionEvent>,
     time: Res<Time>,
 ) {
-    for (e_bullet, mut bullet, mut bullet_tr) in &mut q_bullet {
+    for (e_bullet, mut bullet, mut bullet_tr, team) in &mut q_bullet {
         bullet.timer.tick(time.delta());
         if bullet.timer.finished() {
-            cmd.entity(e_bullet).despawn_recursive();
+            pool::release(&mut cmd, e_bullet);
+            if bullet.weapon.kind == WeaponKind::Shell {
+                ev_explosion.send(ExplosionEvent {
+                    damage: Some(SplashDamage {
+                        damage: bullet.weapon.damage,
+                        radius: bullet.weapon.splash_radius,
+                        falloff: Falloff::Linear,
+                        team: *team,
+                        friendly_fire: false,
+                        source: bullet.shooter,
+                        effect: bullet.effect,
+                    }),
+                    ..ExplosionEvent::new(SHELL_EXPLOSION, bullet_tr.translation.truncate())
+                });
+            }
         } else {
-            bullet_tr.translation += (bullet.direction * BULLET_SPEED).extend(0.0);
+            bullet_tr.translation +=
+                (bullet.direction * bullet.weapon.projectile_speed * time.delta_seconds())
+                    .extend(0.0);
         }
     }
 }
@@ -91,23 +161,21 @@ fn move_bullet(
 fn check_bullet_hit(
     mut cmd: Commands,
     rapier_context: Res<RapierContext>,
-    q_bullet: Query<(Entity, &Bullet, &GlobalTransform, &Transform, &Team)>,
-    mut q_other: Query<(&mut Life, &Team)>,
+    q_bullet: Query<(Entity, &Bullet, &GlobalTransform, &Transform, &Team), Without<Pooled>>,
+    mut q_other: Query<(&mut Life, Option<&mut StatusEffects>, &Team, Has<Portal>)>,
     mut ev_explosion: EventWriter<ExplosionEvent>,
+    mut ev_portal_damaged: EventWriter<PortalDamaged>,
+    mut ev_bullet_hit: EventWriter<BulletHit>,
+    mut ev_apply_status: EventWriter<ApplyStatusEvent>,
 ) {
     let shape = Collider::capsule_x(6.0, 2.0);
     for (e_bullet, bullet, bullet_gtr, bullet_tr, bullet_team) in &q_bullet {
+        // shells fly over everything
+        if bullet.weapon.kind == WeaponKind::Shell {
+            continue;
+        }
         let filter = QueryFilter {
-            groups: Some(match *bullet_team {
-                Team::Player => coll_groups(
-                    ObjectGroup::PLAYER_BULLET,
-                    ObjectGroup::ENEMY_ROBOT | ObjectGroup::ENEMY_PORTAL,
-                ),
-                Team::Enemy => coll_groups(
-                    ObjectGroup::ENEMY_BULLET,
-                    ObjectGroup::PLAYER_ROBOT | ObjectGroup::PLAYER_PORTAL,
-                ),
-            }),
+            groups: Some(bullet_groups(*bullet_team)),
             ..Default::default()
         };
         rapier_context.intersections_with_shape(
@@ -117,17 +185,42 @@ fn check_bullet_hit(
             filter,
             |other| {
                 let mut result = true;
-                if let Ok((mut life, team)) = q_other.get_mut(other) {
+                if let Ok((mut life, effects, team, is_portal)) = q_other.get_mut(other) {
                     if team != bullet_team {
-                        cmd.entity(e_bullet).despawn_recursive();
-                        life.curr_hp -= 1.0;
+                        pool::release(&mut cmd, e_bullet);
+                        let damage = deal_damage(&mut life, effects, bullet.weapon.damage);
+                        cmd.entity(other).try_insert(LastHitBy {
+                            shooter: bullet.shooter,
+                            team: *bullet_team,
+                        });
+                        if let Some(effect) = bullet.effect {
+                            ev_apply_status.send(ApplyStatusEvent {
+                                target: other,
+                                effect,
+                            });
+                        }
+                        ev_bullet_hit.send(BulletHit {
+                            target: other,
+                            shooter: bullet.shooter,
+                            damage,
+                            position: bullet_tr.translation.truncate(),
+                            source: HitSource::Bullet,
+                        });
+                        if is_portal {
+                            ev_portal_damaged.send(PortalDamaged {
+                                portal: other,
+                                team: *team,
+                                damage,
+                                remaining_hp: life.curr_hp,
+                                shooter: bullet.shooter,
+                            });
+                        }
                         ev_explosion.send(ExplosionEvent {
-                            location: bullet_tr.translation.truncate(),
-                            particle_radius: 2.,
-                            spread: 3.,
-                            particle_speed: bullet.direction / 2.,
-                            duration: Duration::from_millis(100),
-                            ..Default::default()
+                            direction: bullet.direction,
+                            ..ExplosionEvent::new(
+                                BULLET_IMPACT_EXPLOSION,
+                                bullet_tr.translation.truncate(),
+                            )
                         });
                         result = false;
                         //println!("yoh");
@@ -0,0 +1,260 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{
+    console::{Console, ConsoleCommand},
+    game::GameState,
+    game_camera::{move_camera, MainCamera},
+    gameplay_events::{PortalDamaged, PortalDestroyed, RobotKilled},
+    storage,
+};
+
+// trauma lost per second
+const TRAUMA_DECAY: f32 = 1.2;
+// camera offset at full trauma, in screen pixels
+const MAX_SHAKE_OFFSET: f32 = 24.;
+const SHAKE_FREQUENCY: f32 = 25.;
+const ROBOT_KILLED_TRAUMA: f32 = 0.15;
+const PORTAL_DAMAGED_TRAUMA: f32 = 0.05;
+const PORTAL_DESTROYED_TRAUMA: f32 = 0.8;
+// real seconds the game runs at HIT_STOP_SPEED after a portal is destroyed
+const PORTAL_DESTROYED_HIT_STOP: f32 = 0.35;
+const HIT_STOP_SPEED: f32 = 0.1;
+const MOTION_SETTINGS_FILE: &str = "motion_settings.txt";
+
+pub struct CameraEffectsPlugin;
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraEffectsSettings::load())
+            .insert_resource(CameraShake::default())
+            .insert_resource(HitStop::default())
+            .add_systems(
+                Update,
+                (add_trauma, toggle_reduced_motion).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    remove_shake.before(move_camera),
+                    apply_shake
+                        .after(move_camera)
+                        .before(TransformSystem::TransformPropagate),
+                    update_hit_stop,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Update, set_time_scale)
+            .add_systems(PostUpdate, save_camera_effects_settings)
+            .add_systems(OnExit(GameState::Playing), clear_effects);
+    }
+}
+
+/// With `reduced_motion` set there is no screen shake and no hit-stop.
+#[derive(Resource, Default)]
+pub struct CameraEffectsSettings {
+    pub reduced_motion: bool,
+}
+
+// One setting per line:
+//   motion <full|reduced>
+impl CameraEffectsSettings {
+    fn load() -> Self {
+        let mut settings = Self::default();
+        for line in storage::read(MOTION_SETTINGS_FILE)
+            .unwrap_or_default()
+            .lines()
+        {
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["motion", "full"] => settings.reduced_motion = false,
+                ["motion", "reduced"] => settings.reduced_motion = true,
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "motion {}\n",
+            if self.reduced_motion {
+                "reduced"
+            } else {
+                "full"
+            }
+        )
+    }
+}
+
+/// Trauma adds up from gameplay events and decays over time. The shake grows with its square,
+/// so small hits barely move the camera and big ones stand out.
+#[derive(Resource, Default)]
+pub struct CameraShake {
+    pub trauma: f32,
+    // offset currently added to the camera translation
+    applied: Vec3,
+    elapsed: f32,
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+}
+
+/// Game time slowed down for a moment on top of the time scale set from the console.
+#[derive(Resource)]
+pub struct HitStop {
+    remaining: f32,
+    time_scale: f32,
+}
+
+impl Default for HitStop {
+    fn default() -> Self {
+        Self {
+            remaining: 0.,
+            time_scale: 1.,
+        }
+    }
+}
+
+impl HitStop {
+    pub fn start(&mut self, duration: f32, time: &mut Time<Virtual>) {
+        self.remaining = self.remaining.max(duration);
+        time.set_relative_speed(self.speed());
+    }
+
+    fn speed(&self) -> f32 {
+        if self.remaining > 0. {
+            self.time_scale * HIT_STOP_SPEED
+        } else {
+            self.time_scale
+        }
+    }
+}
+
+fn add_trauma(
+    mut shake: ResMut<CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    settings: Res<CameraEffectsSettings>,
+    mut ev_robot_killed: EventReader<RobotKilled>,
+    mut ev_portal_damaged: EventReader<PortalDamaged>,
+    mut ev_portal_destroyed: EventReader<PortalDestroyed>,
+) {
+    let mut trauma = ev_robot_killed.read().count() as f32 * ROBOT_KILLED_TRAUMA
+        + ev_portal_damaged.read().count() as f32 * PORTAL_DAMAGED_TRAUMA;
+    let portals_destroyed = ev_portal_destroyed.read().count();
+    trauma += portals_destroyed as f32 * PORTAL_DESTROYED_TRAUMA;
+    if settings.reduced_motion {
+        return;
+    }
+    shake.add_trauma(trauma);
+    if portals_destroyed > 0 {
+        hit_stop.start(PORTAL_DESTROYED_HIT_STOP, &mut virtual_time);
+    }
+}
+
+fn toggle_reduced_motion(
+    mut ev_command: EventReader<ConsoleCommand>,
+    mut settings: ResMut<CameraEffectsSettings>,
+    mut console: ResMut<Console>,
+) {
+    for command in ev_command.read() {
+        if let ConsoleCommand::ReducedMotion(reduced) = command {
+            settings.reduced_motion = *reduced;
+            console.log(format!(
+                "reduced motion {}",
+                if *reduced { "on" } else { "off" }
+            ));
+        }
+    }
+}
+
+fn set_time_scale(
+    mut ev_command: EventReader<ConsoleCommand>,
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut console: ResMut<Console>,
+) {
+    for command in ev_command.read() {
+        if let ConsoleCommand::TimeScale(factor) = command {
+            hit_stop.time_scale = *factor;
+            virtual_time.set_relative_speed(hit_stop.speed());
+            console.log(format!("time scale set to {}", factor));
+        }
+    }
+}
+
+fn save_camera_effects_settings(settings: Res<CameraEffectsSettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        if let Err(err) = storage::write(MOTION_SETTINGS_FILE, &settings.to_text()) {
+            warn!("could not save motion settings: {}", err);
+        }
+    }
+}
+
+fn remove_shake(
+    mut shake: ResMut<CameraShake>,
+    mut q_camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    for mut tr in &mut q_camera {
+        tr.translation -= shake.applied;
+    }
+    shake.applied = Vec3::ZERO;
+}
+
+fn apply_shake(
+    mut shake: ResMut<CameraShake>,
+    mut q_camera: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    settings: Res<CameraEffectsSettings>,
+    time: Res<Time<Real>>,
+) {
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+    if settings.reduced_motion {
+        shake.trauma = 0.;
+    }
+    if shake.trauma == 0. {
+        return;
+    }
+    shake.elapsed += time.delta_seconds();
+    // sums of sines out of phase, smoother than random jumps
+    let t = shake.elapsed * SHAKE_FREQUENCY;
+    let noise = Vec2::new(
+        (t * 1.0).sin() * 0.6 + (t * 2.3 + 1.7).sin() * 0.4,
+        (t * 1.3 + 0.5).sin() * 0.6 + (t * 2.9 + 2.2).sin() * 0.4,
+    );
+    let strength = shake.trauma * shake.trauma * MAX_SHAKE_OFFSET;
+    for (mut tr, projection) in &mut q_camera {
+        let offset = (noise * strength * projection.scale).extend(0.);
+        tr.translation += offset;
+        shake.applied = offset;
+    }
+}
+
+fn update_hit_stop(
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    time: Res<Time<Real>>,
+) {
+    if hit_stop.remaining <= 0. {
+        return;
+    }
+    hit_stop.remaining -= time.delta_seconds();
+    if hit_stop.remaining <= 0. {
+        virtual_time.set_relative_speed(hit_stop.speed());
+    }
+}
+
+fn clear_effects(
+    mut shake: ResMut<CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut q_camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    for mut tr in &mut q_camera {
+        tr.translation -= shake.applied;
+    }
+    *shake = CameraShake::default();
+    hit_stop.remaining = 0.;
+    virtual_time.set_relative_speed(hit_stop.speed());
+}
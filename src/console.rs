@@ -0,0 +1,627 @@
+use std::str::FromStr;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_ecs_ldtk::{utils::grid_coords_to_translation, GridCoords, LevelIid};
+use bevy_inspector_egui::{
+    bevy_egui::{EguiContext, EguiContexts, EguiPlugin},
+    egui, DefaultInspectorConfigPlugin,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    controls::{Action, Actions, TextInputFocus},
+    game::{GameRng, GameState, LevelState},
+    game_camera::MouseWorldCoords,
+    game_ui::ChangeLevelEvent,
+    gameplay_events::ToolKind,
+    hp::Life,
+    inventory::Inventory,
+    levels::LevelSize,
+    physics::Team,
+    portal::Portal,
+    robot::SpawnRobotEvent,
+    savestate::{LoadStateEvent, SaveStateEvent, QUICKSAVE_FILE},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const STARTUP_SCRIPT: &str = "console_startup.txt";
+const MAX_LOG_LINES: usize = 200;
+// distance between robots spawned by the same command
+const SPAWN_SPACING: f32 = 6.;
+
+const USAGE: [&str; 15] = [
+    "spawn robot <player|enemy> [count] [at <x> <y>]",
+    "give <arrow|fork|grouper|defender> [count]",
+    "level <index>",
+    "win",
+    "lose",
+    "timescale <factor>",
+    "seed <number>",
+    "hp portal <player|enemy> <hp>",
+    "inspect [entity]",
+    "save [file]",
+    "load [file]",
+    "bench <robots>",
+    "motion <full|reduced>",
+    "aim <player|enemy> <accuracy 0..1>",
+    "help",
+];
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.add_plugins(DefaultInspectorConfigPlugin)
+            .add_event::<ConsoleCommand>()
+            .insert_resource(Console::default())
+            .insert_resource(InspectedEntity::default())
+            .add_systems(Startup, load_startup_script)
+            .add_systems(OnEnter(LevelState::Planning), run_startup_script)
+            .add_systems(
+                Update,
+                (toggle_console, console_ui, run_commands)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                inspector_ui
+                    .after(run_commands)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// A parsed console line. Send it directly to drive the game from code.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub enum ConsoleCommand {
+    /// `at` is in grid coordinates, the mouse position is used when it is missing.
+    SpawnRobots {
+        team: Team,
+        count: u32,
+        at: Option<IVec2>,
+    },
+    Give {
+        kind: ToolKind,
+        count: u32,
+    },
+    Level(usize),
+    Win,
+    Lose,
+    TimeScale(f32),
+    Seed(u64),
+    SetPortalHp {
+        team: Team,
+        hp: f32,
+    },
+    Inspect(Option<Entity>),
+    Save(String),
+    Load(String),
+    /// Spawns robots and reports the average frame time, see `benchmark`.
+    Bench(u32),
+    /// Turns screen shake and hit-stop off, see `camera_effects`.
+    ReducedMotion(bool),
+    /// How much robots of `team` lead moving targets, see `weapon::AimAccuracy`.
+    AimAccuracy {
+        team: Team,
+        accuracy: f32,
+    },
+    Help,
+}
+
+impl ConsoleCommand {
+    /// Commands that change how a level plays out, the level no longer counts for best scores.
+    pub fn is_cheat(&self) -> bool {
+        matches!(
+            self,
+            ConsoleCommand::SpawnRobots { .. }
+                | ConsoleCommand::Give { .. }
+                | ConsoleCommand::Win
+                | ConsoleCommand::SetPortalHp { .. }
+                | ConsoleCommand::Bench(_)
+                | ConsoleCommand::AimAccuracy { .. }
+        )
+    }
+}
+
+/// Opened with the backtick key. Release builds only get it when `allowed` is set.
+#[derive(Resource)]
+pub struct Console {
+    pub allowed: bool,
+    pub open: bool,
+    input: String,
+    log: Vec<String>,
+    startup: Vec<ConsoleCommand>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            allowed: cfg!(debug_assertions),
+            open: false,
+            input: String::new(),
+            log: vec![],
+            startup: vec![],
+        }
+    }
+}
+
+impl Console {
+    pub fn log(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        if self.log.len() > MAX_LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct InspectedEntity(pub Option<Entity>);
+
+pub fn parse_command(line: &str) -> Result<ConsoleCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["spawn", "robot", team, rest @ ..] => {
+            let team = parse_team(team)?;
+            let (count, rest) = match rest {
+                [count, rest @ ..] if *count != "at" => (parse(count, "count")?, rest),
+                _ => (1, rest),
+            };
+            let at = match rest {
+                [] => None,
+                ["at", x, y] => Some(IVec2::new(parse(x, "x")?, parse(y, "y")?)),
+                _ => return Err(format!("usage: {}", USAGE[0])),
+            };
+            Ok(ConsoleCommand::SpawnRobots { team, count, at })
+        }
+        ["give", kind, rest @ ..] => {
+            let kind = parse_tool(kind)?;
+            let count = match rest {
+                [] => 1,
+                [count] => parse(count, "count")?,
+                _ => return Err(format!("usage: {}", USAGE[1])),
+            };
+            Ok(ConsoleCommand::Give { kind, count })
+        }
+        ["level", index] => Ok(ConsoleCommand::Level(parse(index, "level index")?)),
+        ["win"] => Ok(ConsoleCommand::Win),
+        ["lose"] => Ok(ConsoleCommand::Lose),
+        ["timescale", factor] => {
+            let factor: f32 = parse(factor, "factor")?;
+            if factor.is_finite() && factor >= 0. {
+                Ok(ConsoleCommand::TimeScale(factor))
+            } else {
+                Err(format!("invalid factor: '{}'", factor))
+            }
+        }
+        ["seed", seed] => Ok(ConsoleCommand::Seed(parse(seed, "seed")?)),
+        ["hp", "portal", team, hp] => Ok(ConsoleCommand::SetPortalHp {
+            team: parse_team(team)?,
+            hp: parse(hp, "hp")?,
+        }),
+        ["inspect"] => Ok(ConsoleCommand::Inspect(None)),
+        ["inspect", entity] => Ok(ConsoleCommand::Inspect(Some(parse_entity(entity)?))),
+        ["save"] => Ok(ConsoleCommand::Save(QUICKSAVE_FILE.to_string())),
+        ["save", file] => Ok(ConsoleCommand::Save(file.to_string())),
+        ["load"] => Ok(ConsoleCommand::Load(QUICKSAVE_FILE.to_string())),
+        ["load", file] => Ok(ConsoleCommand::Load(file.to_string())),
+        ["bench", robots] => Ok(ConsoleCommand::Bench(parse(robots, "robot count")?)),
+        ["motion", "full"] => Ok(ConsoleCommand::ReducedMotion(false)),
+        ["motion", "reduced"] => Ok(ConsoleCommand::ReducedMotion(true)),
+        ["aim", team, accuracy] => {
+            let team = parse_team(team)?;
+            let accuracy: f32 = parse(accuracy, "accuracy")?;
+            if (0. ..=1.).contains(&accuracy) {
+                Ok(ConsoleCommand::AimAccuracy { team, accuracy })
+            } else {
+                Err(format!("invalid accuracy: '{}'", accuracy))
+            }
+        }
+        ["help"] => Ok(ConsoleCommand::Help),
+        _ => Err(format!("unknown command '{}', try 'help'", line.trim())),
+    }
+}
+
+fn parse<T: FromStr>(word: &str, what: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("invalid {}: '{}'", what, word))
+}
+
+fn parse_team(word: &str) -> Result<Team, String> {
+    match word {
+        "player" => Ok(Team::Player),
+        "enemy" => Ok(Team::Enemy),
+        _ => Err(format!("invalid team: '{}'", word)),
+    }
+}
+
+fn parse_tool(word: &str) -> Result<ToolKind, String> {
+    match word {
+        "arrow" => Ok(ToolKind::Arrow),
+        "fork" => Ok(ToolKind::Fork),
+        "grouper" => Ok(ToolKind::Grouper),
+        "defender" => Ok(ToolKind::Defender),
+        _ => Err(format!("invalid tool: '{}'", word)),
+    }
+}
+
+/// Accepts the `12v0` form entities are printed with, or a bare index.
+fn parse_entity(word: &str) -> Result<Entity, String> {
+    let (index, generation) = word.split_once('v').unwrap_or((word, "0"));
+    let index: u32 = parse(index, "entity")?;
+    let generation: u32 = parse(generation, "entity")?;
+    Ok(Entity::from_bits((generation as u64) << 32 | index as u64))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_startup_script(mut console: ResMut<Console>) {
+    if let Ok(script) = std::fs::read_to_string(STARTUP_SCRIPT) {
+        for line in script.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_command(line) {
+                Ok(command) => console.startup.push(command),
+                Err(err) => warn!("{}: {}", STARTUP_SCRIPT, err),
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_startup_script() {}
+
+fn run_startup_script(mut console: ResMut<Console>, mut ev_command: EventWriter<ConsoleCommand>) {
+    for command in console.startup.drain(..) {
+        ev_command.send(command);
+    }
+}
+
+fn toggle_console(
+    actions: Actions,
+    mut console: ResMut<Console>,
+    mut text_input_focus: ResMut<TextInputFocus>,
+) {
+    if console.allowed && actions.just_pressed(Action::ToggleConsole) {
+        console.open = !console.open;
+    }
+    text_input_focus.0 = console.open;
+}
+
+fn console_ui(
+    mut contexts: EguiContexts,
+    mut console: ResMut<Console>,
+    mut ev_command: EventWriter<ConsoleCommand>,
+) {
+    if !console.open {
+        return;
+    }
+    let console = &mut *console;
+    // the key that opens the console is typed into the field as well
+    console.input.retain(|c| c != '`');
+    let mut submitted = None;
+    egui::Window::new("Console")
+        .default_width(480.)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(240.)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in &console.log {
+                        ui.monospace(line);
+                    }
+                });
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut console.input)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(f32::INFINITY),
+            );
+            if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                submitted = Some(std::mem::take(&mut console.input));
+            }
+            if ui.input(|input| input.key_pressed(egui::Key::Escape)) {
+                console.open = false;
+            }
+            response.request_focus();
+        });
+    if let Some(line) = submitted {
+        if line.trim().is_empty() {
+            return;
+        }
+        console.log(format!("> {}", line));
+        match parse_command(&line) {
+            Ok(command) => ev_command.send(command),
+            Err(err) => console.log(err),
+        }
+    }
+}
+
+fn run_commands(
+    mut ev_command: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut ev_spawn_robot: EventWriter<SpawnRobotEvent>,
+    mut ev_change_level: EventWriter<ChangeLevelEvent>,
+    mut ev_save_state: EventWriter<SaveStateEvent>,
+    mut ev_load_state: EventWriter<LoadStateEvent>,
+    mut inventory: ResMut<Inventory>,
+    mut q_portal: Query<(&Team, &mut Life), With<Portal>>,
+    mut rng: ResMut<GameRng>,
+    mut inspected: ResMut<InspectedEntity>,
+    q_entity: Query<()>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    mouse_pos: Res<MouseWorldCoords>,
+    level_size: Res<LevelSize>,
+) {
+    for command in ev_command.read() {
+        match command {
+            ConsoleCommand::SpawnRobots { team, count, at } => {
+                let (Ok(level_gtr), Some(size_info)) = (q_level.get_single(), level_size.0) else {
+                    console.log("no level loaded");
+                    continue;
+                };
+                // robots are children of the level
+                let center = match at {
+                    Some(at) => {
+                        grid_coords_to_translation(GridCoords::from(*at), size_info.tile_size_vec())
+                    }
+                    None => mouse_pos
+                        .0
+                        .map_or(size_info.pixel_size().as_vec2() / 2., |pos| {
+                            pos - level_gtr.translation().truncate()
+                        }),
+                };
+                for i in 0..*count {
+                    let offset =
+                        Vec2::from_angle(i as f32 * 2.4) * (i as f32).sqrt() * SPAWN_SPACING;
+                    ev_spawn_robot.send(SpawnRobotEvent {
+                        pos: center + offset,
+                        dir: Vec2::X,
+                        team: *team,
+                        weapons: vec![],
+                    });
+                }
+                console.log(format!("spawned {} {:?} robots", count, team));
+            }
+            ConsoleCommand::Give { kind, count } => {
+                let stock = match kind {
+                    ToolKind::Arrow => &mut inventory.arrow_count,
+                    ToolKind::Fork => &mut inventory.fork_count,
+                    ToolKind::Grouper => &mut inventory.grouper_count,
+                    ToolKind::Defender => &mut inventory.defender_count,
+                };
+                *stock += *count;
+                console.log(format!("{:?} count is now {}", kind, stock));
+            }
+            ConsoleCommand::Level(index) => {
+                ev_change_level.send(ChangeLevelEvent::Index(*index));
+                console.log(format!("loading level {}", index));
+            }
+            ConsoleCommand::Win | ConsoleCommand::Lose => {
+                let losing_team = match command {
+                    ConsoleCommand::Win => Team::Enemy,
+                    _ => Team::Player,
+                };
+                for (team, mut life) in &mut q_portal {
+                    if *team == losing_team {
+                        life.curr_hp = 0.;
+                    }
+                }
+                console.log(format!("destroyed {:?} portals", losing_team));
+            }
+            ConsoleCommand::Seed(seed) => {
+                rng.0 = StdRng::seed_from_u64(*seed);
+                console.log(format!("random seed set to {}", seed));
+            }
+            ConsoleCommand::SetPortalHp { team, hp } => {
+                for (portal_team, mut life) in &mut q_portal {
+                    if portal_team == team {
+                        life.curr_hp = *hp;
+                        life.max_hp = life.max_hp.max(*hp);
+                    }
+                }
+                console.log(format!("{:?} portals set to {} hp", team, hp));
+            }
+            ConsoleCommand::Inspect(entity) => match entity {
+                Some(entity) if !q_entity.contains(*entity) => {
+                    console.log(format!("no entity {:?}", entity));
+                }
+                _ => inspected.0 = *entity,
+            },
+            ConsoleCommand::Save(file) => {
+                ev_save_state.send(SaveStateEvent(file.clone()));
+                console.log(format!("saving to {}", file));
+            }
+            ConsoleCommand::Load(file) => {
+                ev_load_state.send(LoadStateEvent(file.clone()));
+                console.log(format!("loading {}", file));
+            }
+            // handled by the benchmark plugin
+            ConsoleCommand::Bench(_) => {}
+            // handled by the camera effects plugin
+            ConsoleCommand::ReducedMotion(_) | ConsoleCommand::TimeScale(_) => {}
+            // handled by the weapon plugin
+            ConsoleCommand::AimAccuracy { .. } => {}
+            ConsoleCommand::Help => {
+                for usage in USAGE {
+                    console.log(usage);
+                }
+            }
+        }
+    }
+}
+
+fn inspector_ui(world: &mut World) {
+    let Some(entity) = world.resource::<InspectedEntity>().0 else {
+        return;
+    };
+    if world.get_entity(entity).is_none() {
+        world.resource_mut::<InspectedEntity>().0 = None;
+        return;
+    }
+    let Ok(egui_context) = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+    let mut open = true;
+    egui::Window::new(format!("Inspect {:?}", entity))
+        .open(&mut open)
+        .default_width(320.)
+        .show(egui_context.get_mut(), |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                bevy_inspector_egui::bevy_inspector::ui_for_entity(world, entity, ui);
+            });
+        });
+    if !open {
+        world.resource_mut::<InspectedEntity>().0 = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_spawn_robot() {
+        assert_eq!(
+            parse_command("spawn robot enemy"),
+            Ok(ConsoleCommand::SpawnRobots {
+                team: Team::Enemy,
+                count: 1,
+                at: None
+            })
+        );
+        assert_eq!(
+            parse_command("spawn robot player 5 at 3 -2"),
+            Ok(ConsoleCommand::SpawnRobots {
+                team: Team::Player,
+                count: 5,
+                at: Some(IVec2::new(3, -2))
+            })
+        );
+        assert_eq!(
+            parse_command("  spawn  robot player at 1 2 "),
+            Ok(ConsoleCommand::SpawnRobots {
+                team: Team::Player,
+                count: 1,
+                at: Some(IVec2::new(1, 2))
+            })
+        );
+        assert!(parse_command("spawn robot player 5 at 3").is_err());
+        assert!(parse_command("spawn robot neutral").is_err());
+        assert!(parse_command("spawn robot player many").is_err());
+    }
+
+    #[test]
+    fn parses_give() {
+        assert_eq!(
+            parse_command("give fork"),
+            Ok(ConsoleCommand::Give {
+                kind: ToolKind::Fork,
+                count: 1
+            })
+        );
+        assert_eq!(
+            parse_command("give defender 3"),
+            Ok(ConsoleCommand::Give {
+                kind: ToolKind::Defender,
+                count: 3
+            })
+        );
+        assert!(parse_command("give hammer").is_err());
+        assert!(parse_command("give arrow 1 2").is_err());
+    }
+
+    #[test]
+    fn parses_simple_commands() {
+        assert_eq!(parse_command("level 2"), Ok(ConsoleCommand::Level(2)));
+        assert_eq!(parse_command("win"), Ok(ConsoleCommand::Win));
+        assert_eq!(parse_command("lose"), Ok(ConsoleCommand::Lose));
+        assert_eq!(parse_command("seed 42"), Ok(ConsoleCommand::Seed(42)));
+        assert_eq!(parse_command("bench 500"), Ok(ConsoleCommand::Bench(500)));
+        assert_eq!(parse_command("help"), Ok(ConsoleCommand::Help));
+        assert_eq!(
+            parse_command("hp portal enemy 30"),
+            Ok(ConsoleCommand::SetPortalHp {
+                team: Team::Enemy,
+                hp: 30.
+            })
+        );
+        assert_eq!(
+            parse_command("motion reduced"),
+            Ok(ConsoleCommand::ReducedMotion(true))
+        );
+        assert_eq!(
+            parse_command("motion full"),
+            Ok(ConsoleCommand::ReducedMotion(false))
+        );
+    }
+
+    #[test]
+    fn validates_numbers() {
+        assert_eq!(
+            parse_command("timescale 0.5"),
+            Ok(ConsoleCommand::TimeScale(0.5))
+        );
+        assert!(parse_command("timescale -1").is_err());
+        assert!(parse_command("timescale inf").is_err());
+        assert_eq!(
+            parse_command("aim enemy 0.25"),
+            Ok(ConsoleCommand::AimAccuracy {
+                team: Team::Enemy,
+                accuracy: 0.25
+            })
+        );
+        assert!(parse_command("aim enemy 1.5").is_err());
+        assert!(parse_command("level two").is_err());
+    }
+
+    #[test]
+    fn parses_save_and_load() {
+        assert_eq!(
+            parse_command("save"),
+            Ok(ConsoleCommand::Save(QUICKSAVE_FILE.to_string()))
+        );
+        assert_eq!(
+            parse_command("load run.save"),
+            Ok(ConsoleCommand::Load("run.save".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_inspect() {
+        assert_eq!(parse_command("inspect"), Ok(ConsoleCommand::Inspect(None)));
+        assert_eq!(
+            parse_command("inspect 12"),
+            Ok(ConsoleCommand::Inspect(Some(Entity::from_raw(12))))
+        );
+        let entity = Entity::from_bits(3 << 32 | 7);
+        assert_eq!(
+            parse_command(&format!("inspect {:?}", entity)),
+            Ok(ConsoleCommand::Inspect(Some(entity)))
+        );
+        assert!(parse_command("inspect 7x3").is_err());
+    }
+
+    #[test]
+    fn cheats_are_flagged() {
+        assert!(parse_command("give arrow 2").unwrap().is_cheat());
+        assert!(parse_command("win").unwrap().is_cheat());
+        assert!(parse_command("hp portal player 100").unwrap().is_cheat());
+        assert!(!parse_command("lose").unwrap().is_cheat());
+        assert!(!parse_command("timescale 2").unwrap().is_cheat());
+        assert!(!parse_command("help").unwrap().is_cheat());
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        assert!(parse_command("").is_err());
+        assert!(parse_command("fly").is_err());
+        assert!(parse_command("win now").is_err());
+    }
+}
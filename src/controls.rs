@@ -0,0 +1,518 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::mouse::MouseMotion,
+    prelude::*,
+    utils::HashMap,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_ecs_ldtk::{
+    utils::{grid_coords_to_translation, translation_to_grid_coords},
+    GridCoords, LevelIid,
+};
+
+use crate::{
+    arrow::DraggedArrow,
+    debug_overlay::DebugLayer,
+    defender::DraggedDefender,
+    draggable::{DragState, LockedDirection},
+    fork::DraggedFork,
+    game::{GameState, LevelState},
+    game_camera::{CameraTargetPos, CameraTargetScale, MouseWorldCoords},
+    game_ui::{SimulateAheadEvent, StartLevelEvent},
+    gameplay_events::ToolKind,
+    grouper::DraggedGrouper,
+    inventory::Inventory,
+    levels::{tools_unlocked, LevelSize},
+    mouse::{Drag, DragPos, MouseState},
+};
+
+// in window heights per second
+const PAN_SPEED: f32 = 1.2;
+const EDGE_PAN_MARGIN: f32 = 16.;
+// zoom factor per second while a zoom key is held
+const ZOOM_SPEED: f32 = 1.8;
+const VIRTUAL_CURSOR_SPEED: f32 = 900.;
+const STICK_DEADZONE: f32 = 0.2;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::default())
+            .insert_resource(TextInputFocus::default())
+            .insert_resource(VirtualCursor::default())
+            .add_systems(
+                Update,
+                (
+                    (pan_camera, zoom_camera),
+                    (
+                        update_virtual_cursor,
+                        draw_virtual_cursor,
+                        rotate_direction,
+                        unlock_direction,
+                        start_level,
+                        simulate_ahead,
+                    )
+                        .chain(),
+                    tool_hotkeys.run_if(tools_unlocked).run_if(
+                        in_state(LevelState::Planning).or_else(in_state(LevelState::Playing)),
+                    ),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum Action {
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    Tool(ToolKind),
+    Rotate,
+    Confirm,
+    Cancel,
+    StartLevel,
+    ToggleDebugOverlay,
+    ToggleDebugLayer(DebugLayer),
+    ToggleConsole,
+    FollowSelection,
+    SimulateAhead,
+    QuickSave,
+    QuickLoad,
+    FitLevel,
+    CameraBookmark(usize),
+    /// Held while a bookmark key is pressed to store the bookmark instead of recalling it.
+    StoreCameraBookmark,
+}
+
+/// Keyboard keys and gamepad buttons bound to each action. Every action can have
+/// several bindings, use `bind_key`/`bind_gamepad_button` to remap one.
+#[derive(Resource)]
+pub struct Bindings {
+    pub keys: HashMap<Action, Vec<KeyCode>>,
+    pub gamepad_buttons: HashMap<Action, Vec<GamepadButtonType>>,
+    pub edge_pan: bool,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let keys = [
+            (Action::PanUp, vec![KeyCode::W, KeyCode::Up]),
+            (Action::PanDown, vec![KeyCode::S, KeyCode::Down]),
+            (Action::PanLeft, vec![KeyCode::A, KeyCode::Left]),
+            (Action::PanRight, vec![KeyCode::D, KeyCode::Right]),
+            (
+                Action::ZoomIn,
+                vec![KeyCode::Equals, KeyCode::Plus, KeyCode::NumpadAdd],
+            ),
+            (
+                Action::ZoomOut,
+                vec![KeyCode::Minus, KeyCode::NumpadSubtract],
+            ),
+            (Action::Tool(ToolKind::Arrow), vec![KeyCode::Key1]),
+            (Action::Tool(ToolKind::Fork), vec![KeyCode::Key2]),
+            (Action::Tool(ToolKind::Grouper), vec![KeyCode::Key3]),
+            (Action::Tool(ToolKind::Defender), vec![KeyCode::Key4]),
+            (Action::Rotate, vec![KeyCode::R]),
+            (Action::Confirm, vec![KeyCode::Space]),
+            (Action::Cancel, vec![KeyCode::Escape]),
+            (Action::StartLevel, vec![KeyCode::Return]),
+            (Action::FollowSelection, vec![KeyCode::F]),
+            (Action::SimulateAhead, vec![KeyCode::P]),
+            (Action::QuickSave, vec![KeyCode::F5]),
+            (Action::QuickLoad, vec![KeyCode::F9]),
+            (Action::FitLevel, vec![KeyCode::Home]),
+            (Action::CameraBookmark(0), vec![KeyCode::F1]),
+            (Action::CameraBookmark(1), vec![KeyCode::F2]),
+            (Action::CameraBookmark(2), vec![KeyCode::F3]),
+            (Action::CameraBookmark(3), vec![KeyCode::F4]),
+            (
+                Action::StoreCameraBookmark,
+                vec![KeyCode::ControlLeft, KeyCode::ControlRight],
+            ),
+            (Action::ToggleDebugOverlay, vec![KeyCode::F10]),
+            (Action::ToggleConsole, vec![KeyCode::Grave]),
+            (
+                Action::ToggleDebugLayer(DebugLayer::ToolSensors),
+                vec![KeyCode::Numpad1],
+            ),
+            (
+                Action::ToggleDebugLayer(DebugLayer::PortalSensors),
+                vec![KeyCode::Numpad2],
+            ),
+            (
+                Action::ToggleDebugLayer(DebugLayer::RobotTargets),
+                vec![KeyCode::Numpad3],
+            ),
+            (
+                Action::ToggleDebugLayer(DebugLayer::EngineDirs),
+                vec![KeyCode::Numpad4],
+            ),
+            (
+                Action::ToggleDebugLayer(DebugLayer::SteeringForces),
+                vec![KeyCode::Numpad5],
+            ),
+            (
+                Action::ToggleDebugLayer(DebugLayer::CollisionGroups),
+                vec![KeyCode::Numpad6],
+            ),
+        ];
+        let gamepad_buttons = [
+            (Action::ZoomIn, vec![GamepadButtonType::RightTrigger2]),
+            (Action::ZoomOut, vec![GamepadButtonType::LeftTrigger2]),
+            (
+                Action::Tool(ToolKind::Arrow),
+                vec![GamepadButtonType::DPadUp],
+            ),
+            (
+                Action::Tool(ToolKind::Fork),
+                vec![GamepadButtonType::DPadRight],
+            ),
+            (
+                Action::Tool(ToolKind::Grouper),
+                vec![GamepadButtonType::DPadDown],
+            ),
+            (
+                Action::Tool(ToolKind::Defender),
+                vec![GamepadButtonType::DPadLeft],
+            ),
+            (Action::Rotate, vec![GamepadButtonType::RightTrigger]),
+            (Action::Confirm, vec![GamepadButtonType::South]),
+            (Action::Cancel, vec![GamepadButtonType::East]),
+            (Action::StartLevel, vec![GamepadButtonType::Start]),
+            (Action::FollowSelection, vec![GamepadButtonType::North]),
+            (Action::SimulateAhead, vec![GamepadButtonType::Select]),
+            (Action::FitLevel, vec![GamepadButtonType::RightThumb]),
+        ];
+        Self {
+            keys: keys.into_iter().collect(),
+            gamepad_buttons: gamepad_buttons.into_iter().collect(),
+            edge_pan: true,
+        }
+    }
+}
+
+impl Bindings {
+    pub fn bind_key(&mut self, action: Action, key: KeyCode) {
+        self.keys.insert(action, vec![key]);
+    }
+
+    pub fn bind_gamepad_button(&mut self, action: Action, button: GamepadButtonType) {
+        self.gamepad_buttons.insert(action, vec![button]);
+    }
+}
+
+/// Set while a text field has the keyboard, keyboard bindings are ignored until it is cleared.
+#[derive(Resource, Default)]
+pub struct TextInputFocus(pub bool);
+
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    bindings: Res<'w, Bindings>,
+    text_input_focus: Res<'w, TextInputFocus>,
+    keys: Res<'w, Input<KeyCode>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepads: Res<'w, Gamepads>,
+}
+
+impl<'w> Actions<'w> {
+    fn check(
+        &self,
+        action: Action,
+        key_check: impl Fn(&Input<KeyCode>, KeyCode) -> bool,
+        button_check: impl Fn(&Input<GamepadButton>, GamepadButton) -> bool,
+    ) -> bool {
+        let typing = self.text_input_focus.0 && action != Action::ToggleConsole;
+        let key = !typing
+            && self
+                .bindings
+                .keys
+                .get(&action)
+                .is_some_and(|keys| keys.iter().any(|key| key_check(&self.keys, *key)));
+        let button = self
+            .bindings
+            .gamepad_buttons
+            .get(&action)
+            .is_some_and(|buttons| {
+                self.gamepads.iter().any(|gamepad| {
+                    buttons.iter().any(|button_type| {
+                        button_check(
+                            &self.gamepad_buttons,
+                            GamepadButton::new(gamepad, *button_type),
+                        )
+                    })
+                })
+            });
+        key || button
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.check(
+            action,
+            |keys, key| keys.pressed(key),
+            |buttons, button| buttons.pressed(button),
+        )
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.check(
+            action,
+            |keys, key| keys.just_pressed(key),
+            |buttons, button| buttons.just_pressed(button),
+        )
+    }
+}
+
+/// Cursor driven by the gamepad left stick. While active it replaces the mouse
+/// in `MouseWorldCoords`, and it snaps to the center of a grid cell when the stick
+/// is released.
+#[derive(Resource, Default)]
+pub struct VirtualCursor {
+    pub active: bool,
+    pub pos: Vec2,
+}
+
+fn stick(
+    gamepads: &Gamepads,
+    axes: &Axis<GamepadAxis>,
+    x: GamepadAxisType,
+    y: GamepadAxisType,
+) -> Vec2 {
+    let value = gamepads
+        .iter()
+        .map(|gamepad| {
+            Vec2::new(
+                axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.),
+                axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.),
+            )
+        })
+        .fold(Vec2::ZERO, |acc, value| acc + value);
+    if value.length() < STICK_DEADZONE {
+        Vec2::ZERO
+    } else {
+        value.clamp_length_max(1.0)
+    }
+}
+
+pub fn pan_camera(
+    actions: Actions,
+    mouse_state: Res<MouseState>,
+    mut target_pos: ResMut<CameraTargetPos>,
+    target_scale: Res<CameraTargetScale>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    virtual_cursor: Res<VirtualCursor>,
+    time: Res<Time>,
+) {
+    if *mouse_state == MouseState::CameraMovement {
+        return;
+    }
+    let mut dir = Vec2::ZERO;
+    for (action, action_dir) in [
+        (Action::PanUp, Vec2::Y),
+        (Action::PanDown, Vec2::NEG_Y),
+        (Action::PanLeft, Vec2::NEG_X),
+        (Action::PanRight, Vec2::X),
+    ] {
+        if actions.pressed(action) {
+            dir += action_dir;
+        }
+    }
+    dir += stick(
+        &gamepads,
+        &axes,
+        GamepadAxisType::RightStickX,
+        GamepadAxisType::RightStickY,
+    );
+    if actions.bindings.edge_pan && !virtual_cursor.active {
+        if let Ok(window) = q_window.get_single() {
+            if let Some(cursor) = window.cursor_position() {
+                if window.focused && window.cursor.grab_mode == CursorGrabMode::None {
+                    if cursor.x < EDGE_PAN_MARGIN {
+                        dir.x -= 1.0;
+                    } else if cursor.x > window.width() - EDGE_PAN_MARGIN {
+                        dir.x += 1.0;
+                    }
+                    // window coordinates go down
+                    if cursor.y < EDGE_PAN_MARGIN {
+                        dir.y += 1.0;
+                    } else if cursor.y > window.height() - EDGE_PAN_MARGIN {
+                        dir.y -= 1.0;
+                    }
+                }
+            }
+        }
+    }
+    if dir != Vec2::ZERO {
+        let window_height = q_window.get_single().map_or(720., |window| window.height());
+        let delta = dir.clamp_length_max(1.0)
+            * PAN_SPEED
+            * window_height
+            * target_scale.get()
+            * time.delta_seconds();
+        target_pos.0 += delta.extend(0.0);
+    }
+}
+
+fn zoom_camera(actions: Actions, mut target_scale: ResMut<CameraTargetScale>, time: Res<Time>) {
+    let mut factor = 1.0;
+    if actions.pressed(Action::ZoomIn) {
+        factor /= ZOOM_SPEED.powf(time.delta_seconds());
+    }
+    if actions.pressed(Action::ZoomOut) {
+        factor *= ZOOM_SPEED.powf(time.delta_seconds());
+    }
+    if factor != 1.0 {
+        let scale = target_scale.get();
+        target_scale.set(scale * factor);
+    }
+}
+
+fn update_virtual_cursor(
+    mut virtual_cursor: ResMut<VirtualCursor>,
+    mut motion_ev: EventReader<MouseMotion>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mouse_pos: Res<MouseWorldCoords>,
+    target_pos: Res<CameraTargetPos>,
+    target_scale: Res<CameraTargetScale>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    level_size: Res<LevelSize>,
+    time: Res<Time>,
+) {
+    let dir = stick(
+        &gamepads,
+        &axes,
+        GamepadAxisType::LeftStickX,
+        GamepadAxisType::LeftStickY,
+    );
+    if motion_ev.read().count() > 0 {
+        virtual_cursor.active = false;
+    } else if !virtual_cursor.active
+        && (dir != Vec2::ZERO || gamepad_buttons.get_just_pressed().next().is_some())
+    {
+        virtual_cursor.active = true;
+        virtual_cursor.pos = mouse_pos.0.unwrap_or(target_pos.0.truncate());
+    }
+    if !virtual_cursor.active {
+        return;
+    }
+
+    if dir != Vec2::ZERO {
+        virtual_cursor.pos +=
+            dir * VIRTUAL_CURSOR_SPEED * target_scale.get() * time.delta_seconds();
+    } else if let (Ok(level_gtr), Some(size_info)) = (q_level.get_single(), level_size.0) {
+        let level_pos = level_gtr.translation().truncate();
+        let coords =
+            translation_to_grid_coords(virtual_cursor.pos - level_pos, size_info.tile_size_vec());
+        let coords = GridCoords::new(
+            coords.x.clamp(0, size_info.size.x - 1),
+            coords.y.clamp(0, size_info.size.y - 1),
+        );
+        virtual_cursor.pos =
+            grid_coords_to_translation(coords, size_info.tile_size_vec()) + level_pos;
+    }
+}
+
+fn draw_virtual_cursor(
+    mut gizmos: Gizmos,
+    virtual_cursor: Res<VirtualCursor>,
+    level_size: Res<LevelSize>,
+) {
+    if virtual_cursor.active {
+        let size = level_size
+            .0
+            .map_or(16.0, |size_info| size_info.tile_size as f32);
+        gizmos.rect_2d(virtual_cursor.pos, 0., Vec2::splat(size), Color::WHITE);
+    }
+}
+
+fn rotate_direction(
+    mut cmd: Commands,
+    actions: Actions,
+    q_drag: Query<(Entity, &Transform, &DragState, Option<&LockedDirection>)>,
+) {
+    if actions.just_pressed(Action::Rotate) {
+        for (entity, drag_tr, drag_state, locked) in &q_drag {
+            if let DragState::SettingDirection(_) = drag_state {
+                let angle = locked.map_or_else(
+                    || {
+                        let dir = drag_tr.rotation.mul_vec3(Vec3::X).truncate();
+                        (Vec2::X.angle_between(dir) / FRAC_PI_2).round() * FRAC_PI_2
+                    },
+                    |locked| locked.0,
+                );
+                cmd.entity(entity)
+                    .insert(LockedDirection(angle + FRAC_PI_2));
+            }
+        }
+    }
+}
+
+fn unlock_direction(
+    mut cmd: Commands,
+    mut motion_ev: EventReader<MouseMotion>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    q_drag: Query<Entity, With<LockedDirection>>,
+) {
+    let stick_moved = stick(
+        &gamepads,
+        &axes,
+        GamepadAxisType::LeftStickX,
+        GamepadAxisType::LeftStickY,
+    ) != Vec2::ZERO;
+    if motion_ev.read().count() > 0 || stick_moved {
+        for entity in &q_drag {
+            cmd.entity(entity).remove::<LockedDirection>();
+        }
+    }
+}
+
+fn tool_hotkeys(
+    mut cmd: Commands,
+    actions: Actions,
+    inventory: Res<Inventory>,
+    mouse_state: Res<MouseState>,
+    mouse_pos: Res<MouseWorldCoords>,
+    target_pos: Res<CameraTargetPos>,
+) {
+    if *mouse_state != MouseState::Default {
+        return;
+    }
+    let pos = DragPos(mouse_pos.0.unwrap_or(target_pos.0.truncate()));
+    if actions.just_pressed(Action::Tool(ToolKind::Arrow)) && inventory.arrow_count > 0 {
+        cmd.spawn((Drag, pos, DraggedArrow));
+    } else if actions.just_pressed(Action::Tool(ToolKind::Fork)) && inventory.fork_count > 0 {
+        cmd.spawn((Drag, pos, DraggedFork));
+    } else if actions.just_pressed(Action::Tool(ToolKind::Grouper)) && inventory.grouper_count > 0 {
+        cmd.spawn((Drag, pos, DraggedGrouper));
+    } else if actions.just_pressed(Action::Tool(ToolKind::Defender)) && inventory.defender_count > 0
+    {
+        cmd.spawn((Drag, pos, DraggedDefender));
+    }
+}
+
+fn start_level(
+    actions: Actions,
+    level_state: Res<State<LevelState>>,
+    mut ev_start_level: EventWriter<StartLevelEvent>,
+) {
+    if *level_state == LevelState::Planning && actions.just_pressed(Action::StartLevel) {
+        ev_start_level.send(StartLevelEvent);
+    }
+}
+
+fn simulate_ahead(actions: Actions, mut ev_simulate_ahead: EventWriter<SimulateAheadEvent>) {
+    if actions.just_pressed(Action::SimulateAhead) {
+        ev_simulate_ahead.send(SimulateAheadEvent);
+    }
+}
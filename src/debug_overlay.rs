@@ -0,0 +1,309 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    arrow::Arrow,
+    controls::{Action, Actions},
+    defender::Defender,
+    draggable::TOOL_SENSOR_RADIUS,
+    fork::Fork,
+    game::GameState,
+    game_camera::MouseWorldCoords,
+    grouper::Grouper,
+    physics::{ObjectGroup, Team},
+    portal::PortalSensor,
+    robot::{EngineDir, Robot, RobotTarget, SteeringForces},
+    storage,
+};
+
+const ENGINE_DIR_LENGTH: f32 = 16.;
+// world units per unit of force
+const FORCE_SCALE: f32 = 1. / 5000.;
+const WALL_FORCE_COLOR: Color = Color::ORANGE;
+const ROBOT_FORCE_COLOR: Color = Color::CYAN;
+const TARGET_COLOR: Color = Color::YELLOW;
+const DEBUG_OVERLAY_FILE: &str = "debug_overlay.txt";
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DebugOverlay::load())
+            .add_systems(
+                Update,
+                (
+                    toggle_overlay,
+                    (
+                        draw_tool_sensors,
+                        draw_portal_sensors,
+                        draw_robot_targets,
+                        draw_engine_dirs,
+                        draw_steering_forces,
+                        update_collision_groups_text,
+                    )
+                        .run_if(overlay_enabled),
+                    despawn_collision_groups_text.run_if(not(overlay_enabled)),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_collision_groups_text)
+            .add_systems(PostUpdate, save_debug_overlay_setting);
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum DebugLayer {
+    ToolSensors,
+    PortalSensors,
+    RobotTargets,
+    EngineDirs,
+    SteeringForces,
+    CollisionGroups,
+}
+
+impl DebugLayer {
+    pub const ALL: [DebugLayer; 6] = [
+        DebugLayer::ToolSensors,
+        DebugLayer::PortalSensors,
+        DebugLayer::RobotTargets,
+        DebugLayer::EngineDirs,
+        DebugLayer::SteeringForces,
+        DebugLayer::CollisionGroups,
+    ];
+}
+
+/// Gizmo overlay for tuning levels. Allowed in debug builds, and in release builds once it is
+/// turned on from the menu.
+#[derive(Resource)]
+pub struct DebugOverlay {
+    pub allowed: bool,
+    pub enabled: bool,
+    pub layers: HashSet<DebugLayer>,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self {
+            allowed: cfg!(debug_assertions),
+            enabled: false,
+            layers: DebugLayer::ALL.into_iter().collect(),
+        }
+    }
+}
+
+// One setting per line:
+//   debug_overlay <on|off>
+impl DebugOverlay {
+    fn load() -> Self {
+        let mut overlay = Self::default();
+        for line in storage::read(DEBUG_OVERLAY_FILE)
+            .unwrap_or_default()
+            .lines()
+        {
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["debug_overlay", "on"] => overlay.allowed = true,
+                ["debug_overlay", "off"] => overlay.allowed = false,
+                _ => {}
+            }
+        }
+        overlay
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "debug_overlay {}\n",
+            if self.allowed { "on" } else { "off" }
+        )
+    }
+
+    fn shows(&self, layer: DebugLayer) -> bool {
+        self.layers.contains(&layer)
+    }
+}
+
+#[derive(Component)]
+struct CollisionGroupsText;
+
+fn overlay_enabled(overlay: Res<DebugOverlay>) -> bool {
+    overlay.allowed && overlay.enabled
+}
+
+fn save_debug_overlay_setting(overlay: Res<DebugOverlay>) {
+    if overlay.is_changed() && !overlay.is_added() {
+        if let Err(err) = storage::write(DEBUG_OVERLAY_FILE, &overlay.to_text()) {
+            warn!("could not save debug overlay setting: {}", err);
+        }
+    }
+}
+
+fn toggle_overlay(actions: Actions, mut overlay: ResMut<DebugOverlay>) {
+    if !overlay.allowed {
+        return;
+    }
+    if actions.just_pressed(Action::ToggleDebugOverlay) {
+        overlay.enabled = !overlay.enabled;
+    }
+    for layer in DebugLayer::ALL {
+        if actions.just_pressed(Action::ToggleDebugLayer(layer)) && !overlay.layers.remove(&layer) {
+            overlay.layers.insert(layer);
+        }
+    }
+}
+
+fn draw_tool_sensors(
+    mut gizmos: Gizmos,
+    overlay: Res<DebugOverlay>,
+    q_tool: Query<
+        (&GlobalTransform, &Team),
+        Or<(With<Arrow>, With<Fork>, With<Grouper>, With<Defender>)>,
+    >,
+) {
+    if overlay.shows(DebugLayer::ToolSensors) {
+        for (gtr, team) in &q_tool {
+            gizmos
+                .circle_2d(
+                    gtr.translation().truncate(),
+                    TOOL_SENSOR_RADIUS,
+                    team.tint(),
+                )
+                .segments(48);
+        }
+    }
+}
+
+fn draw_portal_sensors(
+    mut gizmos: Gizmos,
+    overlay: Res<DebugOverlay>,
+    q_sensor: Query<(&GlobalTransform, &Collider, &Parent), With<PortalSensor>>,
+    q_team: Query<&Team>,
+) {
+    if overlay.shows(DebugLayer::PortalSensors) {
+        for (gtr, collider, parent) in &q_sensor {
+            if let Some(cuboid) = collider.as_cuboid() {
+                let (_, rotation, translation) = gtr.to_scale_rotation_translation();
+                let color = q_team
+                    .get(parent.get())
+                    .map_or(Color::WHITE, |team| team.tint());
+                gizmos.rect_2d(
+                    translation.truncate(),
+                    rotation.to_euler(EulerRot::XYZ).2,
+                    cuboid.half_extents() * 2.,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+fn draw_robot_targets(
+    mut gizmos: Gizmos,
+    overlay: Res<DebugOverlay>,
+    q_robot: Query<(&GlobalTransform, &RobotTarget), With<Robot>>,
+    q_target: Query<&GlobalTransform>,
+) {
+    if overlay.shows(DebugLayer::RobotTargets) {
+        for (gtr, RobotTarget(target)) in &q_robot {
+            if let Ok(target_gtr) = q_target.get(*target) {
+                gizmos.line_2d(
+                    gtr.translation().truncate(),
+                    target_gtr.translation().truncate(),
+                    TARGET_COLOR,
+                );
+            }
+        }
+    }
+}
+
+fn draw_engine_dirs(
+    mut gizmos: Gizmos,
+    overlay: Res<DebugOverlay>,
+    q_robot: Query<(&GlobalTransform, &EngineDir, &Team), With<Robot>>,
+) {
+    if overlay.shows(DebugLayer::EngineDirs) {
+        for (gtr, engine_dir, team) in &q_robot {
+            gizmos.ray_2d(
+                gtr.translation().truncate(),
+                engine_dir.0 * ENGINE_DIR_LENGTH,
+                team.tint(),
+            );
+        }
+    }
+}
+
+fn draw_steering_forces(
+    mut gizmos: Gizmos,
+    overlay: Res<DebugOverlay>,
+    q_robot: Query<(&GlobalTransform, &SteeringForces), With<Robot>>,
+) {
+    if overlay.shows(DebugLayer::SteeringForces) {
+        for (gtr, steering) in &q_robot {
+            let pos = gtr.translation().truncate();
+            gizmos.ray_2d(pos, steering.walls * FORCE_SCALE, WALL_FORCE_COLOR);
+            gizmos.ray_2d(pos, steering.robots * FORCE_SCALE, ROBOT_FORCE_COLOR);
+        }
+    }
+}
+
+fn update_collision_groups_text(
+    mut cmd: Commands,
+    overlay: Res<DebugOverlay>,
+    mouse_pos: Res<MouseWorldCoords>,
+    rapier_context: Res<RapierContext>,
+    q_groups: Query<(Option<&CollisionGroups>, Option<&Name>)>,
+    mut q_text: Query<&mut Text, With<CollisionGroupsText>>,
+) {
+    let mut lines = vec![];
+    if overlay.shows(DebugLayer::CollisionGroups) {
+        if let Some(pos) = mouse_pos.0 {
+            rapier_context.intersections_with_point(pos, QueryFilter::default(), |entity| {
+                if let Ok((groups, name)) = q_groups.get(entity) {
+                    let label = name.map_or(format!("{:?}", entity), |name| {
+                        format!("{} {:?}", name, entity)
+                    });
+                    match groups {
+                        Some(groups) => lines.push(format!(
+                            "{}\n  member of: {}\n  filters: {}",
+                            label,
+                            ObjectGroup::names(groups.memberships.bits()).join(" "),
+                            ObjectGroup::names(groups.filters.bits()).join(" "),
+                        )),
+                        None => lines.push(format!("{}\n  all groups", label)),
+                    }
+                }
+                true
+            });
+        }
+    }
+    let value = lines.join("\n");
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value = value;
+    } else {
+        cmd.spawn((
+            CollisionGroupsText,
+            TextBundle::from_section(
+                value,
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.),
+                left: Val::Px(8.),
+                ..Default::default()
+            }),
+        ));
+    }
+}
+
+fn despawn_collision_groups_text(
+    mut cmd: Commands,
+    q_text: Query<Entity, With<CollisionGroupsText>>,
+) {
+    for entity in &q_text {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
@@ -1,14 +1,20 @@
 use std::{collections::VecDeque, f32::consts::PI};
 
 use crate::{
-    draggable::{drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag},
+    draggable::{
+        drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag,
+        TOOL_SENSOR_RADIUS,
+    },
     game::GameState,
+    gameplay_events::{ToolKind, ToolPlaced, ToolRemoved},
     inventory::Inventory,
     levels::{LevelLoadedEvent, LevelSize, NoPlacingHere},
     load::TextureAssets,
     mouse::{ClickSensor, ClickSensorEvent, Drag, DragDropConfirm, DragDropRequest, DragPos},
     physics::{coll_groups, ObjectGroup, Team},
-    robot::{EngineDir, Robot},
+    robot::{EngineDir, LastRedirect, Robot},
+    sprite_animation::{SpriteAnimation, TOOL_IDLE},
+    status_effect::{StatusEffect, StatusKind, ToolEffect},
 };
 use bevy::{
     math::{vec2, vec3},
@@ -20,11 +26,15 @@ use bevy_ecs_ldtk::{
 };
 use bevy_rapier2d::prelude::*;
 
+// damage absorbed by robots waiting in a defender, and for how long
+const DEFENDER_SHIELD: f32 = 2.;
+const DEFENDER_SHIELD_DURATION: f32 = 10.;
+
 pub struct DefenderPlugin;
 
 impl Plugin for DefenderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.insert_resource(DefenderShield::default()).add_systems(
             Update,
             (
                 (
@@ -42,6 +52,10 @@ impl Plugin for DefenderPlugin {
     }
 }
 
+/// Set from the `defender_shield` level field, defenders then shield the robots waiting in them.
+#[derive(Resource, Default)]
+pub struct DefenderShield(pub bool);
+
 #[derive(Component)]
 pub struct DraggedDefender;
 
@@ -53,8 +67,8 @@ struct DefenderRobotSensor;
 
 #[derive(Component)]
 pub struct Defender {
-    dir: Vec2,
-    group: VecDeque<Entity>,
+    pub dir: Vec2,
+    pub group: VecDeque<Entity>,
 }
 
 fn drop_request(
@@ -72,6 +86,8 @@ fn drop_request(
     assets: Res<TextureAssets>,
     mut inventory: ResMut<Inventory>,
     q_level: Query<(Entity, &GlobalTransform), With<LevelIid>>,
+    mut ev_tool_placed: EventWriter<ToolPlaced>,
+    defender_shield: Res<DefenderShield>,
 ) {
     for (entity, drag_tr, maybe_valid, mut drag_state, grid_coords) in &mut q_drag {
         if maybe_valid.is_some() {
@@ -94,8 +110,15 @@ fn drop_request(
                         Team::Player,
                         Some(assets.defender.clone()),
                         *grid_coords,
+                        defender_shield.0,
                     );
                     cmd.entity(level_entity).add_child(defender);
+                    ev_tool_placed.send(ToolPlaced {
+                        tool: defender,
+                        kind: ToolKind::Defender,
+                        team: Team::Player,
+                        grid_coords: *grid_coords,
+                    });
                 }
             }
         } else {
@@ -104,13 +127,14 @@ fn drop_request(
     }
 }
 
-fn spawn_defender(
+pub fn spawn_defender(
     cmd: &mut Commands,
     tr: Transform,
     dir: Vec2,
     team: Team,
-    texture: Option<Handle<Image>>,
+    texture: Option<Handle<TextureAtlas>>,
     grid_coords: GridCoords,
+    shield: bool,
 ) -> Entity {
     let spawned_entity = cmd
         .spawn((
@@ -122,13 +146,22 @@ fn spawn_defender(
             grid_coords,
         ))
         .id();
+    if shield {
+        cmd.entity(spawned_entity)
+            .insert(ToolEffect(StatusEffect::new(
+                StatusKind::Shield,
+                DEFENDER_SHIELD,
+                DEFENDER_SHIELD_DURATION,
+            )));
+    }
     if team == Team::Player {
         cmd.entity(spawned_entity).insert((
-            SpriteBundle {
-                texture: texture.unwrap(),
+            SpriteSheetBundle {
+                texture_atlas: texture.unwrap(),
                 transform: tr,
                 ..Default::default()
             },
+            SpriteAnimation::new(TOOL_IDLE),
             NoPlacingHere,
         ));
     } else {
@@ -148,7 +181,7 @@ fn spawn_defender(
         cmd.spawn((
             DefenderRobotSensor,
             Sensor,
-            Collider::ball(96.),
+            Collider::ball(TOOL_SENSOR_RADIUS),
             match team {
                 Team::Player => coll_groups(
                     ObjectGroup::PLAYER_DEFENDER_SENSOR,
@@ -169,11 +202,12 @@ fn check_click(
     mut cmd: Commands,
     mut ev_click_sensor: EventReader<ClickSensorEvent>,
     q_sensor: Query<&Parent, With<DefenderClickSensor>>,
-    q_defender: Query<(Entity, &GlobalTransform, &Team), With<Defender>>,
+    q_defender: Query<(Entity, &GlobalTransform, &Team, &GridCoords), With<Defender>>,
     mut inventory: ResMut<Inventory>,
+    mut ev_tool_removed: EventWriter<ToolRemoved>,
 ) {
     for ClickSensorEvent(sensor_entity) in ev_click_sensor.read() {
-        if let Ok((defender_entity, defender_gtr, team)) = q_sensor
+        if let Ok((defender_entity, defender_gtr, team, grid_coords)) = q_sensor
             .get(*sensor_entity)
             .map(|parent| parent.get())
             .and_then(|defender_entity| q_defender.get(defender_entity))
@@ -186,6 +220,11 @@ fn check_click(
                     DraggedDefender,
                 ));
                 cmd.entity(defender_entity).despawn_recursive();
+                ev_tool_removed.send(ToolRemoved {
+                    kind: ToolKind::Defender,
+                    team: *team,
+                    grid_coords: *grid_coords,
+                });
             }
         }
     }
@@ -201,6 +240,7 @@ fn update_robot_motors(
     q_robot_sensor: Query<&Parent, With<DefenderRobotSensor>>,
     mut q_defender: Query<(&mut Defender, &Team, &Transform)>,
     mut q_robot: Query<(&mut EngineDir, &Team, &Transform), With<Robot>>,
+    mut q_last_redirect: Query<&mut LastRedirect>,
     mut delete_robot: Local<DeleteRobots>,
 ) {
     for ev in collision_events.read() {
@@ -212,14 +252,17 @@ fn update_robot_motors(
                         (false, true) => (*e2, *e1),
                         _ => continue,
                     };
-                if let (Ok((_, robot_team, _)), Ok((mut defender, defender_team, _))) = (
-                    q_robot.get(other),
-                    q_robot_sensor
-                        .get(sensor)
-                        .and_then(|parent| q_defender.get_mut(parent.get())),
-                ) {
+                let Ok(e_defender) = q_robot_sensor.get(sensor).map(|parent| parent.get()) else {
+                    continue;
+                };
+                if let (Ok((_, robot_team, _)), Ok((mut defender, defender_team, _))) =
+                    (q_robot.get(other), q_defender.get_mut(e_defender))
+                {
                     if robot_team == defender_team {
                         defender.group.push_back(other);
+                        if let Ok(mut last_redirect) = q_last_redirect.get_mut(other) {
+                            last_redirect.0 = Some((ToolKind::Defender, e_defender));
+                        }
                         while defender.group.len() >= GROUP_SIZE {
                             if let Ok((mut engine_dir, _, _)) =
                                 q_robot.get_mut(defender.group.pop_front().unwrap())
@@ -280,6 +323,7 @@ fn fixup_enemy_defender(
     q_level: Query<Entity, With<LevelIid>>,
     level_size: Res<LevelSize>,
     mut ev_level_loaded: EventReader<LevelLoadedEvent>,
+    defender_shield: Res<DefenderShield>,
 ) {
     for _ in ev_level_loaded.read() {
         for (entity, LdtkDir(ldtk_dir), grid_coords, tr) in &q_defender {
@@ -295,6 +339,7 @@ fn fixup_enemy_defender(
                     Team::Enemy,
                     None,
                     *grid_coords,
+                    defender_shield.0,
                 );
                 cmd.entity(level_entity).add_child(defender);
             }
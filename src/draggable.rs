@@ -8,8 +8,14 @@ use crate::{
     game_camera::MouseWorldCoords,
     levels::{LevelSize, NoPlacingHere, WallCache},
     mouse::{Drag, DragCancelConfirm, DragCancelRequest, DragPos},
+    portal::Portal,
 };
 
+/// Radius of the sensor that lets a tool act on robots.
+pub const TOOL_SENSOR_RADIUS: f32 = 96.;
+// minimal distance in cells between a tool and anything else that blocks placing
+const MIN_PLACING_DISTANCE: f32 = 2.0;
+
 #[derive(Component, PartialEq)]
 pub enum DragState {
     Dragging,
@@ -19,15 +25,47 @@ pub enum DragState {
 #[derive(Component)]
 pub struct ValidDrag;
 
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub enum InvalidDrag {
+    OutOfBounds,
+    Wall,
+    TooCloseToTool,
+    TooCloseToPortal,
+}
+
+impl InvalidDrag {
+    pub fn message(self) -> &'static str {
+        match self {
+            InvalidDrag::OutOfBounds => "out of bounds",
+            InvalidDrag::Wall => "on a wall",
+            InvalidDrag::TooCloseToTool => "too close to another tool",
+            InvalidDrag::TooCloseToPortal => "too close to a portal",
+        }
+    }
+}
+
+/// Direction chosen with the rotate key, followed instead of the cursor until it moves.
+#[derive(Component)]
+pub struct LockedDirection(pub f32);
+
 pub fn validate_drag<DraggedMarker: Component>(
     mut cmd: Commands,
-    mut q_drag: Query<(Entity, &mut Transform, &mut Sprite, &DragState), With<DraggedMarker>>,
+    mut q_drag: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Sprite,
+            &DragState,
+            Option<&LockedDirection>,
+        ),
+        With<DraggedMarker>,
+    >,
     mouse_pos: Res<MouseWorldCoords>,
     q_level: Query<(&GlobalTransform, &WallCache), With<LevelIid>>,
     level_size: Res<LevelSize>,
-    q_occupied: Query<&GridCoords, With<NoPlacingHere>>,
+    q_occupied: Query<(&GridCoords, Has<Portal>), With<NoPlacingHere>>,
 ) {
-    for (entity, mut drag_tr, mut sprite, drag_state) in &mut q_drag {
+    for (entity, mut drag_tr, mut sprite, drag_state, locked) in &mut q_drag {
         match drag_state {
             DragState::Dragging => {
                 cmd.entity(entity).remove::<ValidDrag>();
@@ -40,15 +78,29 @@ pub fn validate_drag<DraggedMarker: Component>(
                             drag_tr.translation.truncate() - level_gtr.translation().truncate(),
                             size_info.tile_size_vec(),
                         );
-                        if size_info.grid_coords_in_bound(coords)
-                            && !wall_cache.items.contains_key(&coords)
-                            && q_occupied.iter().all(|grid_coord| {
-                                (Into::<IVec2>::into(*grid_coord) - Into::<IVec2>::into(coords))
+                        let invalid = if !size_info.grid_coords_in_bound(coords) {
+                            Some(InvalidDrag::OutOfBounds)
+                        } else if wall_cache.items.contains_key(&coords) {
+                            Some(InvalidDrag::Wall)
+                        } else if let Some((_, is_portal)) =
+                            q_occupied.iter().find(|(grid_coord, _)| {
+                                (Into::<IVec2>::into(**grid_coord) - Into::<IVec2>::into(coords))
                                     .as_vec2()
                                     .length()
-                                    >= 2.0
+                                    < MIN_PLACING_DISTANCE
                             })
                         {
+                            Some(if is_portal {
+                                InvalidDrag::TooCloseToPortal
+                            } else {
+                                InvalidDrag::TooCloseToTool
+                            })
+                        } else {
+                            None
+                        };
+                        if let Some(invalid) = invalid {
+                            cmd.entity(entity).insert(invalid);
+                        } else {
                             drag_tr.translation =
                                 grid_coords_to_translation(coords, size_info.tile_size_vec())
                                     .extend(0.0)
@@ -57,11 +109,18 @@ pub fn validate_drag<DraggedMarker: Component>(
                                         .truncate()
                                         .extend(drag_tr.translation.z);
                             sprite.color = Color::WHITE.with_a(1.0);
-                            cmd.entity(entity).insert(ValidDrag).insert(coords);
+                            cmd.entity(entity)
+                                .insert(ValidDrag)
+                                .insert(coords)
+                                .remove::<InvalidDrag>();
                         }
                     }
                 }
             }
+            DragState::SettingDirection(_) if locked.is_some() => {
+                let angle = locked.map_or(0., |locked| locked.0);
+                *drag_tr = drag_tr.with_rotation(Quat::from_rotation_z(angle));
+            }
             DragState::SettingDirection(center_tr) => {
                 if let Some(pos) = mouse_pos.0 {
                     let angle =
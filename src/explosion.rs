@@ -1,22 +1,38 @@
-use std::{f32::consts::PI, time::Duration};
+use std::{borrow::Cow, f32::consts::PI, str::FromStr, time::Duration};
 
 use bevy::{
-    math::{vec2, vec3},
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    math::vec3,
     prelude::*,
+    utils::{BoxedFuture, HashMap, HashSet},
 };
-use bevy_easings::{Ease, EaseMethod, EasingType};
 use bevy_ecs_ldtk::LevelIid;
+use bevy_rapier2d::prelude::*;
 
-use crate::{game::GameState, load::TextureAssets};
-use rand::prelude::*;
+use crate::{
+    game::GameState,
+    gameplay_events::{BulletHit, HitSource, PortalDamaged},
+    hp::{LastHitBy, Life},
+    load::{ExplosionAssets, TextureAssets},
+    physics::{coll_groups, ObjectGroup, Team},
+    pool::{self, Pooled},
+    portal::Portal,
+    status_effect::{deal_damage, ApplyStatusEvent, StatusEffect, StatusEffects},
+};
+use rand::{prelude::*, rngs::StdRng};
 pub struct ExplosionPlugin;
 
 impl Plugin for ExplosionPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<ExplosionEvent>()
+            .init_asset::<ExplosionPresets>()
+            .register_asset_loader(ExplosionPresetsLoader)
+            .insert_resource(ParticleBudget::default())
+            .insert_resource(ParticleRng(StdRng::from_entropy()))
             .add_systems(
                 Update,
-                (watch_for_explosion, run_explosion).run_if(in_state(GameState::Playing)),
+                (watch_for_explosion, run_explosion, apply_splash_damage)
+                    .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 PostUpdate,
@@ -30,6 +46,7 @@ const EXPLOSION_PARTICLE_COUNT: u32 = 5;
 const EXPLOSION_PARTICLE_LIFETIME: Duration = Duration::from_millis(300);
 const EXPLOSION_PARTICLE_SPREAD: f32 = 8.;
 const EXPLOSION_PARTICLE_RADIUS: f32 = 8.;
+const DEFAULT_PARTICLE_BUDGET: usize = 600;
 
 pub const EXPLOSION_COLORS: [Color; 3] = [
     Color::YELLOW,
@@ -37,30 +54,251 @@ pub const EXPLOSION_COLORS: [Color; 3] = [
     Color::rgba(0., 0., 0., 0.),
 ];
 
+pub const ROBOT_DEATH_EXPLOSION: &str = "robot_death";
+pub const PORTAL_DEATH_EXPLOSION: &str = "portal_death";
+pub const PORTAL_SPAWN_EXPLOSION: &str = "portal_spawn";
+pub const BULLET_IMPACT_EXPLOSION: &str = "bullet_impact";
+pub const SHELL_EXPLOSION: &str = "shell_impact";
+
 #[derive(Event)]
 pub struct ExplosionEvent {
     pub location: Vec2,
+    /// Name of a preset in `explosions.presets`.
+    pub preset: Cow<'static, str>,
+    /// Particles move along it, scaled by the preset speed.
+    pub direction: Vec2,
+    pub damage: Option<SplashDamage>,
+}
+
+impl ExplosionEvent {
+    pub fn new(preset: impl Into<Cow<'static, str>>, location: Vec2) -> Self {
+        Self {
+            location,
+            preset: preset.into(),
+            direction: Vec2::ZERO,
+            damage: None,
+        }
+    }
+}
+
+/// Damage dealt to robots and portals within `radius` of an explosion.
+#[derive(Clone, Copy, Debug)]
+pub struct SplashDamage {
+    /// Damage at the center, lowered by `falloff` towards the edge.
+    pub damage: f32,
+    pub radius: f32,
+    pub falloff: Falloff,
+    /// Team of the attacker, only the other team is hurt unless `friendly_fire` is set.
+    pub team: Team,
+    pub friendly_fire: bool,
+    /// Credited with the hits.
+    pub source: Entity,
+    /// Given to everything hit.
+    pub effect: Option<StatusEffect>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Falloff {
+    None,
+    Linear,
+    Quadratic,
+}
+
+impl Falloff {
+    /// Damage factor at `t`, the distance to the center over the radius.
+    fn factor(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Falloff::None => 1.,
+            Falloff::Linear => 1. - t,
+            Falloff::Quadratic => 1. - t * t,
+        }
+    }
+}
+
+/// How particles go through their colors and sizes over their lifetime.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    CubicOut,
+    SineInOut,
+}
+
+impl Easing {
+    fn ease(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1. - (1. - t) * (1. - t),
+            Easing::CubicOut => 1. - (1. - t).powi(3),
+            Easing::SineInOut => (1. - (t * PI).cos()) / 2.,
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = ();
+
+    fn from_str(word: &str) -> Result<Self, ()> {
+        match word {
+            "linear" => Ok(Easing::Linear),
+            "quad_in" => Ok(Easing::QuadIn),
+            "quad_out" => Ok(Easing::QuadOut),
+            "cubic_out" => Ok(Easing::CubicOut),
+            "sine_in_out" => Ok(Easing::SineInOut),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExplosionPreset {
     pub colors: [Color; 3],
     pub spread: f32,
     pub particle_radius: f32,
-    pub particle_speed: Vec2,
+    pub particle_speed: f32,
     pub duration: Duration,
     pub particle_duration: Duration,
     pub particle_count: u32,
+    pub easing: Easing,
 }
 
-impl Default for ExplosionEvent {
+impl Default for ExplosionPreset {
     fn default() -> Self {
         Self {
-            location: Default::default(),
             colors: EXPLOSION_COLORS,
             spread: EXPLOSION_PARTICLE_SPREAD,
             particle_radius: EXPLOSION_PARTICLE_RADIUS,
-            particle_speed: Vec2::ZERO,
+            particle_speed: 0.,
             duration: EXPLOSION_DURATION,
             particle_duration: EXPLOSION_PARTICLE_LIFETIME,
             particle_count: EXPLOSION_PARTICLE_COUNT,
+            easing: Easing::Linear,
+        }
+    }
+}
+
+/// Named presets loaded from a `.presets` file. Each preset starts with a `preset <name>`
+/// line followed by `<key> <values>` lines, values left out keep their defaults.
+/// Lines starting with `#` are comments.
+#[derive(Asset, TypePath, Default, Clone)]
+pub struct ExplosionPresets(pub HashMap<String, ExplosionPreset>);
+
+impl ExplosionPresets {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut presets = ExplosionPresets::default();
+        let mut current = None;
+        for (index, line) in text.lines().enumerate() {
+            parse_preset_line(&mut presets, &mut current, line)
+                .map_err(|err| format!("line {}: {}", index + 1, err))?;
+        }
+        Ok(presets)
+    }
+}
+
+fn parse_preset_line(
+    presets: &mut ExplosionPresets,
+    current: &mut Option<String>,
+    line: &str,
+) -> Result<(), String> {
+    let mut words = line.split_whitespace();
+    let key = match words.next() {
+        None => return Ok(()),
+        Some(key) if key.starts_with('#') => return Ok(()),
+        Some(key) => key,
+    };
+    let values: Vec<&str> = words.collect();
+    if key == "preset" {
+        let [name] = values.as_slice() else {
+            return Err("usage: preset <name>".to_string());
+        };
+        presets
+            .0
+            .insert(name.to_string(), ExplosionPreset::default());
+        *current = Some(name.to_string());
+        return Ok(());
+    }
+    let preset = current
+        .as_ref()
+        .and_then(|name| presets.0.get_mut(name))
+        .ok_or_else(|| format!("'{}' before any preset", key))?;
+    let floats = values
+        .iter()
+        .map(|word| parse_value::<f32>(word))
+        .collect::<Result<Vec<_>, _>>();
+    let single = |floats: Vec<f32>| match floats.as_slice() {
+        [value] if *value >= 0. => Ok(*value),
+        _ => Err(format!("usage: {} <positive number>", key)),
+    };
+    match key {
+        "colors" => {
+            let floats = floats?;
+            if floats.len() != 12 {
+                return Err("usage: colors <r g b a> <r g b a> <r g b a>".to_string());
+            }
+            for (color, rgba) in preset.colors.iter_mut().zip(floats.chunks(4)) {
+                *color = Color::rgba(rgba[0], rgba[1], rgba[2], rgba[3]);
+            }
         }
+        "spread" => preset.spread = single(floats?)?,
+        "radius" => preset.particle_radius = single(floats?)?,
+        "speed" => preset.particle_speed = single(floats?)?,
+        "duration" => preset.duration = Duration::from_secs_f32(single(floats?)?),
+        "particle_duration" => preset.particle_duration = Duration::from_secs_f32(single(floats?)?),
+        "count" => match values.as_slice() {
+            [count] => {
+                preset.particle_count = parse_value(count)?;
+                if preset.particle_count == 0 {
+                    return Err("count must be at least 1".to_string());
+                }
+            }
+            _ => return Err("usage: count <particles>".to_string()),
+        },
+        "easing" => match values.as_slice() {
+            [easing] => preset.easing = parse_value(easing)?,
+            _ => {
+                return Err(
+                    "usage: easing <linear|quad_in|quad_out|cubic_out|sine_in_out>".to_string(),
+                )
+            }
+        },
+        _ => return Err(format!("unknown key '{}'", key)),
+    }
+    Ok(())
+}
+
+fn parse_value<T: FromStr>(word: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("invalid value '{}'", word))
+}
+
+#[derive(Default)]
+struct ExplosionPresetsLoader;
+
+impl AssetLoader for ExplosionPresetsLoader {
+    type Asset = ExplosionPresets;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<ExplosionPresets, Self::Error>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            ExplosionPresets::parse(&text)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["presets"]
     }
 }
 
@@ -68,6 +306,9 @@ impl Default for ExplosionEvent {
 struct ExplosionParticle {
     lifetime: Timer,
     speed: Vec2,
+    colors: [Color; 3],
+    radius: f32,
+    easing: Easing,
 }
 
 #[derive(Component)]
@@ -79,142 +320,306 @@ struct Explosion {
     particle_radius: f32,
     particle_speed: Vec2,
     particle_duration: Duration,
+    easing: Easing,
     //duration : Duration,
 }
 
+/// Particles are cosmetic and how many get emitted depends on the frame rate, so they draw
+/// from their own source to keep `GameRng` replays identical.
+#[derive(Resource)]
+struct ParticleRng(StdRng);
+
+/// Cap on live explosion particles. Past half of it explosions start skipping particles,
+/// more and more often until none are emitted at the cap.
+#[derive(Resource)]
+pub struct ParticleBudget {
+    pub max_particles: usize,
+}
+
+impl Default for ParticleBudget {
+    fn default() -> Self {
+        Self {
+            max_particles: DEFAULT_PARTICLE_BUDGET,
+        }
+    }
+}
+
+impl ParticleBudget {
+    fn emit_chance(&self, live_particles: usize) -> f32 {
+        if self.max_particles == 0 {
+            return 0.;
+        }
+        let soft_limit = self.max_particles as f32 / 2.;
+        1. - ((live_particles as f32 - soft_limit) / soft_limit).clamp(0., 1.)
+    }
+}
+
+/// Unknown presets fall back to the default one, with a warning the first time.
 fn watch_for_explosion(
     mut cmd: Commands,
     mut ev_explosion: EventReader<ExplosionEvent>,
     q_level: Query<Entity, With<LevelIid>>,
+    mut q_free: Query<(Entity, &mut Explosion, &mut Transform), With<Pooled>>,
+    explosion_assets: Res<ExplosionAssets>,
+    presets: Res<Assets<ExplosionPresets>>,
+    mut warned: Local<HashSet<String>>,
 ) {
+    let presets = presets.get(&explosion_assets.presets);
+    let default_preset = ExplosionPreset::default();
+    let mut free = q_free.iter_mut();
     for ev in ev_explosion.read() {
         if let Ok(e_level) = q_level.get_single() {
-            let particle_interval =
-                Duration::from_secs_f32(ev.duration.as_secs_f32() / ev.particle_count as f32);
+            let preset = match presets.and_then(|presets| presets.0.get(ev.preset.as_ref())) {
+                Some(preset) => preset,
+                None => {
+                    if warned.insert(ev.preset.to_string()) {
+                        warn!("unknown explosion preset '{}'", ev.preset);
+                    }
+                    &default_preset
+                }
+            };
+            let particle_interval = Duration::from_secs_f32(
+                preset.duration.as_secs_f32() / preset.particle_count as f32,
+            );
             let mut next_particle = Timer::new(particle_interval, TimerMode::Once);
             next_particle.tick(particle_interval);
-            let e_explosion = cmd
-                .spawn((
-                    TransformBundle::from_transform(Transform::from_translation(
-                        ev.location.extend(10.0),
-                    )),
-                    Explosion {
-                        lifetime: Timer::new(ev.duration, TimerMode::Once),
-                        next_particle,
-                        colors: ev.colors,
-                        spread: ev.spread,
-                        particle_radius: ev.particle_radius,
-                        particle_speed: ev.particle_speed,
-                        particle_duration: ev.particle_duration,
-                    },
-                ))
-                .id();
-            cmd.entity(e_level).add_child(e_explosion);
+            let explosion = Explosion {
+                lifetime: Timer::new(preset.duration, TimerMode::Once),
+                next_particle,
+                colors: preset.colors,
+                spread: preset.spread,
+                particle_radius: preset.particle_radius,
+                particle_speed: ev.direction * preset.particle_speed,
+                particle_duration: preset.particle_duration,
+                easing: preset.easing,
+            };
+            let tr = Transform::from_translation(ev.location.extend(10.0));
+            if let Some((e_explosion, mut pooled_explosion, mut pooled_tr)) = free.next() {
+                *pooled_explosion = explosion;
+                *pooled_tr = tr;
+                pool::reuse(&mut cmd, e_explosion);
+            } else {
+                let e_explosion = cmd
+                    .spawn((
+                        TransformBundle::from_transform(tr),
+                        VisibilityBundle::default(),
+                        explosion,
+                    ))
+                    .id();
+                cmd.entity(e_level).add_child(e_explosion);
+            }
         }
     }
 }
 
 fn run_explosion(
     mut cmd: Commands,
-    mut q_explosion: Query<(Entity, &Transform, &Parent, &mut Explosion)>,
+    mut q_explosion: Query<(Entity, &Transform, &Parent, &mut Explosion), Without<Pooled>>,
+    q_live_particle: Query<(), (With<ExplosionParticle>, Without<Pooled>)>,
+    mut q_free_particle: Query<
+        (Entity, &mut ExplosionParticle, &mut Transform, &mut Sprite),
+        With<Pooled>,
+    >,
     time: Res<Time>,
     textures: Res<TextureAssets>,
+    budget: Res<ParticleBudget>,
+    mut rng: ResMut<ParticleRng>,
 ) {
+    let mut live_particles = q_live_particle.iter().count();
+    let mut free = q_free_particle.iter_mut();
     for (e_explosion, tr, parent, mut explosion) in &mut q_explosion {
         explosion.lifetime.tick(time.delta());
         explosion.next_particle.tick(time.delta());
         if explosion.next_particle.finished() {
-            let mut rng = thread_rng();
+            let rng = &mut rng.0;
             explosion.next_particle.reset();
-            let delta_pos = Quat::from_rotation_z(rng.gen_range(0.0..2.0 * PI))
-                .mul_vec3(vec3(1.0, 0.0, 0.0) * rng.gen_range(0.0..explosion.spread));
-            spawn_particle(
-                parent.get(),
-                &mut cmd,
-                tr.translation + delta_pos,
-                &textures,
-                &explosion.colors,
-                explosion.particle_radius,
-                explosion.particle_speed,
-                explosion.particle_duration,
-            );
+            if rng.gen::<f32>() < budget.emit_chance(live_particles) {
+                live_particles += 1;
+                let delta_pos = Quat::from_rotation_z(rng.gen_range(0.0..2.0 * PI))
+                    .mul_vec3(vec3(1.0, 0.0, 0.0) * rng.gen_range(0.0..explosion.spread));
+                let particle = ExplosionParticle {
+                    lifetime: Timer::new(explosion.particle_duration, TimerMode::Once),
+                    speed: explosion.particle_speed,
+                    colors: explosion.colors,
+                    radius: explosion.particle_radius,
+                    easing: explosion.easing,
+                };
+                let pos = tr.translation + delta_pos;
+                if let Some((e_particle, mut pooled_particle, mut particle_tr, mut sprite)) =
+                    free.next()
+                {
+                    *pooled_particle = particle;
+                    particle_tr.translation = pos;
+                    sprite.custom_size = Some(Vec2::ZERO);
+                    pool::reuse(&mut cmd, e_particle);
+                } else {
+                    let e_particle = cmd
+                        .spawn((
+                            particle,
+                            SpriteBundle {
+                                transform: Transform::from_translation(pos),
+                                texture: textures.explosion_particle.clone(),
+                                sprite: Sprite {
+                                    custom_size: Some(Vec2::ZERO),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                        ))
+                        .id();
+                    cmd.entity(parent.get()).add_child(e_particle);
+                }
+            }
         }
         if explosion.lifetime.finished() {
-            cmd.entity(e_explosion).despawn_recursive();
+            pool::release(&mut cmd, e_explosion);
         }
     }
 }
 
-fn spawn_particle(
-    parent: Entity,
-    cmd: &mut Commands,
-    pos: Vec3,
-    textures: &Res<TextureAssets>,
-    colors: &[Color; 3],
-    radius: f32,
-    speed: Vec2,
-    duration: Duration,
-) {
-    let size = Some(Vec2::splat(radius * 2.));
-    let ease_step_time = Duration::from_secs_f32(duration.as_secs_f32() / 2.0);
-    let e_particle = cmd
-        .spawn((
-            ExplosionParticle {
-                lifetime: Timer::new(duration, TimerMode::Once),
-                speed,
-            },
-            SpriteBundle {
-                transform: Transform::from_translation(pos),
-                texture: textures.explosion_particle.clone(),
-                sprite: Sprite {
-                    custom_size: Some(vec2(0.0, 0.0)),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            Sprite {
-                custom_size: size,
-                color: colors[0],
-                ..Default::default()
-            }
-            .ease_to(
-                Sprite {
-                    custom_size: size.map(|v| v / 3.0),
-                    color: colors[1],
-                    ..Default::default()
-                },
-                EaseMethod::Linear,
-                EasingType::Once {
-                    duration: ease_step_time,
-                },
-            )
-            .ease_to(
-                Sprite {
-                    custom_size: Some(vec2(0.0, 0.0)),
-                    color: colors[2],
-                    ..Default::default()
-                },
-                EaseMethod::Linear,
-                EasingType::Once {
-                    duration: ease_step_time,
-                },
-            ),
-        ))
-        .id();
-    cmd.entity(parent).add_child(e_particle);
-}
-
+// particles shrink from full size to a third while fading to the second color,
+// then to nothing while fading to the third
 fn particle_life(
     mut cmd: Commands,
-    mut q_particle: Query<(Entity, &mut ExplosionParticle, &mut Transform)>,
+    mut q_particle: Query<
+        (Entity, &mut ExplosionParticle, &mut Transform, &mut Sprite),
+        Without<Pooled>,
+    >,
     time: Res<Time>,
 ) {
-    for (e_particle, mut particle, mut particle_tr) in &mut q_particle {
+    for (e_particle, mut particle, mut particle_tr, mut sprite) in &mut q_particle {
         particle.lifetime.tick(time.delta());
         if particle.lifetime.finished() {
-            cmd.entity(e_particle).despawn_recursive();
+            pool::release(&mut cmd, e_particle);
         } else {
             particle_tr.translation += particle.speed.extend(0.0);
+            let size = particle.radius * 2.;
+            let t = particle.easing.ease(particle.lifetime.percent()) * 2.;
+            let (color, size) = if t < 1. {
+                (
+                    lerp_color(particle.colors[0], particle.colors[1], t),
+                    size + (size / 3. - size) * t,
+                )
+            } else {
+                (
+                    lerp_color(particle.colors[1], particle.colors[2], t - 1.),
+                    size / 3. * (2. - t),
+                )
+            };
+            sprite.color = color;
+            sprite.custom_size = Some(Vec2::splat(size));
+        }
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let [r1, g1, b1, a1] = from.as_rgba_f32();
+    let [r2, g2, b2, a2] = to.as_rgba_f32();
+    Color::rgba(
+        r1 + (r2 - r1) * t,
+        g1 + (g2 - g1) * t,
+        b1 + (b2 - b1) * t,
+        a1 + (a2 - a1) * t,
+    )
+}
+
+fn apply_splash_damage(
+    mut cmd: Commands,
+    mut ev_explosion: EventReader<ExplosionEvent>,
+    rapier_context: Res<RapierContext>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    mut q_target: Query<(
+        &GlobalTransform,
+        &mut Life,
+        Option<&mut StatusEffects>,
+        &Team,
+        Has<Portal>,
+    )>,
+    mut ev_bullet_hit: EventWriter<BulletHit>,
+    mut ev_portal_damaged: EventWriter<PortalDamaged>,
+    mut ev_apply_status: EventWriter<ApplyStatusEvent>,
+) {
+    let Ok(level_gtr) = q_level.get_single() else {
+        return;
+    };
+    let level_offset = level_gtr.translation().truncate();
+    for ev in ev_explosion.read() {
+        let Some(splash) = ev.damage else {
+            continue;
+        };
+        // bullet groups are what robots and portals of the other team accept hits from
+        let mut members = match splash.team {
+            Team::Player => ObjectGroup::PLAYER_BULLET,
+            Team::Enemy => ObjectGroup::ENEMY_BULLET,
+        };
+        let mut filters = match splash.team {
+            Team::Player => ObjectGroup::ENEMY_ROBOT | ObjectGroup::ENEMY_PORTAL,
+            Team::Enemy => ObjectGroup::PLAYER_ROBOT | ObjectGroup::PLAYER_PORTAL,
+        };
+        if splash.friendly_fire {
+            members = ObjectGroup::PLAYER_BULLET | ObjectGroup::ENEMY_BULLET;
+            filters = ObjectGroup::PLAYER_ROBOT
+                | ObjectGroup::ENEMY_ROBOT
+                | ObjectGroup::PLAYER_PORTAL
+                | ObjectGroup::ENEMY_PORTAL;
+        }
+        let filter = QueryFilter {
+            groups: Some(coll_groups(members, filters)),
+            ..Default::default()
+        };
+        let center = ev.location + level_offset;
+        let mut hits = vec![];
+        rapier_context.intersections_with_shape(
+            center,
+            0.,
+            &Collider::ball(splash.radius),
+            filter,
+            |other| {
+                hits.push(other);
+                true
+            },
+        );
+        for other in hits {
+            let Ok((gtr, mut life, effects, team, is_portal)) = q_target.get_mut(other) else {
+                continue;
+            };
+            // already dying
+            if life.curr_hp <= 0. {
+                continue;
+            }
+            let pos = gtr.translation().truncate();
+            let t = pos.distance(center) / splash.radius;
+            let damage = (splash.damage * splash.falloff.factor(t) * 10.).round() / 10.;
+            if damage <= 0. {
+                continue;
+            }
+            let damage = deal_damage(&mut life, effects, damage);
+            cmd.entity(other).try_insert(LastHitBy {
+                shooter: splash.source,
+                team: splash.team,
+            });
+            if let Some(effect) = splash.effect {
+                ev_apply_status.send(ApplyStatusEvent {
+                    target: other,
+                    effect,
+                });
+            }
+            ev_bullet_hit.send(BulletHit {
+                target: other,
+                shooter: splash.source,
+                damage,
+                position: pos - level_offset,
+                source: HitSource::Splash,
+            });
+            if is_portal {
+                ev_portal_damaged.send(PortalDamaged {
+                    portal: other,
+                    team: *team,
+                    damage,
+                    remaining_hp: life.curr_hp,
+                    shooter: splash.source,
+                });
+            }
         }
     }
 }
@@ -12,14 +12,19 @@ use bevy_ecs_ldtk::{
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    draggable::{drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag},
+    draggable::{
+        drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag,
+        TOOL_SENSOR_RADIUS,
+    },
     game::GameState,
+    gameplay_events::{ToolKind, ToolPlaced, ToolRemoved},
     inventory::Inventory,
     levels::{LevelLoadedEvent, LevelSize, NoPlacingHere},
     load::TextureAssets,
     mouse::{ClickSensor, ClickSensorEvent, Drag, DragDropConfirm, DragDropRequest, DragPos},
     physics::{coll_groups, ObjectGroup, Team},
-    robot::{EngineDir, Robot},
+    robot::{EngineDir, LastRedirect, Robot},
+    sprite_animation::{SpriteAnimation, TOOL_IDLE},
 };
 
 pub struct ForkPlugin;
@@ -102,8 +107,8 @@ struct ForkRobotSensor;
 
 #[derive(Component)]
 pub struct Fork {
-    dirs: [Vec2; 2],
-    forked_count: usize,
+    pub dirs: [Vec2; 2],
+    pub forked_count: usize,
 }
 
 fn drop_request(
@@ -121,6 +126,7 @@ fn drop_request(
     assets: Res<TextureAssets>,
     mut inventory: ResMut<Inventory>,
     q_level: Query<(Entity, &GlobalTransform), With<LevelIid>>,
+    mut ev_tool_placed: EventWriter<ToolPlaced>,
 ) {
     for (entity, drag_tr, maybe_valid, mut drag_state, grid_coords) in &mut q_drag {
         if maybe_valid.is_some() {
@@ -145,6 +151,12 @@ fn drop_request(
                         *grid_coords,
                     );
                     cmd.entity(level_entity).add_child(fork);
+                    ev_tool_placed.send(ToolPlaced {
+                        tool: fork,
+                        kind: ToolKind::Fork,
+                        team: Team::Player,
+                        grid_coords: *grid_coords,
+                    });
                 }
             }
         } else {
@@ -153,12 +165,12 @@ fn drop_request(
     }
 }
 
-fn spawn_fork(
+pub fn spawn_fork(
     cmd: &mut Commands,
     tr: Transform,
     dir: Vec2,
     team: Team,
-    texture: Option<Handle<Image>>,
+    texture: Option<Handle<TextureAtlas>>,
     grid_coords: GridCoords,
 ) -> Entity {
     let dirs = [
@@ -181,11 +193,12 @@ fn spawn_fork(
         .id();
     if team == Team::Player {
         cmd.entity(fork_entity).insert((
-            SpriteBundle {
-                texture: texture.unwrap(),
+            SpriteSheetBundle {
+                texture_atlas: texture.unwrap(),
                 transform: tr,
                 ..Default::default()
             },
+            SpriteAnimation::new(TOOL_IDLE),
             NoPlacingHere,
         ));
     } else {
@@ -205,7 +218,7 @@ fn spawn_fork(
         cmd.spawn((
             ForkRobotSensor,
             Sensor,
-            Collider::ball(96.),
+            Collider::ball(TOOL_SENSOR_RADIUS),
             match team {
                 Team::Player => {
                     coll_groups(ObjectGroup::PLAYER_FORK_SENSOR, ObjectGroup::PLAYER_ROBOT)
@@ -225,11 +238,12 @@ fn check_click(
     mut cmd: Commands,
     mut ev_click_sensor: EventReader<ClickSensorEvent>,
     q_sensor: Query<&Parent, With<ForkClickSensor>>,
-    q_fork: Query<(Entity, &GlobalTransform, &Team), With<Fork>>,
+    q_fork: Query<(Entity, &GlobalTransform, &Team, &GridCoords), With<Fork>>,
     mut inventory: ResMut<Inventory>,
+    mut ev_tool_removed: EventWriter<ToolRemoved>,
 ) {
     for ClickSensorEvent(sensor_entity) in ev_click_sensor.read() {
-        if let Ok((fork_entity, fork_gtr, team)) = q_sensor
+        if let Ok((fork_entity, fork_gtr, team, grid_coords)) = q_sensor
             .get(*sensor_entity)
             .map(|parent| parent.get())
             .and_then(|fork_entity| q_fork.get(fork_entity))
@@ -242,6 +256,11 @@ fn check_click(
                     DraggedFork,
                 ));
                 cmd.entity(fork_entity).despawn_recursive();
+                ev_tool_removed.send(ToolRemoved {
+                    kind: ToolKind::Fork,
+                    team: *team,
+                    grid_coords: *grid_coords,
+                });
             }
         }
     }
@@ -251,7 +270,7 @@ fn update_robot_motors(
     mut collision_events: EventReader<CollisionEvent>,
     q_robot_sensor: Query<&Parent, With<ForkRobotSensor>>,
     mut q_fork: Query<(&mut Fork, &Team)>,
-    mut q_robot: Query<(&mut EngineDir, &Team), With<Robot>>,
+    mut q_robot: Query<(&mut EngineDir, &mut LastRedirect, &Team), With<Robot>>,
 ) {
     for ev in collision_events.read() {
         if let CollisionEvent::Started(e1, e2, _) = ev {
@@ -261,15 +280,18 @@ fn update_robot_motors(
                 (false, true) => (*e2, *e1),
                 _ => continue,
             };
-            if let (Ok((mut engine_dir, robot_team)), Ok((mut fork, fork_team))) = (
-                q_robot.get_mut(other),
-                q_robot_sensor
-                    .get(sensor)
-                    .and_then(|parent| q_fork.get_mut(parent.get())),
-            ) {
+            let Ok(e_fork) = q_robot_sensor.get(sensor).map(|parent| parent.get()) else {
+                continue;
+            };
+            if let (
+                Ok((mut engine_dir, mut last_redirect, robot_team)),
+                Ok((mut fork, fork_team)),
+            ) = (q_robot.get_mut(other), q_fork.get_mut(e_fork))
+            {
                 if robot_team == fork_team {
                     engine_dir.0 = fork.dirs[fork.forked_count % 2];
                     fork.forked_count += 1;
+                    last_redirect.0 = Some((ToolKind::Fork, e_fork));
                 }
             }
         }
@@ -4,25 +4,45 @@ use bevy_ecs_ldtk::assets::LdtkProject;
 use bevy_ecs_ldtk::LdtkPlugin;
 
 use crate::arrow::ArrowPlugin;
+use crate::audio::GameAudioPlugin;
+use crate::benchmark::BenchmarkPlugin;
 use crate::bullet::BulletPlugin;
+use crate::camera_effects::CameraEffectsPlugin;
+use crate::console::ConsolePlugin;
+use crate::controls::ControlsPlugin;
+use crate::debug_overlay::DebugOverlayPlugin;
 use crate::defender::DefenderPlugin;
 use crate::explosion::ExplosionPlugin;
 use crate::fork::ForkPlugin;
 use crate::game_ui::{GameUiPlugin, MainMenuEvent};
+use crate::gameplay_events::GameplayEventsPlugin;
+use crate::ghost::GhostSimulationPlugin;
 use crate::grouper::GrouperPlugin;
+use crate::health_bar::HealthBarPlugin;
 use crate::hp::HpPlugin;
+use crate::intro::IntroPlugin;
 use crate::inventory::InventoryPlugin;
 use crate::levels::LevelsPlugin;
 use crate::load::LoadPlugin;
 use crate::menu::MenuPlugin;
+use crate::minimap::MinimapPlugin;
 use crate::mouse::MousePlugin;
+use crate::placement_preview::PlacementPreviewPlugin;
 use crate::portal::PortalPlugin;
+use crate::savestate::SaveStatePlugin;
+use crate::score::ScorePlugin;
+use crate::selection::SelectionPlugin;
+use crate::sprite_animation::SpriteAnimationPlugin;
+use crate::status_effect::StatusEffectPlugin;
+use crate::touch::TouchControlsPlugin;
+use crate::weapon::WeaponPlugin;
 use crate::{game_camera::GameCameraPlugin, robot::RobotPlugin};
 
 use bevy_rapier2d::plugin::{NoUserData, RapierPhysicsPlugin};
 use bevy_rapier2d::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
 
-const PIXELS_PER_METER: f32 = 8.0;
+pub const PIXELS_PER_METER: f32 = 8.0;
 
 #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum GameState {
@@ -37,11 +57,23 @@ pub enum GameState {
 pub enum LevelState {
     #[default]
     WaitingLevelSpawn,
+    Intro,
+    Planning,
     Playing,
     Win,
     Loss,
 }
 
+/// Shared random source for gameplay, so a run can be replayed with the `seed` console command.
+#[derive(Resource)]
+pub struct GameRng(pub StdRng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
@@ -54,6 +86,8 @@ impl Plugin for GamePlugin {
                 RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER),
                 LdtkPlugin,
                 MousePlugin,
+                ControlsPlugin,
+                TouchControlsPlugin,
                 LevelsPlugin,
                 MenuPlugin,
                 InventoryPlugin,
@@ -65,16 +99,32 @@ impl Plugin for GamePlugin {
                 ForkPlugin,
                 GrouperPlugin,
                 DefenderPlugin,
+                MinimapPlugin,
+                SelectionPlugin,
+                PlacementPreviewPlugin,
+                GhostSimulationPlugin,
+                SpriteAnimationPlugin,
+                IntroPlugin,
+                CameraEffectsPlugin,
+                StatusEffectPlugin,
+                WeaponPlugin,
             ),
             BulletPlugin,
             HpPlugin,
+            HealthBarPlugin,
+            GameplayEventsPlugin,
+            GameAudioPlugin,
+            ScorePlugin,
             ExplosionPlugin,
             EasingsPlugin,
+            DebugOverlayPlugin,
+            (ConsolePlugin, SaveStatePlugin, BenchmarkPlugin),
         ))
         .insert_resource(RapierConfiguration {
             gravity: Vect::new(0.0, 0.0),
             ..Default::default()
         })
+        .insert_resource(GameRng::default())
         .add_state::<GameState>()
         .add_state::<LevelState>()
         .add_systems(
@@ -1,13 +1,28 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{prelude::*, utils::HashMap, window::PrimaryWindow};
 use bevy_ecs_ldtk::prelude::*;
 
-use crate::{game::GameState, levels::LevelLoadedEvent};
+use crate::{
+    controls::{Action, Actions, VirtualCursor},
+    game::GameState,
+    intro::{extract_intro_path, IntroPath},
+    levels::{LevelIndex, LevelLoadedEvent, LevelSize},
+    selection::Selection,
+    touch::TouchCursor,
+};
 
 // const CAMERA_SPEED: f32 = 100.0;
 
 const CAMERA_DEFAULT_SCALE: f32 = 0.25;
+// zoom range until a level gives a better one
 const CAMERA_MIN_SCALE: f32 = 0.2;
 const CAMERA_MAX_SCALE: f32 = 0.45;
+// screen pixels per tile when zoomed in the most
+const MAX_TILE_PIXELS: f32 = 80.;
+// room left around the level when it is fit to the window
+const FIT_MARGIN: f32 = 1.1;
+// rate at which the camera closes the distance to its target, per second
+const CAMERA_SMOOTHING: f32 = 6.;
+pub const CAMERA_BOOKMARK_COUNT: usize = 4;
 pub struct GameCameraPlugin;
 
 #[derive(Resource, Default)]
@@ -23,8 +38,15 @@ impl Plugin for GameCameraPlugin {
             .insert_resource(MouseScreenCoords::default())
             .insert_resource(CameraTargetPos(Vec3::ZERO))
             .insert_resource(CameraTargetScale::new())
+            .insert_resource(CameraBookmarks::default())
             //maybe we'll have to put fixup_camera_start in a preupdate
             .add_systems(Update, (update_mouse_coords, fixup_camera_start))
+            .add_systems(
+                Update,
+                (update_scale_range, fit_level, camera_bookmarks)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
             .add_systems(
                 PostUpdate,
                 (move_camera, scale_camera).run_if(in_state(GameState::Playing)),
@@ -39,8 +61,19 @@ pub struct MainCamera;
 #[derive(Resource, Default)]
 pub struct CameraTargetPos(pub Vec3);
 
+/// Zoom the camera eases to. The allowed range follows the level and window sizes, from
+/// `MAX_TILE_PIXELS` per tile to the whole level on screen.
 #[derive(Resource)]
-pub struct CameraTargetScale(f32);
+pub struct CameraTargetScale {
+    scale: f32,
+    min: f32,
+    max: f32,
+}
+
+/// Camera positions and zooms stored per level index, recalled with F1-F4 and stored
+/// with Ctrl held.
+#[derive(Resource, Default)]
+pub struct CameraBookmarks(pub HashMap<usize, [Option<(Vec3, f32)>; CAMERA_BOOKMARK_COUNT]>);
 
 #[derive(Component, Default)]
 pub struct CameraStart;
@@ -48,6 +81,8 @@ pub struct CameraStart;
 #[derive(Bundle, LdtkEntity, Default)]
 pub struct CameraStartBundle {
     camera_start: CameraStart,
+    #[with(extract_intro_path)]
+    intro_path: IntroPath,
     #[grid_coords]
     grid_coords: GridCoords,
 }
@@ -58,7 +93,7 @@ pub struct CameraStartBundle {
 //     }
 // }
 
-fn fixup_camera_start(
+pub fn fixup_camera_start(
     mut cmd: Commands,
     mut ev_level_loaded: EventReader<LevelLoadedEvent>,
     q_camera_start: Query<(Entity, &GlobalTransform), With<CameraStart>>,
@@ -76,7 +111,7 @@ fn fixup_camera_start(
             //println!("{}", gtr.translation());
             //println!("{}", q_level.single().translation());
             camera_target_pos.0 = gtr.translation();
-            camera_target_scale.0 = CAMERA_DEFAULT_SCALE;
+            camera_target_scale.set(CAMERA_DEFAULT_SCALE);
             cmd.entity(entity).despawn_recursive();
         }
     }
@@ -84,18 +119,99 @@ fn fixup_camera_start(
 
 impl CameraTargetScale {
     pub fn new() -> Self {
-        Self(CAMERA_DEFAULT_SCALE)
+        Self {
+            scale: CAMERA_DEFAULT_SCALE,
+            min: CAMERA_MIN_SCALE,
+            max: CAMERA_MAX_SCALE,
+        }
     }
 
     pub fn get(&self) -> f32 {
-        self.0
+        self.scale
     }
 
     pub fn set(&mut self, value: f32) -> f32 {
-        let value = value.clamp(CAMERA_MIN_SCALE, CAMERA_MAX_SCALE);
-        self.0 = value;
+        let value = value.clamp(self.min, self.max);
+        self.scale = value;
         value
     }
+
+    pub fn range(&self) -> (f32, f32) {
+        (self.min, self.max)
+    }
+
+    /// Zoom that shows the whole level.
+    pub fn fit(&mut self) {
+        self.scale = self.max;
+    }
+
+    fn set_range(&mut self, min: f32, max: f32) {
+        self.min = min;
+        self.max = max.max(min);
+        self.set(self.scale);
+    }
+}
+
+fn update_scale_range(
+    mut target_scale: ResMut<CameraTargetScale>,
+    level_size: Res<LevelSize>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+) {
+    let (Some(size_info), Ok(window)) = (level_size.0, q_window.get_single()) else {
+        return;
+    };
+    if window.width() <= 0. || window.height() <= 0. {
+        return;
+    }
+    let min = size_info.tile_size as f32 / MAX_TILE_PIXELS;
+    let fit = (size_info.pixel_size().as_vec2() / Vec2::new(window.width(), window.height()))
+        .max_element()
+        * FIT_MARGIN;
+    if target_scale.range() != (min, fit.max(min)) {
+        target_scale.set_range(min, fit);
+    }
+}
+
+fn fit_level(
+    actions: Actions,
+    mut target_pos: ResMut<CameraTargetPos>,
+    mut target_scale: ResMut<CameraTargetScale>,
+    mut selection: ResMut<Selection>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    level_size: Res<LevelSize>,
+) {
+    if !actions.just_pressed(Action::FitLevel) {
+        return;
+    }
+    if let (Ok(level_gtr), Some(size_info)) = (q_level.get_single(), level_size.0) {
+        let center = level_gtr.translation().truncate() + size_info.pixel_size().as_vec2() / 2.;
+        target_pos.0 = center.extend(target_pos.0.z);
+        target_scale.fit();
+        selection.follow = false;
+    }
+}
+
+fn camera_bookmarks(
+    actions: Actions,
+    level_index: Res<LevelIndex>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut target_pos: ResMut<CameraTargetPos>,
+    mut target_scale: ResMut<CameraTargetScale>,
+    mut selection: ResMut<Selection>,
+) {
+    for i in 0..CAMERA_BOOKMARK_COUNT {
+        if !actions.just_pressed(Action::CameraBookmark(i)) {
+            continue;
+        }
+        let level_bookmarks = bookmarks.0.entry(level_index.0).or_default();
+        if actions.pressed(Action::StoreCameraBookmark) {
+            level_bookmarks[i] = Some((target_pos.0, target_scale.get()));
+        } else if let Some((pos, scale)) = level_bookmarks[i] {
+            target_pos.0 = pos;
+            target_scale.set(scale);
+            selection.follow = false;
+        }
+    }
 }
 
 fn init(mut cmd: Commands) {
@@ -112,6 +228,8 @@ fn update_mouse_coords(
     mut mouse_screen_coords: ResMut<MouseScreenCoords>,
     q_window: Query<&Window, With<PrimaryWindow>>,
     q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    virtual_cursor: Res<VirtualCursor>,
+    touch_cursor: Res<TouchCursor>,
 ) {
     // get the camera info and transform
     // assuming there is exactly one main camera entity, so Query::single() is OK
@@ -122,31 +240,44 @@ fn update_mouse_coords(
 
     // check if the cursor is inside the window and get its position
     // then, ask bevy to convert into world coordinates, and truncate to discard Z
-    mouse_world_coords.0 = window
-        .cursor_position()
+    let cursor_position = window.cursor_position().or(touch_cursor.0);
+    mouse_world_coords.0 = cursor_position
         .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
         .map(|ray| ray.origin.truncate());
-    mouse_screen_coords.0 = window.cursor_position();
+    mouse_screen_coords.0 = cursor_position;
+
+    // the gamepad cursor takes over the mouse while it is in use
+    if virtual_cursor.active {
+        mouse_world_coords.0 = Some(virtual_cursor.pos);
+        mouse_screen_coords.0 =
+            camera.world_to_viewport(camera_transform, virtual_cursor.pos.extend(0.0));
+    }
+}
+
+/// Fraction of the way to the target covered this frame, the same at any frame rate.
+/// Real time keeps the camera moving while the game is paused or slowed down.
+fn smoothing(time: &Time<Real>) -> f32 {
+    1.0 - (-CAMERA_SMOOTHING * time.delta_seconds()).exp()
 }
 
-fn move_camera(
+pub fn move_camera(
     mut q_camera: Query<&mut Transform, With<MainCamera>>,
     camera_target_pos: Res<CameraTargetPos>,
-    // time: Res<Time>,
+    time: Res<Time<Real>>,
 ) {
+    let t = smoothing(&time);
     for mut tr in q_camera.iter_mut() {
-        let d_pos = camera_target_pos.0 - tr.translation;
-        // let speed = delta_d / time.
-        let new_loc = tr.translation + d_pos / 10.0;
-        tr.translation = new_loc;
+        tr.translation = tr.translation.lerp(camera_target_pos.0, t);
     }
 }
 
 fn scale_camera(
     mut q_camera: Query<&mut OrthographicProjection, With<MainCamera>>,
     target_scale: Res<CameraTargetScale>,
+    time: Res<Time<Real>>,
 ) {
+    let t = smoothing(&time);
     for mut projection in &mut q_camera {
-        projection.scale = projection.scale + (target_scale.0 - projection.scale) / 10.0;
+        projection.scale += (target_scale.get() - projection.scale) * t;
     }
 }
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{input::touch::Touches, prelude::*};
 
 use crate::{
     arrow::DraggedArrow,
@@ -10,8 +10,9 @@ use crate::{
     game_camera::MouseWorldCoords,
     grouper::DraggedGrouper,
     inventory::Inventory,
-    levels::{LevelCount, LevelIndex, LevelTitle, MaxAttainableLevel},
-    mouse::{Drag, DragPos, MouseState},
+    levels::{LevelCount, LevelIndex, LevelTitle, MaxAttainableLevel, PuzzleLevel},
+    mouse::{Drag, DragCancelRequest, DragPos, MouseState},
+    score::{compute_score, LevelScore},
 };
 
 pub struct GameUiPlugin;
@@ -21,11 +22,29 @@ impl Plugin for GameUiPlugin {
         app.add_event::<ResetLevelEvent>()
             .add_event::<ChangeLevelEvent>()
             .add_event::<MainMenuEvent>()
+            .add_event::<StartLevelEvent>()
+            .add_event::<SimulateAheadEvent>()
             // .add_systems(OnEnter(GameState::Playing), instanciate)
             // .add_systems(OnExit(GameState::Playing), destroy)
-            .add_systems(OnEnter(LevelState::Playing), instanciate_ui)
+            .add_systems(OnEnter(LevelState::Planning), instanciate_ui)
+            // resetting a transformed level goes back through the intro
+            .add_systems(OnEnter(LevelState::Intro), destroy_ui)
+            .add_systems(
+                OnEnter(LevelState::Playing),
+                (destroy_go_button, lock_tools),
+            )
             .add_systems(OnExit(LevelState::Playing), destroy_ui)
-            .add_systems(OnEnter(LevelState::Win), instanciate_win_screen)
+            .add_systems(
+                OnTransition {
+                    from: LevelState::Planning,
+                    to: LevelState::WaitingLevelSpawn,
+                },
+                destroy_ui,
+            )
+            .add_systems(
+                OnEnter(LevelState::Win),
+                instanciate_win_screen.after(compute_score),
+            )
             .add_systems(OnExit(LevelState::Win), destroy_win_screen)
             .add_systems(OnEnter(LevelState::Loss), instanciate_loss_screen)
             .add_systems(OnExit(LevelState::Loss), destroy_loss_screen)
@@ -40,11 +59,15 @@ impl Plugin for GameUiPlugin {
                     update_level_title,
                 ),
             )
-            .add_systems(Update, check_disabled.run_if(in_state(LevelState::Playing)))
+            .add_systems(
+                Update,
+                check_disabled
+                    .run_if(in_state(LevelState::Planning).or_else(in_state(LevelState::Playing))),
+            )
             .add_systems(
                 PostUpdate,
                 (maybe_disable_previous_level, maybe_disable_next_level)
-                    .run_if(in_state(LevelState::Playing)),
+                    .run_if(in_state(LevelState::Planning).or_else(in_state(LevelState::Playing))),
             );
     }
 }
@@ -76,6 +99,12 @@ struct PreviousLevelButton;
 #[derive(Component)]
 struct MainMenuButton;
 
+#[derive(Component)]
+struct GoButton;
+
+#[derive(Component)]
+struct PlaceableButton;
+
 #[derive(Component)]
 enum ButtonType {
     Arrow,
@@ -86,6 +115,8 @@ enum ButtonType {
     NextLevel,
     PreviousLevel,
     MainMenu,
+    Go,
+    SimulateAhead,
 }
 
 #[derive(Component)]
@@ -104,11 +135,18 @@ pub struct ResetLevelEvent;
 pub enum ChangeLevelEvent {
     Next,
     Previous,
+    Index(usize),
 }
 
 #[derive(Event)]
 pub struct MainMenuEvent;
 
+#[derive(Event)]
+pub struct StartLevelEvent;
+
+#[derive(Event)]
+pub struct SimulateAheadEvent;
+
 #[derive(Component)]
 struct LevelTitleText;
 #[derive(Component)]
@@ -136,6 +174,8 @@ const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const PRESSED_BUTTON: Color = Color::rgb(0.9, 0.9, 0.9);
 const DISABLED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.3);
+const STAR_ON: Color = Color::rgb(1.0, 0.85, 0.2);
+const STAR_OFF: Color = Color::rgb(0.3, 0.3, 0.3);
 
 fn maybe_disable_next_level(
     mut cmd: Commands,
@@ -229,12 +269,22 @@ fn button_system(
     mut ev_reset_level: EventWriter<ResetLevelEvent>,
     mut ev_next_level: EventWriter<ChangeLevelEvent>,
     mut ev_main_menu: EventWriter<MainMenuEvent>,
+    mut ev_start_level: EventWriter<StartLevelEvent>,
+    mut ev_simulate_ahead: EventWriter<SimulateAheadEvent>,
+    touches: Res<Touches>,
 ) {
     for (interaction, mut color, mut border_color, mut button_state, button_type, disabled) in
         &mut interaction_query
     {
         //let text = &mut text_query.get_mut(children[0]).unwrap().sections[0];
         if disabled.is_none() {
+            // a lifted finger doesn't hover, so the button goes straight from pressed to none
+            let clicked = matches!(*button_state, ButtonState::Down)
+                && match *interaction {
+                    Interaction::Hovered => true,
+                    Interaction::None => touches.any_just_released(),
+                    Interaction::Pressed => false,
+                };
             match *interaction {
                 Interaction::Pressed => {
                     *color = PRESSED_BUTTON.into();
@@ -246,65 +296,7 @@ fn button_system(
                     *color = HOVERED_BUTTON.into();
                     border_color.0 = Color::WHITE;
                     //text.style.color = Color::rgb(0.9, 0.9, 0.9);
-                    match *button_state {
-                        ButtonState::Down => {
-                            match button_type {
-                                ButtonType::Arrow => {
-                                    if inventory.arrow_count > 0
-                                        && *mouse_state != MouseState::Dragging
-                                    {
-                                        cmd.spawn((
-                                            Drag,
-                                            DragPos(mouse_pos.0.unwrap()),
-                                            DraggedArrow,
-                                        ));
-                                    }
-                                }
-                                ButtonType::Reset => ev_reset_level.send(ResetLevelEvent),
-                                ButtonType::NextLevel => {
-                                    // info!("send next level event from button {:?}", e_button);
-                                    ev_next_level.send(ChangeLevelEvent::Next);
-                                }
-                                ButtonType::PreviousLevel => {
-                                    ev_next_level.send(ChangeLevelEvent::Previous)
-                                }
-                                ButtonType::MainMenu => ev_main_menu.send(MainMenuEvent),
-                                ButtonType::Fork => {
-                                    if inventory.fork_count > 0
-                                        && *mouse_state != MouseState::Dragging
-                                    {
-                                        cmd.spawn((
-                                            Drag,
-                                            DragPos(mouse_pos.0.unwrap()),
-                                            DraggedFork,
-                                        ));
-                                    }
-                                }
-                                ButtonType::Grouper => {
-                                    if inventory.grouper_count > 0
-                                        && *mouse_state != MouseState::Dragging
-                                    {
-                                        cmd.spawn((
-                                            Drag,
-                                            DragPos(mouse_pos.0.unwrap()),
-                                            DraggedGrouper,
-                                        ));
-                                    }
-                                }
-                                ButtonType::Defender => {
-                                    if inventory.defender_count > 0 {
-                                        cmd.spawn((
-                                            Drag,
-                                            DragPos(mouse_pos.0.unwrap()),
-                                            DraggedDefender,
-                                        ));
-                                    }
-                                }
-                            }
-                            *button_state = ButtonState::None;
-                        }
-                        _ => *button_state = ButtonState::None,
-                    }
+                    *button_state = ButtonState::None;
                 }
                 Interaction::None => {
                     *color = NORMAL_BUTTON.into();
@@ -313,6 +305,39 @@ fn button_system(
                     *button_state = ButtonState::None;
                 }
             }
+            if clicked {
+                match button_type {
+                    ButtonType::Arrow => {
+                        if inventory.arrow_count > 0 && *mouse_state != MouseState::Dragging {
+                            cmd.spawn((Drag, DragPos(mouse_pos.0.unwrap()), DraggedArrow));
+                        }
+                    }
+                    ButtonType::Reset => ev_reset_level.send(ResetLevelEvent),
+                    ButtonType::NextLevel => {
+                        // info!("send next level event from button {:?}", e_button);
+                        ev_next_level.send(ChangeLevelEvent::Next);
+                    }
+                    ButtonType::PreviousLevel => ev_next_level.send(ChangeLevelEvent::Previous),
+                    ButtonType::MainMenu => ev_main_menu.send(MainMenuEvent),
+                    ButtonType::Go => ev_start_level.send(StartLevelEvent),
+                    ButtonType::SimulateAhead => ev_simulate_ahead.send(SimulateAheadEvent),
+                    ButtonType::Fork => {
+                        if inventory.fork_count > 0 && *mouse_state != MouseState::Dragging {
+                            cmd.spawn((Drag, DragPos(mouse_pos.0.unwrap()), DraggedFork));
+                        }
+                    }
+                    ButtonType::Grouper => {
+                        if inventory.grouper_count > 0 && *mouse_state != MouseState::Dragging {
+                            cmd.spawn((Drag, DragPos(mouse_pos.0.unwrap()), DraggedGrouper));
+                        }
+                    }
+                    ButtonType::Defender => {
+                        if inventory.defender_count > 0 {
+                            cmd.spawn((Drag, DragPos(mouse_pos.0.unwrap()), DraggedDefender));
+                        }
+                    }
+                }
+            }
         }
     }
 }
@@ -324,6 +349,28 @@ fn destroy_ui(mut cmd: Commands, q_game_ui: Query<Entity, With<GameUi>>) {
     }
 }
 
+fn destroy_go_button(mut cmd: Commands, q_go_button: Query<Entity, With<GoButton>>) {
+    for entity in &q_go_button {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn lock_tools(
+    mut cmd: Commands,
+    puzzle_level: Res<PuzzleLevel>,
+    q_placeable_button: Query<Entity, With<PlaceableButton>>,
+    q_drag: Query<Entity, With<Drag>>,
+) {
+    if puzzle_level.0 {
+        for entity in &q_placeable_button {
+            cmd.entity(entity).try_insert(ButtonDisabled);
+        }
+        for entity in &q_drag {
+            cmd.entity(entity).insert(DragCancelRequest);
+        }
+    }
+}
+
 fn instanciate_ui(mut cmd: Commands, asset_server: Res<AssetServer>) {
     info!("Instanciating game UI");
     cmd.spawn((
@@ -584,6 +631,63 @@ fn instanciate_ui(mut cmd: Commands, asset_server: Res<AssetServer>) {
                     DefenderButton,
                     DefenderButtonText,
                 );
+                cmd.spawn((
+                    GoButton,
+                    ButtonState::None,
+                    ButtonType::Go,
+                    ButtonBundle {
+                        style: Style {
+                            height: Val::VMin(8.),
+                            padding: UiRect::horizontal(Val::VMin(2.)),
+                            border: UiRect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::all(Val::Px(6.0)),
+                            ..Default::default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: NORMAL_BUTTON.into(),
+                        ..Default::default()
+                    },
+                ))
+                .with_children(|cmd| {
+                    cmd.spawn(TextBundle::from_section(
+                        "Go",
+                        TextStyle {
+                            font: asset_server.load("GeoFont-Bold.otf"),
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                });
+                cmd.spawn((
+                    ButtonState::None,
+                    ButtonType::SimulateAhead,
+                    ButtonBundle {
+                        style: Style {
+                            height: Val::VMin(8.),
+                            padding: UiRect::horizontal(Val::VMin(2.)),
+                            border: UiRect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::all(Val::Px(6.0)),
+                            ..Default::default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: NORMAL_BUTTON.into(),
+                        ..Default::default()
+                    },
+                ))
+                .with_children(|cmd| {
+                    cmd.spawn(TextBundle::from_section(
+                        "Preview",
+                        TextStyle {
+                            font: asset_server.load("GeoFont-Bold.otf"),
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                });
             });
         });
     });
@@ -618,6 +722,7 @@ fn spawn_placeable_button<ButtonMarker: Component, TextMarker: Component>(
             ..Default::default()
         },
         ButtonState::None,
+        PlaceableButton,
         button_marker,
         button_type,
     ))
@@ -762,6 +867,7 @@ fn instanciate_win_screen(
     asset_server: Res<AssetServer>,
     level_count: Res<LevelCount>,
     level_index: Res<LevelIndex>,
+    score: Res<LevelScore>,
 ) {
     info!("instanciating win screen");
     cmd.spawn((
@@ -829,6 +935,7 @@ fn instanciate_win_screen(
                 );
                 cmd.spawn(text_bundle);
             });
+            spawn_score_breakdown(cmd, &asset_server, &score);
             cmd.spawn(NodeBundle {
                 style: Style {
                     padding: UiRect {
@@ -918,6 +1025,99 @@ fn instanciate_win_screen(
     });
 }
 
+fn spawn_score_breakdown(
+    cmd: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    score: &LevelScore,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("GeoFont-Bold.otf"),
+        font_size: 24.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let seconds = score.time as u32;
+    let rows = [
+        (
+            format!("Time {}:{:02}", seconds / 60, seconds % 60),
+            score.time_bonus,
+        ),
+        (
+            format!("Tools used {}/{}", score.tools_used, score.tools_given),
+            score.tools_bonus,
+        ),
+        (
+            format!("Robots lost {}", score.robots_lost),
+            score.robots_bonus,
+        ),
+        (
+            format!("Portal HP {}%", (score.portal_hp * 100.).round()),
+            score.portal_bonus,
+        ),
+    ];
+    cmd.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect {
+                top: Val::Vh(1.),
+                left: Val::Vh(20.),
+                right: Val::Vh(20.),
+                bottom: Val::Vh(1.),
+            },
+            ..Default::default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+        ..Default::default()
+    })
+    .with_children(|cmd| {
+        for (label, points) in rows {
+            cmd.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    column_gap: Val::Vh(4.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with_children(|cmd| {
+                cmd.spawn(TextBundle::from_section(label, text_style.clone()));
+                cmd.spawn(TextBundle::from_section(
+                    format!("+{}", points),
+                    text_style.clone(),
+                ));
+            });
+        }
+        let best = match score.best {
+            _ if score.unranked => " (unranked)".to_string(),
+            Some(best) if best.score >= score.total => format!(" (best {})", best.score),
+            Some(_) => " (new best !)".to_string(),
+            None => "".to_string(),
+        };
+        cmd.spawn(TextBundle::from_section(
+            format!("Score {}{}", score.total, best),
+            TextStyle {
+                font_size: 32.0,
+                ..text_style.clone()
+            },
+        ));
+        cmd.spawn(TextBundle::from_sections((1..=3).map(|star| {
+            TextSection::new(
+                "* ",
+                TextStyle {
+                    font_size: 64.0,
+                    color: if star <= score.stars {
+                        STAR_ON
+                    } else {
+                        STAR_OFF
+                    },
+                    ..text_style.clone()
+                },
+            )
+        })));
+    });
+}
+
 #[derive(Component)]
 struct LossScreen;
 
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
+
+use crate::physics::Team;
+
+pub struct GameplayEventsPlugin;
+
+impl Plugin for GameplayEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RobotSpawned>()
+            .add_event::<RobotKilled>()
+            .add_event::<BulletHit>()
+            .add_event::<PortalDamaged>()
+            .add_event::<PortalDestroyed>()
+            .add_event::<ToolPlaced>()
+            .add_event::<ToolRemoved>();
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum ToolKind {
+    Arrow,
+    Fork,
+    Grouper,
+    Defender,
+}
+
+#[derive(Event)]
+pub struct RobotSpawned {
+    pub robot: Entity,
+    pub team: Team,
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct RobotKilled {
+    pub victim: Entity,
+    pub team: Team,
+    pub killer: Option<Entity>,
+    pub killer_team: Option<Team>,
+    pub position: Vec2,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum HitSource {
+    Bullet,
+    Beam,
+    Splash,
+}
+
+#[derive(Event)]
+pub struct BulletHit {
+    pub target: Entity,
+    pub shooter: Entity,
+    pub damage: f32,
+    pub position: Vec2,
+    pub source: HitSource,
+}
+
+#[derive(Event)]
+pub struct PortalDamaged {
+    pub portal: Entity,
+    pub team: Team,
+    pub damage: f32,
+    pub remaining_hp: f32,
+    pub shooter: Entity,
+}
+
+#[derive(Event)]
+pub struct PortalDestroyed {
+    pub portal: Entity,
+    pub team: Team,
+    pub killer_team: Option<Team>,
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct ToolPlaced {
+    pub tool: Entity,
+    pub kind: ToolKind,
+    pub team: Team,
+    pub grid_coords: GridCoords,
+}
+
+#[derive(Event)]
+pub struct ToolRemoved {
+    pub kind: ToolKind,
+    pub team: Team,
+    pub grid_coords: GridCoords,
+}
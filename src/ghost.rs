@@ -0,0 +1,453 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::{
+        schedule::ExecutorKind,
+        system::{RunSystemOnce, SystemParam, SystemState},
+    },
+    math::vec2,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::HashMap,
+};
+use bevy_ecs_ldtk::{utils::grid_coords_to_translation, LevelIid};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    arrow::ArrowPlugin,
+    bullet::BulletPlugin,
+    console::{Console, ConsoleCommand},
+    defender::{DefenderPlugin, DefenderShield},
+    explosion::{ExplosionPlugin, ExplosionPresets, ParticleBudget},
+    fork::ForkPlugin,
+    game::{GameRng, GameState, LevelState, PIXELS_PER_METER},
+    game_camera::{CameraTargetScale, MouseWorldCoords},
+    game_ui::{ResetLevelEvent, SimulateAheadEvent},
+    gameplay_events::{GameplayEventsPlugin, ToolPlaced, ToolRemoved},
+    grouper::GrouperPlugin,
+    hp::{HpPlugin, Life},
+    inventory::Inventory,
+    levels::{wall_collider, LevelLoadedEvent, LevelSize, WallCache},
+    load::{ExplosionAssets, TextureAssets},
+    mouse::ClickSensorEvent,
+    physics::Team,
+    portal::{Portal, PortalPlugin},
+    robot::{DeathSplash, Robot, RobotPlugin},
+    savestate::{skip_restored_contacts, LevelRestore, LevelSnapshot, RestoredRobots},
+    status_effect::{StatusEffectPlugin, StatusZone},
+    weapon::{AimAccuracy, WeaponPlugin},
+};
+
+const SIMULATION_DURATION: f32 = 20.;
+const SIMULATION_STEP: f32 = 1. / 30.;
+// 20 s take two seconds at 30 frames per second
+const STEPS_PER_FRAME: usize = 10;
+const TRACE_INTERVAL: f32 = 0.25;
+const GHOST_ALPHA: f32 = 0.35;
+const GHOST_END_RADIUS: f32 = 2.;
+const LABEL_OFFSET: f32 = 44.;
+const LABEL_Z: f32 = 50.;
+
+pub struct GhostSimulationPlugin;
+
+impl Plugin for GhostSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GhostPreview::default())
+            .insert_non_send_resource(GhostSimulation::default())
+            .add_systems(
+                Update,
+                (
+                    clear_on_change,
+                    start_ghost_simulation,
+                    step_ghost_simulation,
+                    draw_ghost_traces,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_ghosts);
+    }
+}
+
+/// Outcome of the last "simulate ahead", in level coordinates. Traces grow while the
+/// simulation runs.
+#[derive(Resource, Default)]
+pub struct GhostPreview {
+    pub traces: Vec<(Team, Vec<Vec2>)>,
+    /// Damage taken by each portal and whether it was destroyed.
+    pub portal_damage: Vec<(Entity, f32, bool)>,
+}
+
+#[derive(Component)]
+struct GhostLabel;
+
+/// Headless copy of the level running the gameplay plugins with a fixed timestep. An `App`
+/// isn't `Send`, so it is kept as a non-send resource.
+#[derive(Default)]
+struct GhostSimulation(Option<GhostRun>);
+
+struct GhostRun {
+    app: App,
+    elapsed: f32,
+    next_trace: f32,
+    /// Index in `GhostPreview::traces` of each ghost robot.
+    traces: HashMap<Entity, usize>,
+    portals: Vec<GhostPortal>,
+    level_offset: Vec2,
+}
+
+struct GhostPortal {
+    source: Entity,
+    ghost: Entity,
+    team: Team,
+    pos: Vec2,
+    start_hp: f32,
+}
+
+impl GhostRun {
+    fn step(&mut self, preview: &mut GhostPreview) {
+        self.app.update();
+        self.elapsed += SIMULATION_STEP;
+        if self.elapsed >= self.next_trace {
+            self.next_trace += TRACE_INTERVAL;
+            self.record_traces(preview);
+        }
+    }
+
+    fn record_traces(&mut self, preview: &mut GhostPreview) {
+        let world = &mut self.app.world;
+        let mut q_robot = world.query_filtered::<(Entity, &Transform, &Team), With<Robot>>();
+        for (entity, tr, team) in q_robot.iter(world) {
+            let index = *self.traces.entry(entity).or_insert_with(|| {
+                preview.traces.push((*team, vec![]));
+                preview.traces.len() - 1
+            });
+            preview.traces[index].1.push(tr.translation.truncate());
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.elapsed >= SIMULATION_DURATION
+    }
+}
+
+/// Everything the ghost level is copied from.
+#[derive(SystemParam)]
+struct GhostSetup<'w, 's> {
+    snapshot: LevelSnapshot<'w, 's>,
+    q_level: Query<
+        'w,
+        's,
+        (
+            &'static LevelIid,
+            &'static GlobalTransform,
+            &'static WallCache,
+        ),
+    >,
+    q_portal: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Team,
+            &'static Life,
+            &'static Portal,
+        ),
+    >,
+    q_zone: Query<'w, 's, (&'static StatusZone, &'static GlobalTransform)>,
+    level_size: Res<'w, LevelSize>,
+    assets: Res<'w, TextureAssets>,
+    explosion_assets: Res<'w, ExplosionAssets>,
+    presets: Res<'w, Assets<ExplosionPresets>>,
+    aim_accuracy: Res<'w, AimAccuracy>,
+    death_splash: Res<'w, DeathSplash>,
+    defender_shield: Res<'w, DefenderShield>,
+    rng: Res<'w, GameRng>,
+}
+
+impl GhostSetup<'_, '_> {
+    fn start(&self) -> Option<GhostRun> {
+        let (Ok((level_iid, level_gtr, wall_cache)), Some(size_info)) =
+            (self.q_level.get_single(), self.level_size.0)
+        else {
+            return None;
+        };
+
+        let mut app = ghost_app();
+        let presets = self
+            .presets
+            .get(&self.explosion_assets.presets)
+            .cloned()
+            .unwrap_or_default();
+        let presets = app
+            .world
+            .resource_mut::<Assets<ExplosionPresets>>()
+            .add(presets);
+        app.insert_resource(self.assets.clone())
+            .insert_resource(ExplosionAssets { presets })
+            .insert_resource(LevelSize(Some(size_info)))
+            .insert_resource(AimAccuracy {
+                player: self.aim_accuracy.player,
+                enemy: self.aim_accuracy.enemy,
+            })
+            .insert_resource(DeathSplash {
+                damage: self.death_splash.damage,
+                radius: self.death_splash.radius,
+            })
+            .insert_resource(DefenderShield(self.defender_shield.0))
+            .insert_resource(GameRng(self.rng.0.clone()));
+
+        let world = &mut app.world;
+        let e_level = world
+            .spawn((
+                level_iid.clone(),
+                SpatialBundle::from_transform(Transform::from_translation(level_gtr.translation())),
+            ))
+            .id();
+        for grid_coords in wall_cache.items.keys() {
+            let pos = grid_coords_to_translation(*grid_coords, size_info.tile_size_vec());
+            world
+                .spawn((
+                    wall_collider(size_info),
+                    TransformBundle::from_transform(Transform::from_translation(pos.extend(0.))),
+                ))
+                .set_parent(e_level);
+        }
+        let portals = self
+            .q_portal
+            .iter()
+            .map(|(entity, tr, team, life, portal)| GhostPortal {
+                source: entity,
+                ghost: world
+                    .spawn((portal.clone(), *team, TransformBundle::from_transform(*tr)))
+                    .set_parent(e_level)
+                    .id(),
+                team: *team,
+                pos: tr.translation.truncate(),
+                start_hp: life.curr_hp,
+            })
+            .collect();
+        for (zone, zone_gtr) in &self.q_zone {
+            world.spawn((
+                zone.clone(),
+                TransformBundle::from_transform(Transform::from_translation(
+                    zone_gtr.translation(),
+                )),
+            ));
+        }
+
+        // portals get their life and colliders on the first update
+        app.update();
+        let mut restore = SystemState::<LevelRestore>::new(&mut app.world);
+        restore
+            .get_mut(&mut app.world)
+            .restore(&self.snapshot.capture(true));
+        restore.apply(&mut app.world);
+        app.update();
+        app.world.run_system_once(skip_restored_contacts);
+
+        Some(GhostRun {
+            app,
+            elapsed: SIMULATION_STEP,
+            next_trace: 0.,
+            traces: HashMap::default(),
+            portals,
+            level_offset: level_gtr.translation().truncate(),
+        })
+    }
+}
+
+fn ghost_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..default()
+        },
+        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER),
+        (
+            RobotPlugin,
+            PortalPlugin,
+            ArrowPlugin,
+            ForkPlugin,
+            GrouperPlugin,
+            DefenderPlugin,
+            WeaponPlugin,
+            BulletPlugin,
+            HpPlugin,
+            StatusEffectPlugin,
+            ExplosionPlugin,
+            GameplayEventsPlugin,
+        ),
+    ))
+    .insert_resource(RapierConfiguration {
+        gravity: Vect::ZERO,
+        timestep_mode: TimestepMode::Fixed {
+            dt: SIMULATION_STEP,
+            substeps: 1,
+        },
+        ..Default::default()
+    })
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        SIMULATION_STEP,
+    )))
+    // nobody sees the ghost explosions
+    .insert_resource(ParticleBudget { max_particles: 0 })
+    .insert_resource(Console::default())
+    .insert_resource(Inventory::default())
+    .insert_resource(RestoredRobots::default())
+    .insert_resource(MouseWorldCoords(None))
+    .insert_resource(CameraTargetScale::new())
+    .add_event::<ClickSensorEvent>()
+    .add_event::<LevelLoadedEvent>()
+    .add_event::<ConsoleCommand>()
+    .add_state::<GameState>()
+    .add_state::<LevelState>()
+    .insert_resource(State::new(GameState::Playing))
+    .insert_resource(State::new(LevelState::Planning));
+    // the ghost level is too small to be worth spreading over threads
+    let single_threaded = |schedule: &mut Schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    };
+    app.edit_schedule(PreUpdate, single_threaded)
+        .edit_schedule(Update, single_threaded)
+        .edit_schedule(PostUpdate, single_threaded);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+fn clear_on_change(
+    mut cmd: Commands,
+    mut preview: ResMut<GhostPreview>,
+    mut simulation: NonSendMut<GhostSimulation>,
+    q_label: Query<Entity, With<GhostLabel>>,
+    mut ev_tool_placed: EventReader<ToolPlaced>,
+    mut ev_tool_removed: EventReader<ToolRemoved>,
+    mut ev_reset_level: EventReader<ResetLevelEvent>,
+    level_state: Res<State<LevelState>>,
+) {
+    let changed = ev_tool_placed.read().count() > 0
+        || ev_tool_removed.read().count() > 0
+        || ev_reset_level.read().count() > 0
+        || level_state.is_changed();
+    if changed {
+        *preview = GhostPreview::default();
+        simulation.0 = None;
+        for entity in &q_label {
+            cmd.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn despawn_ghosts(
+    mut cmd: Commands,
+    mut preview: ResMut<GhostPreview>,
+    mut simulation: NonSendMut<GhostSimulation>,
+    q_label: Query<Entity, With<GhostLabel>>,
+) {
+    *preview = GhostPreview::default();
+    simulation.0 = None;
+    for entity in &q_label {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn start_ghost_simulation(
+    mut cmd: Commands,
+    mut ev_simulate_ahead: EventReader<SimulateAheadEvent>,
+    mut preview: ResMut<GhostPreview>,
+    mut simulation: NonSendMut<GhostSimulation>,
+    q_label: Query<Entity, With<GhostLabel>>,
+    setup: GhostSetup,
+) {
+    if ev_simulate_ahead.read().count() == 0 {
+        return;
+    }
+    *preview = GhostPreview::default();
+    for entity in &q_label {
+        cmd.entity(entity).despawn_recursive();
+    }
+    simulation.0 = setup.start();
+}
+
+fn step_ghost_simulation(
+    mut cmd: Commands,
+    mut preview: ResMut<GhostPreview>,
+    mut simulation: NonSendMut<GhostSimulation>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(run) = simulation.0.as_mut() else {
+        return;
+    };
+    for _ in 0..STEPS_PER_FRAME {
+        run.step(&mut preview);
+        if run.finished() {
+            break;
+        }
+    }
+    if !run.finished() {
+        return;
+    }
+
+    // end every trace where its robot stands now
+    run.record_traces(&mut preview);
+    let world = &run.app.world;
+    for portal in &run.portals {
+        // destroyed portals are despawned
+        let hp = world
+            .get::<Life>(portal.ghost)
+            .map_or(0., |life| life.curr_hp.max(0.));
+        let destroyed = hp <= 0.;
+        let damage = portal.start_hp - hp;
+        preview
+            .portal_damage
+            .push((portal.source, damage, destroyed));
+        let label = if destroyed {
+            "destroyed".to_string()
+        } else {
+            format!("-{} hp", damage)
+        };
+        cmd.spawn((
+            GhostLabel,
+            Text2dBundle {
+                text: Text::from_section(
+                    label,
+                    TextStyle {
+                        font: asset_server.load("GeoFont-Bold.otf"),
+                        font_size: 24.0,
+                        color: portal.team.tint().with_a(0.8),
+                    },
+                ),
+                transform: Transform::from_translation(
+                    (portal.pos + run.level_offset + vec2(0., LABEL_OFFSET)).extend(LABEL_Z),
+                )
+                .with_scale(Vec3::splat(0.25)),
+                ..Default::default()
+            },
+        ));
+    }
+    simulation.0 = None;
+}
+
+fn draw_ghost_traces(
+    mut gizmos: Gizmos,
+    preview: Res<GhostPreview>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+) {
+    let Ok(level_gtr) = q_level.get_single() else {
+        return;
+    };
+    let offset = level_gtr.translation().truncate();
+    for (team, trace) in &preview.traces {
+        let color = team.tint().with_a(GHOST_ALPHA);
+        gizmos.linestrip_2d(trace.iter().map(|pos| *pos + offset), color);
+        if let Some(end) = trace.last() {
+            gizmos.circle_2d(*end + offset, GHOST_END_RADIUS, color);
+        }
+    }
+}
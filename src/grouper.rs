@@ -13,14 +13,19 @@ use bevy_ecs_ldtk::{
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    draggable::{drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag},
+    draggable::{
+        drag_cancel_request, draggable_spawner, validate_drag, DragState, ValidDrag,
+        TOOL_SENSOR_RADIUS,
+    },
     game::GameState,
+    gameplay_events::{ToolKind, ToolPlaced, ToolRemoved},
     inventory::Inventory,
     levels::{LevelLoadedEvent, LevelSize, NoPlacingHere},
     load::TextureAssets,
     mouse::{ClickSensor, ClickSensorEvent, Drag, DragDropConfirm, DragDropRequest, DragPos},
     physics::{coll_groups, ObjectGroup, Team},
-    robot::{EngineDir, Robot},
+    robot::{EngineDir, LastRedirect, Robot},
+    sprite_animation::{SpriteAnimation, TOOL_IDLE},
 };
 
 const GROUP_SIZE: usize = 25;
@@ -104,8 +109,8 @@ struct GrouperRobotSensor;
 
 #[derive(Component)]
 pub struct Grouper {
-    dir: Vec2,
-    group: HashSet<Entity>,
+    pub dir: Vec2,
+    pub group: HashSet<Entity>,
 }
 
 fn drop_request(
@@ -123,6 +128,7 @@ fn drop_request(
     assets: Res<TextureAssets>,
     mut inventory: ResMut<Inventory>,
     q_level: Query<(Entity, &GlobalTransform), With<LevelIid>>,
+    mut ev_tool_placed: EventWriter<ToolPlaced>,
 ) {
     for (entity, drag_tr, maybe_valid, mut drag_state, grid_coords) in &mut q_drag {
         if maybe_valid.is_some() {
@@ -147,6 +153,12 @@ fn drop_request(
                         *grid_coords,
                     );
                     cmd.entity(level_entity).add_child(grouper);
+                    ev_tool_placed.send(ToolPlaced {
+                        tool: grouper,
+                        kind: ToolKind::Grouper,
+                        team: Team::Player,
+                        grid_coords: *grid_coords,
+                    });
                 }
             }
         } else {
@@ -155,12 +167,12 @@ fn drop_request(
     }
 }
 
-fn spawn_grouper(
+pub fn spawn_grouper(
     cmd: &mut Commands,
     tr: Transform,
     dir: Vec2,
     team: Team,
-    texture: Option<Handle<Image>>,
+    texture: Option<Handle<TextureAtlas>>,
     grid_coords: GridCoords,
 ) -> Entity {
     let spawned_entity = cmd
@@ -175,11 +187,12 @@ fn spawn_grouper(
         .id();
     if team == Team::Player {
         cmd.entity(spawned_entity).insert((
-            SpriteBundle {
-                texture: texture.unwrap(),
+            SpriteSheetBundle {
+                texture_atlas: texture.unwrap(),
                 transform: tr,
                 ..Default::default()
             },
+            SpriteAnimation::new(TOOL_IDLE),
             NoPlacingHere,
         ));
     } else {
@@ -199,7 +212,7 @@ fn spawn_grouper(
         cmd.spawn((
             GrouperRobotSensor,
             Sensor,
-            Collider::ball(96.),
+            Collider::ball(TOOL_SENSOR_RADIUS),
             match team {
                 Team::Player => coll_groups(
                     ObjectGroup::PLAYER_GROUPER_SENSOR,
@@ -220,11 +233,12 @@ fn check_click(
     mut cmd: Commands,
     mut ev_click_sensor: EventReader<ClickSensorEvent>,
     q_sensor: Query<&Parent, With<GrouperClickSensor>>,
-    q_grouper: Query<(Entity, &GlobalTransform, &Team), With<Grouper>>,
+    q_grouper: Query<(Entity, &GlobalTransform, &Team, &GridCoords), With<Grouper>>,
     mut inventory: ResMut<Inventory>,
+    mut ev_tool_removed: EventWriter<ToolRemoved>,
 ) {
     for ClickSensorEvent(sensor_entity) in ev_click_sensor.read() {
-        if let Ok((arrow_entity, arrow_gtr, team)) = q_sensor
+        if let Ok((arrow_entity, arrow_gtr, team, grid_coords)) = q_sensor
             .get(*sensor_entity)
             .map(|parent| parent.get())
             .and_then(|arrow_entity| q_grouper.get(arrow_entity))
@@ -237,6 +251,11 @@ fn check_click(
                     DraggedGrouper,
                 ));
                 cmd.entity(arrow_entity).despawn_recursive();
+                ev_tool_removed.send(ToolRemoved {
+                    kind: ToolKind::Grouper,
+                    team: *team,
+                    grid_coords: *grid_coords,
+                });
             }
         }
     }
@@ -250,6 +269,7 @@ fn update_robot_motors(
     q_robot_sensor: Query<&Parent, With<GrouperRobotSensor>>,
     mut q_grouper: Query<(&mut Grouper, &Team, &Transform)>,
     mut q_robot: Query<(&mut EngineDir, &Team, &Transform), With<Robot>>,
+    mut q_last_redirect: Query<&mut LastRedirect>,
     mut delete_robot: Local<DeleteRobots>,
 ) {
     for ev in collision_events.read() {
@@ -261,14 +281,17 @@ fn update_robot_motors(
                         (false, true) => (*e2, *e1),
                         _ => continue,
                     };
-                if let (Ok((_, robot_team, _)), Ok((mut grouper, grouper_team, _))) = (
-                    q_robot.get(other),
-                    q_robot_sensor
-                        .get(sensor)
-                        .and_then(|parent| q_grouper.get_mut(parent.get())),
-                ) {
+                let Ok(e_grouper) = q_robot_sensor.get(sensor).map(|parent| parent.get()) else {
+                    continue;
+                };
+                if let (Ok((_, robot_team, _)), Ok((mut grouper, grouper_team, _))) =
+                    (q_robot.get(other), q_grouper.get_mut(e_grouper))
+                {
                     if robot_team == grouper_team {
                         grouper.group.insert(other);
+                        if let Ok(mut last_redirect) = q_last_redirect.get_mut(other) {
+                            last_redirect.0 = Some((ToolKind::Grouper, e_grouper));
+                        }
                         if grouper.group.len() >= GROUP_SIZE {
                             for &entity in grouper.group.iter() {
                                 if let Ok((mut engine_dir, _, _)) = q_robot.get_mut(entity) {
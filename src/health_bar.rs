@@ -0,0 +1,231 @@
+use bevy::{
+    prelude::*,
+    render::{Extract, ExtractSchedule, RenderApp},
+    sprite::{Anchor, ExtractedSprite, ExtractedSprites, SpriteSystem},
+};
+use bevy_ecs_ldtk::LevelIid;
+
+use crate::{
+    game::GameState,
+    game_camera::MouseWorldCoords,
+    gameplay_events::BulletHit,
+    hp::{Dead, Life},
+    portal::Portal,
+    storage,
+};
+
+const BAR_HEIGHT: f32 = 2.;
+const BAR_Z: f32 = 50.;
+const PORTAL_BAR_WIDTH: f32 = 40.;
+const PORTAL_BAR_OFFSET: f32 = 36.;
+const ROBOT_BAR_WIDTH: f32 = 10.;
+const ROBOT_BAR_OFFSET: f32 = 8.;
+const ROBOT_HOVER_RADIUS: f32 = 8.;
+const BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.7);
+const DAMAGE_NUMBER_DURATION: f32 = 0.8;
+const DAMAGE_NUMBER_RISE: f32 = 12.;
+const DAMAGE_NUMBERS_FILE: &str = "damage_numbers.txt";
+
+pub struct HealthBarPlugin;
+
+impl Plugin for HealthBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DamageNumbers::load())
+            .add_systems(
+                Update,
+                (
+                    spawn_damage_numbers.run_if(show_damage_numbers),
+                    animate_damage_numbers,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_all)
+            .add_systems(PostUpdate, save_damage_numbers);
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(
+                ExtractSchedule,
+                extract_health_bars.after(SpriteSystem::ExtractSprites),
+            );
+        }
+    }
+}
+
+/// Show floating damage numbers when a bullet hits.
+#[derive(Resource)]
+pub struct DamageNumbers(pub bool);
+
+// One setting per line:
+//   damage_numbers <on|off>
+impl DamageNumbers {
+    fn load() -> Self {
+        let mut settings = Self(true);
+        for line in storage::read(DAMAGE_NUMBERS_FILE)
+            .unwrap_or_default()
+            .lines()
+        {
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["damage_numbers", "on"] => settings.0 = true,
+                ["damage_numbers", "off"] => settings.0 = false,
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        format!("damage_numbers {}\n", if self.0 { "on" } else { "off" })
+    }
+}
+
+fn save_damage_numbers(settings: Res<DamageNumbers>) {
+    if settings.is_changed() && !settings.is_added() {
+        if let Err(err) = storage::write(DAMAGE_NUMBERS_FILE, &settings.to_text()) {
+            warn!("could not save damage numbers setting: {}", err);
+        }
+    }
+}
+
+#[derive(Component)]
+struct DamageNumber {
+    age: f32,
+}
+
+fn show_damage_numbers(damage_numbers: Res<DamageNumbers>) -> bool {
+    damage_numbers.0
+}
+
+fn despawn_all(mut cmd: Commands, q_entity: Query<Entity, With<DamageNumber>>) {
+    for entity in &q_entity {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn extract_health_bars(
+    mut cmd: Commands,
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    game_state: Extract<Res<State<GameState>>>,
+    mouse_pos: Extract<Res<MouseWorldCoords>>,
+    q_life: Extract<
+        Query<
+            (
+                Entity,
+                &GlobalTransform,
+                &Life,
+                Has<Portal>,
+                Option<&ViewVisibility>,
+            ),
+            Without<Dead>,
+        >,
+    >,
+) {
+    if *game_state.get() != GameState::Playing {
+        return;
+    }
+    for (entity, gtr, life, is_portal, view_visibility) in q_life.iter() {
+        if view_visibility.is_some_and(|visibility| !visibility.get()) {
+            continue;
+        }
+        let pos = gtr.translation().truncate();
+        let (width, offset) = if is_portal {
+            (PORTAL_BAR_WIDTH, PORTAL_BAR_OFFSET)
+        } else {
+            (ROBOT_BAR_WIDTH, ROBOT_BAR_OFFSET)
+        };
+        let hovered = mouse_pos
+            .0
+            .is_some_and(|mouse_pos| mouse_pos.distance(pos) < ROBOT_HOVER_RADIUS);
+        if !is_portal && life.curr_hp >= life.max_hp && !hovered {
+            continue;
+        }
+        let ratio = (life.curr_hp / life.max_hp).clamp(0., 1.);
+        // bars don't turn with the unit
+        let bar_pos = (pos + Vec2::new(0., offset)).extend(BAR_Z);
+        let mut add_sprite = |translation: Vec3, color: Color, size: Vec2, anchor: Anchor| {
+            extracted_sprites.sprites.insert(
+                cmd.spawn_empty().id(),
+                ExtractedSprite {
+                    transform: GlobalTransform::from_translation(translation),
+                    color,
+                    rect: None,
+                    custom_size: Some(size),
+                    image_handle_id: Handle::<Image>::default().id(),
+                    flip_x: false,
+                    flip_y: false,
+                    anchor: anchor.as_vec(),
+                    original_entity: Some(entity),
+                },
+            );
+        };
+        add_sprite(
+            bar_pos,
+            BAR_BACKGROUND,
+            Vec2::new(width + 1., BAR_HEIGHT + 1.),
+            Anchor::Center,
+        );
+        add_sprite(
+            bar_pos + Vec3::new(-width / 2., 0., 0.1),
+            Color::rgb(1. - ratio, ratio, 0.2),
+            Vec2::new(width * ratio, BAR_HEIGHT),
+            Anchor::CenterLeft,
+        );
+    }
+}
+
+fn spawn_damage_numbers(
+    mut cmd: Commands,
+    mut ev_bullet_hit: EventReader<BulletHit>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    asset_server: Res<AssetServer>,
+) {
+    let offset = q_level
+        .get_single()
+        .map(|gtr| gtr.translation().truncate())
+        .unwrap_or_default();
+    for ev in ev_bullet_hit.read() {
+        cmd.spawn((
+            DamageNumber { age: 0. },
+            Text2dBundle {
+                text: Text::from_section(
+                    damage_text(ev.damage),
+                    TextStyle {
+                        font: asset_server.load("GeoFont-Bold.otf"),
+                        font_size: 24.0,
+                        color: Color::rgb(1.0, 0.9, 0.3),
+                    },
+                ),
+                transform: Transform::from_translation((ev.position + offset).extend(BAR_Z))
+                    .with_scale(Vec3::splat(0.25)),
+                ..Default::default()
+            },
+        ));
+    }
+}
+
+// one decimal at most, whole numbers without any
+fn damage_text(damage: f32) -> String {
+    let damage = (damage * 10.).round() / 10.;
+    if damage.fract() == 0. {
+        format!("{:.0}", damage)
+    } else {
+        format!("{:.1}", damage)
+    }
+}
+
+fn animate_damage_numbers(
+    mut cmd: Commands,
+    mut q_number: Query<(Entity, &mut DamageNumber, &mut Transform, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut number, mut tr, mut text) in &mut q_number {
+        number.age += time.delta_seconds();
+        if number.age >= DAMAGE_NUMBER_DURATION {
+            cmd.entity(entity).despawn_recursive();
+            continue;
+        }
+        tr.translation.y += DAMAGE_NUMBER_RISE / DAMAGE_NUMBER_DURATION * time.delta_seconds();
+        let alpha = 1. - number.age / DAMAGE_NUMBER_DURATION;
+        for section in &mut text.sections {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::game::GameState;
+use crate::{game::GameState, physics::Team};
 
 pub struct HpPlugin;
 
@@ -20,6 +20,12 @@ pub struct Life {
 #[component(storage = "SparseSet")]
 pub struct Dead;
 
+#[derive(Component)]
+pub struct LastHitBy {
+    pub shooter: Entity,
+    pub team: Team,
+}
+
 fn watch_life(mut cmd: Commands, q_life: Query<(Entity, &Life), Changed<Life>>) {
     for (entity, life) in &q_life {
         if life.curr_hp <= 0. {
@@ -0,0 +1,251 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::{
+    ldtk::ldtk_fields::LdtkFields,
+    utils::{grid_coords_to_translation, ldtk_grid_coords_to_grid_coords},
+    EntityInstance, LevelIid,
+};
+
+use crate::{
+    game::{GameState, LevelState},
+    game_camera::{fixup_camera_start, CameraStart, CameraTargetPos, MainCamera},
+    levels::{LevelIndex, LevelLoadedEvent, LevelSize, LevelTitle},
+    physics::Team,
+    portal::Portal,
+};
+
+// world units per second along the path
+const INTRO_SPEED: f32 = 400.;
+// shortest time spent between two points, short hops still read as a pan
+const INTRO_MIN_SEGMENT: f32 = 1.2;
+// pause on each point before moving on
+const INTRO_HOLD: f32 = 0.6;
+
+pub struct IntroPlugin;
+
+impl Plugin for IntroPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraIntro::default())
+            .add_systems(
+                Update,
+                (
+                    build_intro_path.after(fixup_camera_start),
+                    (play_intro, skip_intro)
+                        .chain()
+                        .run_if(in_state(LevelState::Intro)),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(LevelState::Intro), despawn_intro_title);
+    }
+}
+
+/// Camera fly-over played once per level before planning starts. Any key skips it.
+#[derive(Resource)]
+pub struct CameraIntro {
+    pub enabled: bool,
+    path: Vec<Vec3>,
+    elapsed: f32,
+    seen: HashSet<usize>,
+}
+
+impl Default for CameraIntro {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: vec![],
+            elapsed: 0.,
+            seen: HashSet::default(),
+        }
+    }
+}
+
+impl CameraIntro {
+    /// The level then goes straight to planning.
+    pub fn mark_seen(&mut self, level_index: usize) {
+        self.seen.insert(level_index);
+    }
+}
+
+/// `intro_path` points of the LDtk `CameraStart` entity, in LDtk grid coordinates.
+#[derive(Component, Default)]
+pub struct IntroPath(Vec<IVec2>);
+
+pub fn extract_intro_path(entity_instance: &EntityInstance) -> IntroPath {
+    IntroPath(
+        entity_instance
+            .iter_points_field("intro_path")
+            .map(|points| points.copied().collect())
+            .unwrap_or_default(),
+    )
+}
+
+#[derive(Component)]
+struct IntroTitle;
+
+/// Without `intro_path` points the camera goes from the enemy portals to the player portals.
+/// The path ends where the camera starts planning.
+fn build_intro_path(
+    mut ev_level_loaded: EventReader<LevelLoadedEvent>,
+    mut intro: ResMut<CameraIntro>,
+    q_camera_start: Query<(&GlobalTransform, &IntroPath), With<CameraStart>>,
+    q_portal: Query<(&GlobalTransform, &Team), With<Portal>>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    level_size: Res<LevelSize>,
+) {
+    for _ in ev_level_loaded.read() {
+        intro.path.clear();
+        intro.elapsed = 0.;
+        let (Ok(level_gtr), Some(size_info)) = (q_level.get_single(), level_size.0) else {
+            continue;
+        };
+        let camera_start = q_camera_start.iter().next();
+        match camera_start {
+            Some((_, IntroPath(points))) if !points.is_empty() => {
+                for point in points {
+                    let coords = ldtk_grid_coords_to_grid_coords(*point, size_info.size.y);
+                    let pos = grid_coords_to_translation(coords, size_info.tile_size_vec());
+                    intro.path.push(level_gtr.translation() + pos.extend(0.));
+                }
+            }
+            _ => {
+                for team in [Team::Enemy, Team::Player] {
+                    intro.path.extend(
+                        q_portal
+                            .iter()
+                            .filter(|(_, portal_team)| **portal_team == team)
+                            .map(|(gtr, _)| gtr.translation()),
+                    );
+                }
+            }
+        }
+        if let Some((gtr, _)) = camera_start {
+            intro.path.push(gtr.translation());
+        }
+        for pos in &mut intro.path {
+            pos.z = 0.;
+        }
+    }
+}
+
+fn segment_duration(from: Vec3, to: Vec3) -> f32 {
+    (from.distance(to) / INTRO_SPEED).max(INTRO_MIN_SEGMENT)
+}
+
+/// Camera position `elapsed` seconds into the intro, or `None` once it is over.
+fn intro_position(path: &[Vec3], mut elapsed: f32) -> Option<Vec3> {
+    for segment in path.windows(2) {
+        if elapsed < INTRO_HOLD {
+            return Some(segment[0]);
+        }
+        elapsed -= INTRO_HOLD;
+        let duration = segment_duration(segment[0], segment[1]);
+        if elapsed < duration {
+            let t = elapsed / duration;
+            let t = t * t * (3. - 2. * t);
+            return Some(segment[0].lerp(segment[1], t));
+        }
+        elapsed -= duration;
+    }
+    None
+}
+
+fn play_intro(
+    mut cmd: Commands,
+    mut intro: ResMut<CameraIntro>,
+    mut camera_target_pos: ResMut<CameraTargetPos>,
+    mut q_camera: Query<&mut Transform, With<MainCamera>>,
+    mut next_level_state: ResMut<NextState<LevelState>>,
+    level_index: Res<LevelIndex>,
+    level_title: Res<LevelTitle>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time<Real>>,
+) {
+    if !intro.enabled || intro.seen.contains(&level_index.0) || intro.path.len() < 2 {
+        next_level_state.set(LevelState::Planning);
+        return;
+    }
+    if intro.elapsed == 0. {
+        // start on the first point instead of flying there from the previous level
+        for mut tr in &mut q_camera {
+            tr.translation.x = intro.path[0].x;
+            tr.translation.y = intro.path[0].y;
+        }
+        cmd.spawn((
+            IntroTitle,
+            TextBundle::from_section(
+                level_title.0.clone(),
+                TextStyle {
+                    font: asset_server.load("GeoFont-Bold.otf"),
+                    font_size: 64.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(15.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            })
+            .with_text_alignment(TextAlignment::Center),
+        ));
+    }
+    intro.elapsed += time.delta_seconds();
+    match intro_position(&intro.path, intro.elapsed) {
+        Some(pos) => {
+            camera_target_pos.0.x = pos.x;
+            camera_target_pos.0.y = pos.y;
+        }
+        None => finish_intro(
+            &mut intro,
+            &mut camera_target_pos,
+            &mut next_level_state,
+            &level_index,
+        ),
+    }
+}
+
+fn skip_intro(
+    mut intro: ResMut<CameraIntro>,
+    mut camera_target_pos: ResMut<CameraTargetPos>,
+    mut next_level_state: ResMut<NextState<LevelState>>,
+    level_index: Res<LevelIndex>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    touches: Res<Touches>,
+) {
+    let pressed = keys.get_just_pressed().next().is_some()
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || gamepad_buttons.get_just_pressed().next().is_some()
+        || touches.any_just_pressed();
+    if pressed {
+        finish_intro(
+            &mut intro,
+            &mut camera_target_pos,
+            &mut next_level_state,
+            &level_index,
+        );
+    }
+}
+
+fn finish_intro(
+    intro: &mut CameraIntro,
+    camera_target_pos: &mut CameraTargetPos,
+    next_level_state: &mut NextState<LevelState>,
+    level_index: &LevelIndex,
+) {
+    if let Some(end) = intro.path.last() {
+        camera_target_pos.0.x = end.x;
+        camera_target_pos.0.y = end.y;
+    }
+    intro.seen.insert(level_index.0);
+    next_level_state.set(LevelState::Planning);
+}
+
+fn despawn_intro_title(mut cmd: Commands, q_title: Query<Entity, With<IntroTitle>>) {
+    for entity in &q_title {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
@@ -1,13 +1,17 @@
 use crate::arrow::EnemyArrowBundle;
-use crate::defender::EnemyDefenderBundle;
+use crate::defender::{DefenderShield, EnemyDefenderBundle};
 use crate::fork::EnemyForkBundle;
 use crate::game::{GameState, LevelState};
 use crate::game_camera::{CameraStartBundle, CameraTargetPos};
-use crate::game_ui::{ChangeLevelEvent, ResetLevelEvent};
+use crate::game_ui::{ChangeLevelEvent, ResetLevelEvent, StartLevelEvent};
 use crate::grouper::EnemyGrouperBundle;
 use crate::inventory::Inventory;
 use crate::physics::{coll_groups, ObjectGroup, Team};
 use crate::portal::{EnemyPortalBundle, PlayerPortalBundle, Portal};
+use crate::robot::DeathSplash;
+use crate::score::StarThresholds;
+use crate::status_effect::StatusZoneBundle;
+use crate::weapon::AimAccuracy;
 use bevy::math::ivec2;
 use bevy::prelude::*;
 use bevy::utils::{info, HashMap};
@@ -45,6 +49,7 @@ impl Plugin for LevelsPlugin {
             ))
             .insert_resource(LevelSize::default())
             .insert_resource(LevelTitle("".to_string()))
+            .insert_resource(PuzzleLevel(false))
             // .insert_resource(CurrentLevel::default())
             .add_systems(OnEnter(GameState::LoadingLevels), load_ldtk)
             .add_systems(
@@ -61,13 +66,21 @@ impl Plugin for LevelsPlugin {
             )
             .add_systems(
                 Update,
-                (keep_level_on_screen.after(crate::mouse::motion),)
+                (keep_level_on_screen
+                    .after(crate::mouse::motion)
+                    .after(crate::controls::pan_camera)
+                    .after(crate::selection::follow_selection),)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(Update, spawn_wall_collisions)
             .add_systems(
                 Update,
-                (check_victory, watch_for_reset, watch_for_next_level)
+                (
+                    check_victory,
+                    watch_for_reset,
+                    watch_for_next_level,
+                    watch_for_start_level,
+                )
                     .run_if(in_state(GameState::Playing)),
             )
             .register_ldtk_int_cell_for_layer::<WallBundle>("Walls", 1)
@@ -77,7 +90,8 @@ impl Plugin for LevelsPlugin {
             .register_ldtk_entity::<EnemyForkBundle>("EnemyFork")
             .register_ldtk_entity::<EnemyGrouperBundle>("EnemyGrouper")
             .register_ldtk_entity::<EnemyDefenderBundle>("EnemyDefender")
-            .register_ldtk_entity::<CameraStartBundle>("CameraStart");
+            .register_ldtk_entity::<CameraStartBundle>("CameraStart")
+            .register_ldtk_entity::<StatusZoneBundle>("StatusZone");
         #[cfg(debug_assertions)]
         app.add_systems(Update, cheat_win.run_if(in_state(GameState::Playing)));
     }
@@ -92,6 +106,10 @@ fn cheat_win(
     q_level: Query<Entity, With<LevelIid>>,
     mut next_level_state: ResMut<NextState<LevelState>>,
 ) {
+    // cheats need ctrl so they don't collide with the camera and rotate bindings
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
     if keys.just_pressed(KeyCode::W) {
         for (entity, team) in &q_portal {
             if *team == Team::Enemy {
@@ -109,7 +127,7 @@ fn cheat_win(
     if keys.just_pressed(KeyCode::R) {
         ev_reset_level.send(ResetLevelEvent);
         for entity in &q_level {
-            next_level_state.0 = Some(LevelState::Playing);
+            next_level_state.0 = Some(LevelState::Planning);
             cmd.entity(entity).remove::<Victory>().remove::<Defeat>();
         }
         //cmd.entity(e_level).remove::<Victory>().remove::<Loss>();
@@ -123,6 +141,14 @@ fn set_level_index_changed(mut level_index: ResMut<LevelIndex>) {
 #[derive(Resource)]
 pub struct LevelTitle(pub String);
 
+/// Puzzle levels lock the player's tools once the simulation is running.
+#[derive(Resource)]
+pub struct PuzzleLevel(pub bool);
+
+pub fn tools_unlocked(puzzle_level: Res<PuzzleLevel>, level_state: Res<State<LevelState>>) -> bool {
+    !(puzzle_level.0 && *level_state == LevelState::Playing)
+}
+
 #[derive(Resource)]
 pub struct MaxAttainableLevel(pub usize);
 #[derive(Resource)]
@@ -239,6 +265,11 @@ pub fn level_changed(
     mut level_count: ResMut<LevelCount>,
     mut next_level_state: ResMut<NextState<LevelState>>,
     mut level_title: ResMut<LevelTitle>,
+    mut puzzle_level: ResMut<PuzzleLevel>,
+    mut star_thresholds: ResMut<StarThresholds>,
+    mut death_splash: ResMut<DeathSplash>,
+    mut defender_shield: ResMut<DefenderShield>,
+    mut aim_accuracy: ResMut<AimAccuracy>,
 ) {
     for level_event in level_events.read() {
         info(level_event);
@@ -265,11 +296,33 @@ pub fn level_changed(
                 inventory.grouper_count = *level.get_int_field("player_groupers").unwrap() as u32;
                 inventory.defender_count = *level.get_int_field("player_defenders").unwrap() as u32;
                 level_title.0 = level.get_string_field("title").unwrap().clone();
+                puzzle_level.0 = *level.get_bool_field("puzzle").unwrap_or(&false);
+                star_thresholds.two_stars = level
+                    .get_int_field("two_stars_score")
+                    .map_or(u32::MAX, |score| *score as u32);
+                star_thresholds.three_stars = level
+                    .get_int_field("three_stars_score")
+                    .map_or(u32::MAX, |score| *score as u32);
+                let default_splash = DeathSplash::default();
+                death_splash.damage = level
+                    .get_float_field("death_splash_damage")
+                    .map_or(default_splash.damage, |damage| *damage);
+                death_splash.radius = level
+                    .get_float_field("death_splash_radius")
+                    .map_or(default_splash.radius, |radius| *radius);
+                defender_shield.0 = *level.get_bool_field("defender_shield").unwrap_or(&false);
+                let default_accuracy = AimAccuracy::default();
+                aim_accuracy.player = level
+                    .get_float_field("player_aim_accuracy")
+                    .map_or(default_accuracy.player, |accuracy| accuracy.clamp(0., 1.));
+                aim_accuracy.enemy = level
+                    .get_float_field("enemy_aim_accuracy")
+                    .map_or(default_accuracy.enemy, |accuracy| accuracy.clamp(0., 1.));
                 level_size.0 = Some(size_info);
             }
             LevelEvent::Transformed(_) => {
                 ev_level_loaded.send(LevelLoadedEvent);
-                next_level_state.0 = Some(LevelState::Playing);
+                next_level_state.0 = Some(LevelState::Intro);
             }
             _ => {}
         }
@@ -303,20 +356,7 @@ fn spawn_wall_collisions(
         let mut wall_cache = HashMap::default();
         for (wall_entity, grid_coord) in &q_wall {
             if let Some(level_size) = level_size.0 {
-                let half_size = level_size.tile_size as f32 / 2.0;
-                cmd.entity(wall_entity).insert((
-                    WallCollider,
-                    Collider::cuboid(half_size + 1.0, half_size + 1.0),
-                    RigidBody::Fixed,
-                    Friction::new(0.0),
-                    Restitution::new(2.0),
-                    coll_groups(
-                        ObjectGroup::WALL,
-                        ObjectGroup::ENEMY_ROBOT
-                            | ObjectGroup::PLAYER_ROBOT
-                            | ObjectGroup::ROBOT_STEERING_SENSOR,
-                    ),
-                ));
+                cmd.entity(wall_entity).insert(wall_collider(level_size));
                 wall_cache.insert(*grid_coord, wall_entity);
             }
         }
@@ -327,6 +367,23 @@ fn spawn_wall_collisions(
     }
 }
 
+pub fn wall_collider(level_size: LevelSizeInfo) -> impl Bundle {
+    let half_size = level_size.tile_size as f32 / 2.0;
+    (
+        WallCollider,
+        Collider::cuboid(half_size + 1.0, half_size + 1.0),
+        RigidBody::Fixed,
+        Friction::new(0.0),
+        Restitution::new(2.0),
+        coll_groups(
+            ObjectGroup::WALL,
+            ObjectGroup::ENEMY_ROBOT
+                | ObjectGroup::PLAYER_ROBOT
+                | ObjectGroup::ROBOT_STEERING_SENSOR,
+        ),
+    )
+}
+
 fn check_victory(
     mut cmd: Commands,
     q_level: Query<Entity, (With<LevelIid>, Without<Victory>, Without<Defeat>)>,
@@ -386,6 +443,18 @@ fn watch_for_reset(
     }
 }
 
+fn watch_for_start_level(
+    mut ev_start_level: EventReader<StartLevelEvent>,
+    level_state: Res<State<LevelState>>,
+    mut next_level_state: ResMut<NextState<LevelState>>,
+) {
+    for _ev in ev_start_level.read() {
+        if *level_state == LevelState::Planning {
+            next_level_state.0 = Some(LevelState::Playing);
+        }
+    }
+}
+
 fn watch_for_next_level(
     mut ev_next_level: EventReader<ChangeLevelEvent>,
     mut level_selection: ResMut<LevelSelection>,
@@ -401,6 +470,9 @@ fn watch_for_next_level(
                 level_index.0 -= 1;
                 // info("Previous level event")
             }
+            ChangeLevelEvent::Index(index) => {
+                level_index.0 = (*index).min(LEVEL_NAMES.len() - 1);
+            }
         }
         *level_selection = LevelSelection::Identifier(LEVEL_NAMES[level_index.0].to_string());
     }
@@ -2,7 +2,13 @@
 #![allow(clippy::too_many_arguments)]
 
 mod arrow;
+mod audio;
+mod benchmark;
 mod bullet;
+mod camera_effects;
+mod console;
+mod controls;
+mod debug_overlay;
 mod defender;
 mod draggable;
 mod explosion;
@@ -10,15 +16,30 @@ mod fork;
 mod game;
 mod game_camera;
 mod game_ui;
+mod gameplay_events;
+mod ghost;
 mod grouper;
+mod health_bar;
 mod hp;
+mod intro;
 mod inventory;
 mod levels;
 mod load;
 mod menu;
+mod minimap;
 mod mouse;
 mod physics;
+mod placement_preview;
+mod pool;
 mod portal;
 mod robot;
+mod savestate;
+mod score;
+mod selection;
+mod sprite_animation;
+mod status_effect;
+mod storage;
+mod touch;
+mod weapon;
 
 pub use game::GamePlugin;
@@ -1,4 +1,4 @@
-use crate::game::GameState;
+use crate::{explosion::ExplosionPresets, game::GameState};
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 
@@ -10,37 +10,83 @@ impl Plugin for LoadPlugin {
         app.add_loading_state(
             LoadingState::new(GameState::Loading).continue_to_state(GameState::LoadingLevels),
         )
-        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading);
+        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading)
+        .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
+        .add_collection_to_loading_state::<_, ExplosionAssets>(GameState::Loading);
         // .add_collection_to_loading_state::<_, LevelAssets>(GameState::Loading);
     }
 }
 
-#[derive(AssetCollection, Resource)]
+/// Sprite sheets are single rows of frames, see `sprite_animation` for their clips.
+#[derive(AssetCollection, Resource, Clone)]
 pub struct TextureAssets {
-    #[asset(path = "robot_body.png")]
-    pub robot_body: Handle<Image>,
+    #[asset(texture_atlas(tile_size_x = 16., tile_size_y = 16., columns = 13, rows = 1))]
+    #[asset(path = "robot_body_sheet.png")]
+    pub robot_body: Handle<TextureAtlas>,
     #[asset(path = "robot_canon.png")]
     pub robot_canon: Handle<Image>,
     #[asset(path = "robot_turret.png")]
     pub robot_turret: Handle<Image>,
     #[asset(path = "robot_train.png")]
     pub robot_train: Handle<Image>,
-    #[asset(path = "enemy_portal.png")]
-    pub enemy_portal: Handle<Image>,
-    #[asset(path = "player_portal.png")]
-    pub player_portal: Handle<Image>,
-    #[asset(path = "arrow.png")]
-    pub arrow: Handle<Image>,
-    #[asset(path = "fork.png")]
-    pub fork: Handle<Image>,
-    #[asset(path = "grouper.png")]
-    pub grouper: Handle<Image>,
-    #[asset(path = "defender.png")]
-    pub defender: Handle<Image>,
+    #[asset(texture_atlas(tile_size_x = 32., tile_size_y = 32., columns = 10, rows = 1))]
+    #[asset(path = "enemy_portal_sheet.png")]
+    pub enemy_portal: Handle<TextureAtlas>,
+    #[asset(texture_atlas(tile_size_x = 32., tile_size_y = 32., columns = 10, rows = 1))]
+    #[asset(path = "player_portal_sheet.png")]
+    pub player_portal: Handle<TextureAtlas>,
+    #[asset(texture_atlas(tile_size_x = 32., tile_size_y = 32., columns = 5, rows = 1))]
+    #[asset(path = "arrow_sheet.png")]
+    pub arrow: Handle<TextureAtlas>,
+    #[asset(texture_atlas(tile_size_x = 32., tile_size_y = 32., columns = 5, rows = 1))]
+    #[asset(path = "fork_sheet.png")]
+    pub fork: Handle<TextureAtlas>,
+    #[asset(texture_atlas(tile_size_x = 32., tile_size_y = 32., columns = 5, rows = 1))]
+    #[asset(path = "grouper_sheet.png")]
+    pub grouper: Handle<TextureAtlas>,
+    #[asset(texture_atlas(tile_size_x = 32., tile_size_y = 32., columns = 5, rows = 1))]
+    #[asset(path = "defender_sheet.png")]
+    pub defender: Handle<TextureAtlas>,
     #[asset(path = "nuzzle_flash.png")]
     pub nuzzle_flash: Handle<Image>,
     #[asset(path = "bullet.png")]
     pub bullet: Handle<Image>,
     #[asset(path = "explosion_particle.png")]
     pub explosion_particle: Handle<Image>,
+    #[asset(texture_atlas(tile_size_x = 8., tile_size_y = 8., columns = 6, rows = 1))]
+    #[asset(path = "status_icons.png")]
+    pub status_icons: Handle<TextureAtlas>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct AudioAssets {
+    #[asset(path = "audio/fire.wav")]
+    pub fire: Handle<AudioSource>,
+    #[asset(path = "audio/hit.wav")]
+    pub hit: Handle<AudioSource>,
+    #[asset(path = "audio/explosion.wav")]
+    pub explosion: Handle<AudioSource>,
+    #[asset(path = "audio/big_explosion.wav")]
+    pub big_explosion: Handle<AudioSource>,
+    #[asset(path = "audio/portal_spawn.wav")]
+    pub portal_spawn: Handle<AudioSource>,
+    #[asset(path = "audio/tool_place.wav")]
+    pub tool_place: Handle<AudioSource>,
+    #[asset(path = "audio/tool_pickup.wav")]
+    pub tool_pickup: Handle<AudioSource>,
+    #[asset(path = "audio/win.wav")]
+    pub win: Handle<AudioSource>,
+    #[asset(path = "audio/loss.wav")]
+    pub loss: Handle<AudioSource>,
+    #[asset(path = "audio/music_menu.wav")]
+    pub music_menu: Handle<AudioSource>,
+    #[asset(path = "audio/music_playing.wav")]
+    pub music_playing: Handle<AudioSource>,
+}
+
+/// Changes to the presets are picked up while playing when built with the `dev` feature.
+#[derive(AssetCollection, Resource)]
+pub struct ExplosionAssets {
+    #[asset(path = "explosions.presets")]
+    pub presets: Handle<ExplosionPresets>,
 }
@@ -1,6 +1,9 @@
 use bevy::{prelude::*, window::PrimaryWindow};
 
-use crate::game::GameState;
+use crate::{
+    audio::AudioSettings, camera_effects::CameraEffectsSettings, debug_overlay::DebugOverlay,
+    game::GameState, health_bar::DamageNumbers,
+};
 
 pub struct MenuPlugin;
 
@@ -10,13 +13,17 @@ impl Plugin for MenuPlugin {
             .add_systems(OnEnter(GameState::Menu), init_menu)
             .add_systems(OnExit(GameState::Menu), destroy)
             .add_systems(PreUpdate, fix_font_sizes.run_if(in_state(GameState::Menu)))
-            .add_systems(Update, button_system.run_if(in_state(GameState::Menu)));
+            .add_systems(
+                Update,
+                (button_system, update_setting_labels).run_if(in_state(GameState::Menu)),
+            );
     }
 }
 
 const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const PRESSED_BUTTON: Color = Color::rgb(0.9, 0.9, 0.9);
+const VOLUME_STEP: f32 = 0.1;
 
 #[derive(Resource)]
 enum ButtonState {
@@ -33,6 +40,29 @@ struct TitleText;
 #[derive(Component)]
 struct ButtonText;
 
+/// Text of the settings rows and their buttons, smaller than the start button.
+#[derive(Component)]
+struct SettingText;
+
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+enum MenuButton {
+    Start,
+    MusicVolume(f32),
+    SfxVolume(f32),
+    ReducedMotion(bool),
+    DamageNumbers(bool),
+    DebugOverlay(bool),
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+enum SettingLabel {
+    Music,
+    Sfx,
+    Motion,
+    DamageNumbers,
+    DebugOverlay,
+}
+
 fn destroy(mut cmd: Commands, q: Query<Entity, With<Menu>>) {
     for e in &q {
         println!("destroy!");
@@ -47,14 +77,19 @@ fn button_system(
             &mut BackgroundColor,
             &mut BorderColor,
             &Children,
+            &MenuButton,
         ),
         (Changed<Interaction>, With<Button>),
     >,
     mut text_query: Query<&mut Text>,
     mut button_state: ResMut<ButtonState>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut audio_settings: ResMut<AudioSettings>,
+    mut camera_effects_settings: ResMut<CameraEffectsSettings>,
+    mut damage_numbers: ResMut<DamageNumbers>,
+    mut debug_overlay: ResMut<DebugOverlay>,
 ) {
-    for (interaction, mut color, mut border_color, children) in &mut interaction_query {
+    for (interaction, mut color, mut border_color, children, button) in &mut interaction_query {
         if let Ok(mut text) = text_query.get_mut(children[0]) {
             let text = &mut text.sections[0];
             match *interaction {
@@ -70,7 +105,25 @@ fn button_system(
                     text.style.color = Color::rgb(0.9, 0.9, 0.9);
                     match *button_state {
                         ButtonState::Down => {
-                            next_state.set(GameState::Playing);
+                            *button_state = ButtonState::None;
+                            match *button {
+                                MenuButton::Start => next_state.set(GameState::Playing),
+                                MenuButton::MusicVolume(step) => {
+                                    audio_settings.music_volume =
+                                        step_volume(audio_settings.music_volume, step);
+                                }
+                                MenuButton::SfxVolume(step) => {
+                                    audio_settings.sfx_volume =
+                                        step_volume(audio_settings.sfx_volume, step);
+                                }
+                                MenuButton::ReducedMotion(reduced) => {
+                                    camera_effects_settings.reduced_motion = reduced;
+                                }
+                                MenuButton::DamageNumbers(show) => damage_numbers.0 = show,
+                                MenuButton::DebugOverlay(allowed) => {
+                                    debug_overlay.allowed = allowed;
+                                }
+                            }
                         }
                         _ => *button_state = ButtonState::None,
                     }
@@ -86,10 +139,55 @@ fn button_system(
     }
 }
 
+fn step_volume(volume: f32, step: f32) -> f32 {
+    ((volume + step) / VOLUME_STEP)
+        .round()
+        .clamp(0., 1. / VOLUME_STEP)
+        * VOLUME_STEP
+}
+
+fn update_setting_labels(
+    audio_settings: Res<AudioSettings>,
+    camera_effects_settings: Res<CameraEffectsSettings>,
+    damage_numbers: Res<DamageNumbers>,
+    debug_overlay: Res<DebugOverlay>,
+    mut q_label: Query<(&mut Text, &SettingLabel)>,
+) {
+    for (mut text, label) in &mut q_label {
+        let value = match label {
+            SettingLabel::Music => format!("Music {:.0}%", audio_settings.music_volume * 100.),
+            SettingLabel::Sfx => format!("Sound {:.0}%", audio_settings.sfx_volume * 100.),
+            SettingLabel::Motion => format!(
+                "Motion {}",
+                if camera_effects_settings.reduced_motion {
+                    "reduced"
+                } else {
+                    "full"
+                }
+            ),
+            SettingLabel::DamageNumbers => format!(
+                "Damage numbers {}",
+                if damage_numbers.0 { "on" } else { "off" }
+            ),
+            SettingLabel::DebugOverlay => format!(
+                "Debug overlay {}",
+                if debug_overlay.allowed { "on" } else { "off" }
+            ),
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
 fn fix_font_sizes(
     q_window: Query<&Window, With<PrimaryWindow>>,
     mut q_title_text: Query<&mut Text, (With<TitleText>, Without<ButtonText>)>,
     mut q_button_text: Query<&mut Text, (Without<TitleText>, With<ButtonText>)>,
+    mut q_setting_text: Query<
+        &mut Text,
+        (With<SettingText>, Without<TitleText>, Without<ButtonText>),
+    >,
 ) {
     let window = q_window.single();
     let height = window.height();
@@ -99,6 +197,65 @@ fn fix_font_sizes(
     for mut text in &mut q_button_text {
         text.sections[0].style.font_size = height / 10.;
     }
+    for mut text in &mut q_setting_text {
+        text.sections[0].style.font_size = height / 30.;
+    }
+}
+
+fn button_bundle(padding: UiRect) -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            padding,
+            border: UiRect::all(Val::Px(5.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        border_color: BorderColor(Color::BLACK),
+        background_color: NORMAL_BUTTON.into(),
+        ..Default::default()
+    }
+}
+
+fn spawn_setting_row(
+    cmd: &mut ChildBuilder,
+    font: &Handle<Font>,
+    label: SettingLabel,
+    buttons: [(MenuButton, &str); 2],
+) {
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 20.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    cmd.spawn(NodeBundle {
+        style: Style {
+            align_items: AlignItems::Center,
+            column_gap: Val::VMax(1.),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .with_children(|cmd| {
+        cmd.spawn((
+            SettingText,
+            label,
+            TextBundle::from_section("", text_style.clone()).with_style(Style {
+                min_width: Val::VMax(12.),
+                ..Default::default()
+            }),
+        ));
+        for (button, text) in buttons {
+            let padding = UiRect::axes(Val::VMax(1.), Val::VMax(0.3));
+            cmd.spawn((button, button_bundle(padding)))
+                .with_children(|cmd| {
+                    cmd.spawn((
+                        SettingText,
+                        TextBundle::from_section(text, text_style.clone()),
+                    ));
+                });
+        }
+    });
 }
 
 fn init_menu(mut cmd: Commands, asset_server: Res<AssetServer>) {
@@ -128,21 +285,15 @@ fn init_menu(mut cmd: Commands, asset_server: Res<AssetServer>) {
                 },
             ),
         ));
-        cmd.spawn(ButtonBundle {
-            style: Style {
-                // width: Val::Px(150.0),
-                // height: Val::Px(65.0),
-                // padding: UiRect::new(Val::Px(20.), Val::Px(20.), Val::Px(15.), Val::Px(10.)),
-                padding: UiRect::new(Val::VMax(2.), Val::VMax(2.), Val::VMax(1.5), Val::VMax(1.)),
-                border: UiRect::all(Val::Px(5.0)),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                ..Default::default()
-            },
-            border_color: BorderColor(Color::BLACK),
-            background_color: NORMAL_BUTTON.into(),
-            ..Default::default()
-        })
+        cmd.spawn((
+            MenuButton::Start,
+            button_bundle(UiRect::new(
+                Val::VMax(2.),
+                Val::VMax(2.),
+                Val::VMax(1.5),
+                Val::VMax(1.),
+            )),
+        ))
         .with_children(|cmd| {
             cmd.spawn((
                 ButtonText,
@@ -156,5 +307,62 @@ fn init_menu(mut cmd: Commands, asset_server: Res<AssetServer>) {
                 ),
             ));
         });
+        cmd.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::VMax(0.5),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|cmd| {
+            let font = asset_server.load("GeoFont-Bold.otf");
+            spawn_setting_row(
+                cmd,
+                &font,
+                SettingLabel::Music,
+                [
+                    (MenuButton::MusicVolume(-VOLUME_STEP), "-"),
+                    (MenuButton::MusicVolume(VOLUME_STEP), "+"),
+                ],
+            );
+            spawn_setting_row(
+                cmd,
+                &font,
+                SettingLabel::Sfx,
+                [
+                    (MenuButton::SfxVolume(-VOLUME_STEP), "-"),
+                    (MenuButton::SfxVolume(VOLUME_STEP), "+"),
+                ],
+            );
+            spawn_setting_row(
+                cmd,
+                &font,
+                SettingLabel::Motion,
+                [
+                    (MenuButton::ReducedMotion(false), "full"),
+                    (MenuButton::ReducedMotion(true), "reduced"),
+                ],
+            );
+            spawn_setting_row(
+                cmd,
+                &font,
+                SettingLabel::DamageNumbers,
+                [
+                    (MenuButton::DamageNumbers(true), "on"),
+                    (MenuButton::DamageNumbers(false), "off"),
+                ],
+            );
+            spawn_setting_row(
+                cmd,
+                &font,
+                SettingLabel::DebugOverlay,
+                [
+                    (MenuButton::DebugOverlay(true), "on"),
+                    (MenuButton::DebugOverlay(false), "off"),
+                ],
+            );
+        });
     });
 }
@@ -0,0 +1,303 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    ui::RelativeCursorPosition,
+    utils::HashMap,
+};
+use bevy_ecs_ldtk::{utils::translation_to_grid_coords, GridCoords, LevelIid};
+
+use crate::{
+    arrow::Arrow,
+    defender::Defender,
+    fork::Fork,
+    game::{GameState, LevelState},
+    game_camera::{CameraTargetPos, MainCamera},
+    grouper::Grouper,
+    levels::{LevelSize, LevelSizeInfo, WallCache},
+    physics::Team,
+    portal::Portal,
+    robot::Robot,
+};
+
+// the longest side of the minimap, in pixels
+const MINIMAP_SIZE: f32 = 200.;
+const BACKGROUND_COLOR: Color = Color::rgba(0.05, 0.05, 0.05, 0.8);
+const WALL_COLOR: Color = Color::rgb(0.45, 0.45, 0.45);
+const TOOL_COLOR: Color = Color::rgb(1.0, 1.0, 0.6);
+// number of robots in a cell for the heatmap to reach full intensity
+const ROBOTS_FOR_FULL_HEAT: f32 = 4.;
+const PORTAL_RADIUS: i32 = 1;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(LevelState::Planning), spawn_minimap)
+            .add_systems(OnEnter(LevelState::Intro), destroy_minimap)
+            .add_systems(OnExit(LevelState::Playing), destroy_minimap)
+            .add_systems(
+                OnTransition {
+                    from: LevelState::Planning,
+                    to: LevelState::WaitingLevelSpawn,
+                },
+                destroy_minimap,
+            )
+            .add_systems(
+                Update,
+                (draw_minimap, update_viewport_rect, minimap_click)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .insert_resource(RedrawTimer(Timer::new(
+                REDRAW_INTERVAL,
+                TimerMode::Repeating,
+            )));
+    }
+}
+
+#[derive(Component)]
+pub struct Minimap;
+
+#[derive(Resource)]
+struct RedrawTimer(Timer);
+
+/// Clicks on the minimap move the camera and must not also reach the level under it.
+pub fn pointer_over_minimap(q_minimap: Query<&Interaction, With<Minimap>>) -> bool {
+    q_minimap
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+}
+
+#[derive(Component)]
+struct MinimapViewport;
+
+fn spawn_minimap(mut cmd: Commands, mut images: ResMut<Assets<Image>>, level_size: Res<LevelSize>) {
+    let Some(size_info) = level_size.0 else {
+        return;
+    };
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size_info.size.x as u32,
+            height: size_info.size.y as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &BACKGROUND_COLOR.as_rgba_u8(),
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::nearest();
+    let cell_size = MINIMAP_SIZE / size_info.size.max_element() as f32;
+
+    cmd.spawn((
+        Minimap,
+        Interaction::default(),
+        RelativeCursorPosition::default(),
+        ImageBundle {
+            image: UiImage::new(images.add(image)),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Vh(2.),
+                right: Val::Vh(2.),
+                width: Val::Px(size_info.size.x as f32 * cell_size),
+                height: Val::Px(size_info.size.y as f32 * cell_size),
+                overflow: Overflow::clip(),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    ))
+    .with_children(|cmd| {
+        cmd.spawn((
+            MinimapViewport,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    border: UiRect::all(Val::Px(1.)),
+                    ..Default::default()
+                },
+                border_color: BorderColor(Color::WHITE),
+                ..Default::default()
+            },
+        ));
+    });
+}
+
+fn destroy_minimap(mut cmd: Commands, q_minimap: Query<Entity, With<Minimap>>) {
+    for entity in &q_minimap {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn level_coords(
+    gtr: &GlobalTransform,
+    level_gtr: &GlobalTransform,
+    size_info: &LevelSizeInfo,
+) -> GridCoords {
+    translation_to_grid_coords(
+        gtr.translation().truncate() - level_gtr.translation().truncate(),
+        size_info.tile_size_vec(),
+    )
+}
+
+fn draw_minimap(
+    q_minimap: Query<(&UiImage, Ref<Minimap>)>,
+    mut redraw_timer: ResMut<RedrawTimer>,
+    time: Res<Time>,
+    mut images: ResMut<Assets<Image>>,
+    level_size: Res<LevelSize>,
+    q_level: Query<(&GlobalTransform, &WallCache), With<LevelIid>>,
+    q_portal: Query<(&GlobalTransform, &Team), With<Portal>>,
+    q_tool: Query<&GridCoords, Or<(With<Arrow>, With<Fork>, With<Grouper>, With<Defender>)>>,
+    q_robot: Query<(&GlobalTransform, &Team), With<Robot>>,
+) {
+    let (Ok((ui_image, minimap)), Some(size_info), Ok((level_gtr, wall_cache))) =
+        (q_minimap.get_single(), level_size.0, q_level.get_single())
+    else {
+        return;
+    };
+    // robots move a fraction of a cell per frame, so a few redraws per second are enough
+    let due = redraw_timer.0.tick(time.delta()).just_finished();
+    if !due && !minimap.is_added() {
+        return;
+    }
+    let Some(image) = images.get_mut(&ui_image.texture) else {
+        return;
+    };
+    if image.size() != size_info.size.as_uvec2() {
+        return;
+    }
+    let width = size_info.size.x;
+    let mut set_pixel = |coords: GridCoords, color: Color| {
+        if size_info.grid_coords_in_bound(coords) {
+            // grid coords go up, image rows go down
+            let index = ((size_info.size.y - 1 - coords.y) * width + coords.x) as usize * 4;
+            image.data[index..index + 4].copy_from_slice(&color.as_rgba_u8());
+        }
+    };
+
+    for y in 0..size_info.size.y {
+        for x in 0..width {
+            let coords = GridCoords::new(x, y);
+            if wall_cache.items.contains_key(&coords) {
+                set_pixel(coords, WALL_COLOR);
+            } else {
+                set_pixel(coords, BACKGROUND_COLOR);
+            }
+        }
+    }
+
+    let mut heat: HashMap<(GridCoords, Team), f32> = HashMap::default();
+    for (gtr, team) in &q_robot {
+        *heat
+            .entry((level_coords(gtr, level_gtr, &size_info), *team))
+            .or_default() += 1.;
+    }
+    for ((coords, team), count) in heat {
+        let intensity = 0.4 + 0.6 * (count / ROBOTS_FOR_FULL_HEAT).min(1.0);
+        let [r, g, b, _] = team.tint().as_rgba_f32();
+        set_pixel(
+            coords,
+            Color::rgb(r * intensity, g * intensity, b * intensity),
+        );
+    }
+
+    for coords in &q_tool {
+        set_pixel(*coords, TOOL_COLOR);
+    }
+
+    for (gtr, team) in &q_portal {
+        let center = level_coords(gtr, level_gtr, &size_info);
+        for dy in -PORTAL_RADIUS..=PORTAL_RADIUS {
+            for dx in -PORTAL_RADIUS..=PORTAL_RADIUS {
+                set_pixel(GridCoords::new(center.x + dx, center.y + dy), team.tint());
+            }
+        }
+    }
+}
+
+fn update_viewport_rect(
+    mut q_viewport: Query<&mut Style, With<MinimapViewport>>,
+    q_camera: Query<(&GlobalTransform, &OrthographicProjection), With<MainCamera>>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    level_size: Res<LevelSize>,
+) {
+    let (Ok(mut style), Ok((camera_gtr, projection)), Ok(level_gtr), Some(size_info)) = (
+        q_viewport.get_single_mut(),
+        q_camera.get_single(),
+        q_level.get_single(),
+        level_size.0,
+    ) else {
+        return;
+    };
+    let pixel_size = size_info.pixel_size().as_vec2();
+    let offset = camera_gtr.translation().truncate() - level_gtr.translation().truncate();
+    let min = (projection.area.min + offset) / pixel_size * 100.;
+    let max = (projection.area.max + offset) / pixel_size * 100.;
+    style.left = Val::Percent(min.x);
+    style.width = Val::Percent(max.x - min.x);
+    // ui goes down from the top
+    style.top = Val::Percent(100. - max.y);
+    style.height = Val::Percent(max.y - min.y);
+}
+
+fn minimap_click(
+    q_minimap: Query<(&Interaction, &RelativeCursorPosition), With<Minimap>>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    level_size: Res<LevelSize>,
+    mut camera_target_pos: ResMut<CameraTargetPos>,
+) {
+    let (Ok((interaction, cursor)), Ok(level_gtr), Some(size_info)) =
+        (q_minimap.get_single(), q_level.get_single(), level_size.0)
+    else {
+        return;
+    };
+    if *interaction != Interaction::Pressed {
+        return;
+    }
+    if let Some(normalized) = cursor.normalized {
+        let pixel_size = size_info.pixel_size().as_vec2();
+        let local_pos =
+            Vec2::new(normalized.x, 1.0 - normalized.y).clamp(Vec2::ZERO, Vec2::ONE) * pixel_size;
+        let world_pos = local_pos + level_gtr.translation().truncate();
+        camera_target_pos.0 = world_pos.extend(camera_target_pos.0.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replanning_through_intro_keeps_one_minimap() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, MinimapPlugin))
+            .add_state::<GameState>()
+            .add_state::<LevelState>()
+            .insert_resource(Assets::<Image>::default())
+            .insert_resource(LevelSize(Some(LevelSizeInfo {
+                size: IVec2::new(20, 10),
+                tile_size: 8,
+            })));
+        for state in [
+            LevelState::Intro,
+            LevelState::Planning,
+            LevelState::Intro,
+            LevelState::Planning,
+        ] {
+            app.insert_resource(NextState(Some(state)));
+            app.update();
+        }
+
+        let count = app
+            .world
+            .query_filtered::<(), With<Minimap>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(count, 1);
+    }
+}
@@ -7,8 +7,11 @@ use bevy::{
 use bevy_rapier2d::{pipeline::QueryFilter, plugin::RapierContext};
 
 use crate::{
+    controls::{Action, Actions},
     game::GameState,
     game_camera::{CameraTargetPos, CameraTargetScale, MainCamera, MouseWorldCoords},
+    levels::tools_unlocked,
+    minimap::{pointer_over_minimap, Minimap},
 };
 
 pub struct MousePlugin;
@@ -30,7 +33,14 @@ impl Plugin for MousePlugin {
             .add_event::<ClickSensorEvent>()
             .add_systems(
                 Update,
-                (motion, buttons, scroll_wheel, check_for_click_sensor)
+                (
+                    motion,
+                    buttons,
+                    scroll_wheel,
+                    check_for_click_sensor
+                        .run_if(tools_unlocked)
+                        .run_if(not(pointer_over_minimap)),
+                )
                     .chain()
                     .run_if(in_state(GameState::Playing)),
             );
@@ -104,6 +114,8 @@ fn buttons(
     q_camera: Query<&Transform, With<MainCamera>>,
     mut target_pos: ResMut<CameraTargetPos>,
     q_drag: Query<Entity, With<Drag>>,
+    q_minimap: Query<&Interaction, With<Minimap>>,
+    actions: Actions,
 ) {
     let ms = *mouse_state;
     match ms {
@@ -130,11 +142,16 @@ fn buttons(
             }
         }
         MouseState::Dragging => {
-            if button.just_pressed(MouseButton::Right) {
+            if button.just_pressed(MouseButton::Right) || actions.just_pressed(Action::Cancel) {
                 for entity in &q_drag {
                     cmd.entity(entity).insert(DragCancelRequest);
                 }
-            } else if button.just_pressed(MouseButton::Left) {
+            } else if (button.just_pressed(MouseButton::Left)
+                && q_minimap
+                    .iter()
+                    .all(|interaction| *interaction == Interaction::None))
+                || actions.just_pressed(Action::Confirm)
+            {
                 for entity in &q_drag {
                     cmd.entity(entity).insert(DragDropRequest);
                 }
@@ -194,24 +211,40 @@ fn scroll_wheel(
     }
 }
 
-fn check_for_click_sensor(
+pub fn check_for_click_sensor(
     q_click_sensor: Query<Entity, With<ClickSensor>>,
     mouse_state: Res<MouseState>,
     mouse_pos: Res<MouseWorldCoords>,
     button: Res<Input<MouseButton>>,
     rapier_context: Res<RapierContext>,
     mut ev_click_sensor: EventWriter<ClickSensorEvent>,
+    actions: Actions,
 ) {
-    if *mouse_state == MouseState::Default && button.just_released(MouseButton::Left) {
+    if *mouse_state == MouseState::Default
+        && (button.just_released(MouseButton::Left) || actions.just_pressed(Action::Confirm))
+    {
         if let Some(mouse_pos) = mouse_pos.0 {
-            let point = mouse_pos;
-            let filter = QueryFilter::default();
-            rapier_context.intersections_with_point(point, filter, |entity| {
-                if q_click_sensor.contains(entity) {
-                    ev_click_sensor.send(ClickSensorEvent(entity));
-                }
-                true
-            });
+            send_click_sensor_events(
+                mouse_pos,
+                &rapier_context,
+                &q_click_sensor,
+                &mut ev_click_sensor,
+            );
         }
     }
 }
+
+pub fn send_click_sensor_events(
+    point: Vec2,
+    rapier_context: &RapierContext,
+    q_click_sensor: &Query<Entity, With<ClickSensor>>,
+    ev_click_sensor: &mut EventWriter<ClickSensorEvent>,
+) {
+    let filter = QueryFilter::default();
+    rapier_context.intersections_with_point(point, filter, |entity| {
+        if q_click_sensor.contains(entity) {
+            ev_click_sensor.send(ClickSensorEvent(entity));
+        }
+        true
+    });
+}
@@ -7,7 +7,7 @@ use bevy_rapier2d::prelude::*;
 const PLAYER_TINT: Color = Color::rgba(0.8, 0.8, 2., 1.0);
 const ENEMY_TINT: Color = Color::rgba(1.5, 0.4, 0.4, 1.0);
 
-#[derive(Component, Clone, Copy, Eq, PartialEq, Debug, Default)]
+#[derive(Component, Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
 pub enum Team {
     #[default]
     Player,
@@ -55,6 +55,37 @@ impl ObjectGroup {
     pub const ENEMY_GROUPER_SENSOR: u32 = 1 << 19;
     pub const PLAYER_DEFENDER_SENSOR: u32 = 1 << 20;
     pub const ENEMY_DEFENDER_SENSOR: u32 = 1 << 21;
+
+    pub const NAMES: [(u32, &'static str); 20] = [
+        (Self::PLAYER_ROBOT, "PLAYER_ROBOT"),
+        (Self::ENEMY_ROBOT, "ENEMY_ROBOT"),
+        (Self::WALL, "WALL"),
+        (Self::ROBOT_STEERING_SENSOR, "ROBOT_STEERING_SENSOR"),
+        (Self::PLAYER_PORTAL, "PLAYER_PORTAL"),
+        (Self::ENEMY_PORTAL, "ENEMY_PORTAL"),
+        (Self::PLAYER_ARROW_SENSOR, "PLAYER_ARROW_SENSOR"),
+        (Self::ENEMY_ARROW_SENSOR, "ENEMY_ARROW_SENSOR"),
+        (Self::PLAYER_PORTAL_SENSOR, "PLAYER_PORTAL_SENSOR"),
+        (Self::ENEMY_PORTAL_SENSOR, "ENEMY_PORTAL_SENSOR"),
+        (Self::PLAYER_TARGETING_SENSOR, "PLAYER_TARGETING_SENSOR"),
+        (Self::ENEMY_TARGETING_SENSOR, "ENEMY_TARGETING_SENSOR"),
+        (Self::PLAYER_BULLET, "PLAYER_BULLET"),
+        (Self::ENEMY_BULLET, "ENEMY_BULLET"),
+        (Self::PLAYER_FORK_SENSOR, "PLAYER_FORK_SENSOR"),
+        (Self::ENEMY_FORK_SENSOR, "ENEMY_FORK_SENSOR"),
+        (Self::PLAYER_GROUPER_SENSOR, "PLAYER_GROUPER_SENSOR"),
+        (Self::ENEMY_GROUPER_SENSOR, "ENEMY_GROUPER_SENSOR"),
+        (Self::PLAYER_DEFENDER_SENSOR, "PLAYER_DEFENDER_SENSOR"),
+        (Self::ENEMY_DEFENDER_SENSOR, "ENEMY_DEFENDER_SENSOR"),
+    ];
+
+    pub fn names(bits: u32) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(group, _)| bits & group != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
 }
 
 pub fn coll_groups(members: u32, filters: u32) -> CollisionGroups {
@@ -0,0 +1,192 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{utils::translation_to_grid_coords, LevelIid};
+
+use crate::{
+    arrow::Arrow,
+    defender::Defender,
+    draggable::{DragState, InvalidDrag, ValidDrag, TOOL_SENSOR_RADIUS},
+    fork::{DraggedFork, Fork},
+    game::GameState,
+    game_camera::MouseScreenCoords,
+    grouper::Grouper,
+    levels::{LevelSize, LevelSizeInfo, WallCache},
+    physics::Team,
+};
+
+const PATH_LENGTH: f32 = 480.;
+const PATH_STEP: f32 = 4.;
+const PATH_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.8);
+const PATH_WALL_COLOR: Color = Color::ORANGE;
+const PATH_TOOL_COLOR: Color = Color::YELLOW;
+const PATH_END_RADIUS: f32 = 3.;
+const VALID_RADIUS_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.5);
+const INVALID_RADIUS_COLOR: Color = Color::rgba(1.0, 0.2, 0.2, 0.5);
+// screen pixels between the cursor and the invalid placement hint
+const HINT_OFFSET: Vec2 = Vec2::new(16., 16.);
+
+pub struct PlacementPreviewPlugin;
+
+impl Plugin for PlacementPreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (draw_sensor_radius, draw_predicted_paths, update_hint)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), despawn_hint);
+    }
+}
+
+#[derive(Component)]
+struct PlacementHint;
+
+enum PathEnd {
+    Free,
+    Wall,
+    Tool,
+}
+
+fn draw_sensor_radius(mut gizmos: Gizmos, q_drag: Query<(&Transform, &DragState, Has<ValidDrag>)>) {
+    for (tr, drag_state, valid) in &q_drag {
+        let pos = match drag_state {
+            DragState::Dragging => tr.translation,
+            DragState::SettingDirection(center_tr) => center_tr.translation,
+        };
+        let color = if valid {
+            VALID_RADIUS_COLOR
+        } else {
+            INVALID_RADIUS_COLOR
+        };
+        gizmos
+            .circle_2d(pos.truncate(), TOOL_SENSOR_RADIUS, color)
+            .segments(48);
+    }
+}
+
+fn draw_predicted_paths(
+    mut gizmos: Gizmos,
+    q_drag: Query<(&Transform, &DragState, Has<DraggedFork>)>,
+    q_level: Query<(&GlobalTransform, &WallCache), With<LevelIid>>,
+    level_size: Res<LevelSize>,
+    q_tool: Query<
+        (&GlobalTransform, &Team),
+        Or<(With<Arrow>, With<Fork>, With<Grouper>, With<Defender>)>,
+    >,
+) {
+    let (Ok((level_gtr, wall_cache)), Some(size_info)) = (q_level.get_single(), level_size.0)
+    else {
+        return;
+    };
+    let level_origin = level_gtr.translation().truncate();
+    let tools: Vec<Vec2> = q_tool
+        .iter()
+        .filter(|(_, team)| **team == Team::Player)
+        .map(|(gtr, _)| gtr.translation().truncate())
+        .collect();
+    for (tr, drag_state, is_fork) in &q_drag {
+        let DragState::SettingDirection(center_tr) = drag_state else {
+            continue;
+        };
+        let start = center_tr.translation.truncate();
+        let dir = tr.rotation.mul_vec3(Vec3::X).truncate();
+        // forks send robots 45° to each side of the chosen direction
+        let turns: &[f32] = if is_fork { &[PI / 4., -PI / 4.] } else { &[0.] };
+        for turn in turns {
+            let dir = Vec2::from_angle(*turn).rotate(dir);
+            let (end, path_end) =
+                trace_path(start, dir, level_origin, size_info, wall_cache, &tools);
+            gizmos.line_2d(start, end, PATH_COLOR);
+            match path_end {
+                PathEnd::Free => {}
+                PathEnd::Wall => {
+                    gizmos.circle_2d(end, PATH_END_RADIUS, PATH_WALL_COLOR);
+                }
+                PathEnd::Tool => {
+                    gizmos.circle_2d(end, PATH_END_RADIUS, PATH_TOOL_COLOR);
+                }
+            }
+        }
+    }
+}
+
+/// Walks from `start` until a wall, the level border or the sensor of another tool is reached.
+fn trace_path(
+    start: Vec2,
+    dir: Vec2,
+    level_origin: Vec2,
+    size_info: LevelSizeInfo,
+    wall_cache: &WallCache,
+    tools: &[Vec2],
+) -> (Vec2, PathEnd) {
+    // sensors the path starts in never fire, robots are already inside them
+    let already_inside: Vec<bool> = tools
+        .iter()
+        .map(|tool| tool.distance(start) < TOOL_SENSOR_RADIUS)
+        .collect();
+    let mut distance = 0.;
+    while distance < PATH_LENGTH {
+        distance += PATH_STEP;
+        let pos = start + dir * distance;
+        let coords = translation_to_grid_coords(pos - level_origin, size_info.tile_size_vec());
+        if !size_info.grid_coords_in_bound(coords) || wall_cache.items.contains_key(&coords) {
+            return (pos, PathEnd::Wall);
+        }
+        let entered_tool = tools
+            .iter()
+            .zip(&already_inside)
+            .any(|(tool, inside)| !inside && tool.distance(pos) < TOOL_SENSOR_RADIUS);
+        if entered_tool {
+            return (pos, PathEnd::Tool);
+        }
+    }
+    (start + dir * PATH_LENGTH, PathEnd::Free)
+}
+
+fn update_hint(
+    mut cmd: Commands,
+    q_invalid: Query<&InvalidDrag, With<DragState>>,
+    mouse_screen_pos: Res<MouseScreenCoords>,
+    mut q_hint: Query<(Entity, &mut Text, &mut Style), With<PlacementHint>>,
+) {
+    let hint = q_invalid
+        .iter()
+        .next()
+        .zip(mouse_screen_pos.0)
+        .map(|(invalid, pos)| (invalid.message(), pos + HINT_OFFSET));
+    match (hint, q_hint.get_single_mut()) {
+        (Some((message, pos)), Ok((_, mut text, mut style))) => {
+            text.sections[0].value = message.to_string();
+            style.left = Val::Px(pos.x);
+            style.top = Val::Px(pos.y);
+        }
+        (Some((message, pos)), Err(_)) => {
+            cmd.spawn((
+                PlacementHint,
+                TextBundle::from_section(
+                    message,
+                    TextStyle {
+                        font_size: 16.0,
+                        color: INVALID_RADIUS_COLOR.with_a(1.0),
+                        ..Default::default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(pos.x),
+                    top: Val::Px(pos.y),
+                    ..Default::default()
+                }),
+            ));
+        }
+        (None, Ok((entity, _, _))) => cmd.entity(entity).despawn_recursive(),
+        (None, Err(_)) => {}
+    }
+}
+
+fn despawn_hint(mut cmd: Commands, q_hint: Query<Entity, With<PlacementHint>>) {
+    for entity in &q_hint {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
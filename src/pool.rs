@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+
+/// Marks a hidden entity kept around to be reused instead of being despawned. Sparse set
+/// storage keeps adding and removing it cheap. Queries over live objects need
+/// `Without<Pooled>`, spawners take an entity `With<Pooled>` before spawning a new one.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Pooled;
+
+/// Hides the entity and hands it back to its pool.
+pub fn release(cmd: &mut Commands, entity: Entity) {
+    if let Some(mut entity_cmd) = cmd.get_entity(entity) {
+        entity_cmd.insert((Pooled, Visibility::Hidden));
+    }
+}
+
+/// Takes the entity out of its pool, the caller resets its components.
+pub fn reuse(cmd: &mut Commands, entity: Entity) {
+    cmd.entity(entity)
+        .remove::<Pooled>()
+        .insert(Visibility::Inherited);
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{borrow::Cow, time::Duration};
 
 use bevy::{math::vec2, prelude::*};
 use bevy_ecs_ldtk::{prelude::*, utils::ldtk_grid_coords_to_translation};
@@ -6,13 +6,17 @@ use bevy_rapier2d::prelude::*;
 use rand::Rng;
 
 use crate::{
-    explosion::ExplosionEvent,
-    game::{GameState, LevelState},
-    hp::{Dead, Life},
+    explosion::{ExplosionEvent, PORTAL_DEATH_EXPLOSION, PORTAL_SPAWN_EXPLOSION},
+    game::{GameRng, GameState, LevelState},
+    gameplay_events::PortalDestroyed,
+    hp::{Dead, LastHitBy, Life},
     levels::NoPlacingHere,
     load::TextureAssets,
     physics::{coll_groups, CollisionCache, ObjectGroup, Team},
     robot::{Robot, SpawnRobotEvent},
+    sprite_animation::{SpriteAnimation, PORTAL_IDLE},
+    status_effect::StatusEffects,
+    weapon::{parse_loadout, Weapon},
 };
 
 const PORTAL_SENSOR_WIDTH: f32 = 64.;
@@ -89,6 +93,22 @@ impl LdtkEntity for PortalBundle {
             portal: Portal {
                 dir: dir - pos,
                 spawn_timer: timer,
+                spawn_explosion: explosion_field(
+                    entity_instance,
+                    "spawn_explosion",
+                    PORTAL_SPAWN_EXPLOSION,
+                ),
+                death_explosion: explosion_field(
+                    entity_instance,
+                    "death_explosion",
+                    PORTAL_DEATH_EXPLOSION,
+                ),
+                weapons: entity_instance
+                    .get_maybe_string_field("weapons")
+                    .ok()
+                    .and_then(|weapons| weapons.as_deref())
+                    .map(parse_loadout)
+                    .unwrap_or_default(),
             },
             team: match entity_instance.identifier.as_str() {
                 "PlayerPortal" => Team::Player,
@@ -99,25 +119,50 @@ impl LdtkEntity for PortalBundle {
     }
 }
 
-#[derive(Component, Default)]
+/// Explosion preset named in the LDtk field, or `default` when it is empty.
+fn explosion_field(
+    entity_instance: &EntityInstance,
+    field: &str,
+    default: &'static str,
+) -> Cow<'static, str> {
+    entity_instance
+        .get_maybe_string_field(field)
+        .ok()
+        .and_then(|preset| preset.clone())
+        .map_or(Cow::Borrowed(default), Cow::Owned)
+}
+
+#[derive(Component, Default, Clone)]
 pub struct Portal {
     pub spawn_timer: Timer,
     pub dir: Vec2,
+    pub spawn_explosion: Cow<'static, str>,
+    pub death_explosion: Cow<'static, str>,
+    /// Mounted on the robots it spawns, a canon alone when empty.
+    pub weapons: Vec<&'static Weapon>,
 }
 
 #[derive(Component)]
-struct PortalSensor;
+pub struct PortalSensor;
 
 fn check_portal_robot_spawn(
     mut ev_spawn_robot: EventWriter<SpawnRobotEvent>,
     mut ev_explosion: EventWriter<ExplosionEvent>,
-    mut q_portal: Query<(&mut Portal, &Transform, &Team)>,
+    mut q_portal: Query<(&mut Portal, &Transform, &Team, Option<&StatusEffects>)>,
     time: Res<Time>,
     level_state: Res<State<LevelState>>,
 ) {
     if *level_state == LevelState::Playing {
-        for (mut portal, tr, team) in &mut q_portal {
-            portal.spawn_timer.tick(time.delta());
+        for (mut portal, tr, team, effects) in &mut q_portal {
+            // stunned portals hold their robots, slow and haste change the spawn rate
+            let factor = effects.map_or(1., |effects| {
+                if effects.stunned() {
+                    0.
+                } else {
+                    effects.speed_factor()
+                }
+            });
+            portal.spawn_timer.tick(time.delta().mul_f32(factor));
             if portal.spawn_timer.finished() && tr.translation != Vec3::ZERO {
                 portal.spawn_timer.reset();
                 ev_spawn_robot.send(SpawnRobotEvent {
@@ -125,21 +170,12 @@ fn check_portal_robot_spawn(
                     pos: tr.translation.truncate(),
                     team: *team,
                     // team: Team::Enemy,
+                    weapons: portal.weapons.clone(),
                 });
-                ev_explosion.send(ExplosionEvent {
-                    colors: [
-                        Color::rgba(1.0, 1.0, 1.0, 0.8),
-                        Color::rgba(0.9, 0.9, 0.9, 0.3),
-                        Color::rgba(0.8, 0.8, 0.8, 0.0),
-                    ],
-                    location: tr.translation.truncate(),
-                    duration: Duration::from_secs_f32(0.3),
-                    particle_radius: 8.,
-                    spread: 12.,
-                    particle_duration: Duration::from_secs_f32(0.8),
-                    particle_count: 40,
-                    ..Default::default()
-                })
+                ev_explosion.send(ExplosionEvent::new(
+                    portal.spawn_explosion.clone(),
+                    tr.translation.truncate(),
+                ))
             }
         }
     }
@@ -159,18 +195,20 @@ pub fn check_added_portals(
                     max_hp: PORTAL_START_HP,
                     curr_hp: PORTAL_START_HP,
                 },
-                SpriteBundle {
+                SpriteSheetBundle {
                     transform: *portal_tr,
-                    texture: match team {
+                    texture_atlas: match team {
                         Team::Player => texture_assets.player_portal.clone(),
                         Team::Enemy => texture_assets.enemy_portal.clone(),
                     },
-                    sprite: Sprite {
+                    sprite: TextureAtlasSprite {
                         custom_size: Some(vec2(64.0, 64.0)),
                         ..Default::default()
                     },
                     ..Default::default()
                 },
+                SpriteAnimation::new(PORTAL_IDLE),
+                StatusEffects::default(),
                 (
                     RigidBody::Fixed,
                     Collider::ball(PORTAL_COLLIDER_RADIUS),
@@ -227,13 +265,14 @@ fn check_sensor_collisions(
     mut q_robot: Query<(&mut ExternalForce, &Team), With<Robot>>,
     mut sensor_cache: Local<PortalSensorCache>,
     q_portal: Query<(&Portal, &Team)>,
+    mut rng: ResMut<GameRng>,
 ) {
     sensor_cache
         .0
         .cache_collisions(&mut collision_events, |entity| {
             q_portal_sensor.contains(entity)
         });
-    let mut rng = rand::thread_rng();
+    let rng = &mut rng.0;
     for (sensor_id, robot_set) in sensor_cache.0.cache.iter() {
         if let Ok(portal_id) = q_portal_sensor.get(*sensor_id).map(|parent| parent.get()) {
             for robot_id in robot_set.iter() {
@@ -254,14 +293,21 @@ fn check_sensor_collisions(
 
 fn check_dead(
     mut cmd: Commands,
-    q_portal: Query<(Entity, &Transform), (With<Portal>, With<Dead>)>,
+    q_portal: Query<(Entity, &Portal, &Transform, &Team, Option<&LastHitBy>), With<Dead>>,
     mut ev_explosion: EventWriter<ExplosionEvent>,
+    mut ev_portal_destroyed: EventWriter<PortalDestroyed>,
 ) {
-    for (e_portal, tr) in &q_portal {
+    for (e_portal, portal, tr, team, last_hit_by) in &q_portal {
         cmd.entity(e_portal).despawn_recursive();
-        ev_explosion.send(ExplosionEvent {
-            location: tr.translation.truncate(),
-            ..Default::default()
+        ev_portal_destroyed.send(PortalDestroyed {
+            portal: e_portal,
+            team: *team,
+            killer_team: last_hit_by.map(|hit| hit.team),
+            position: tr.translation.truncate(),
         });
+        ev_explosion.send(ExplosionEvent::new(
+            portal.death_explosion.clone(),
+            tr.translation.truncate(),
+        ));
     }
 }
@@ -3,24 +3,27 @@ use std::time::Duration;
 use bevy::{
     math::{vec2, vec3},
     prelude::*,
-    sprite::Anchor,
+    render::{primitives::Aabb, Extract, ExtractSchedule, RenderApp},
+    sprite::{Anchor, ExtractedSprite, ExtractedSprites, SpriteSystem},
 };
 
 use bevy_ecs_ldtk::LevelIid;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    explosion::ExplosionEvent,
+    explosion::{ExplosionEvent, Falloff, SplashDamage, ROBOT_DEATH_EXPLOSION},
     game::{GameState, LevelState},
-    hp::{Dead, Life},
+    game_camera::CameraTargetScale,
+    gameplay_events::{RobotKilled, RobotSpawned, ToolKind},
+    hp::{Dead, LastHitBy, Life},
     levels::WallCollider,
     load::TextureAssets,
     physics::{coll_groups, ObjectGroup, Team},
+    sprite_animation::{SpriteAnimation, ROBOT_IDLE},
+    status_effect::StatusEffects,
+    weapon::{mount_offset, Weapon, Weapons, NUZZLE_OFFSET},
 };
 
-use rand::prelude::*;
-
-const CANON_ROTATION_SPEED: f32 = 3.0;
 const TRAIN_ROTATION_SPEED: f32 = 5.0;
 const ROBOT_COLLIDER_RADIUS: f32 = 7.0;
 const ROBOT_COLLIDER_MASS: f32 = 1000.0;
@@ -34,9 +37,11 @@ const WHEEL_POSITIONS: [Vec2; 6] = [
     vec2(-0.5, 7.0),
     vec2(-6.0, 6.0),
 ];
-const CANON_COOLDOWN: Duration = Duration::from_secs(1);
-const CANON_VARIANCE: Duration = Duration::from_millis(900);
 const ROBOT_START_HP: f32 = 5.0;
+// half size of the box used to cull robots outside the view
+const ROBOT_EXTENTS: f32 = 12.;
+const DEFAULT_WHEELS_MAX_SCALE: f32 = 0.35;
+const DEFAULT_DEATH_SPLASH_RADIUS: f32 = 24.;
 
 //const ROBOT_STEERING_SENSOR_RADIUS: f32 = 32.;
 
@@ -46,7 +51,8 @@ impl Plugin for RobotPlugin {
     fn build(&self, app: &mut App) {
         app //.add_systems(OnEnter(GameState::Playing), spawn_robot)
             .add_event::<SpawnRobotEvent>()
-            .add_event::<FireEvent>()
+            .insert_resource(RobotDetail::default())
+            .insert_resource(DeathSplash::default())
             .add_systems(
                 PreUpdate,
                 (reset_robot_strength).run_if(in_state(GameState::Playing)),
@@ -55,21 +61,15 @@ impl Plugin for RobotPlugin {
                 Update,
                 (
                     check_for_target,
-                    set_canon_target,
-                    rotate_canon.after(set_canon_target),
-                    fire_canon,
-                    flash_nuzzle,
                     // set_engine_dir,
                     // apply_engine_dir.after(set_engine_dir),
                     apply_engine_dir,
                     (
-                        (
-                            check_spawn_robot,
-                            steering_forces,
-                            rotate_wheel_train,
-                            rotate_wheel,
-                        ),
-                        set_last_pos,
+                        check_spawn_robot,
+                        steering_forces,
+                        update_robot_detail,
+                        rotate_wheel_train,
+                        rotate_wheels,
                     )
                         .chain(),
                 )
@@ -81,56 +81,99 @@ impl Plugin for RobotPlugin {
                     .chain()
                     .run_if(in_state(GameState::Playing)),
             );
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(
+                ExtractSchedule,
+                extract_robot_sprites.after(SpriteSystem::ExtractSprites),
+            );
+        }
     }
 }
 
 #[derive(Component)]
 pub struct Robot;
 
-#[derive(Component)]
-pub struct Turret;
-
-#[derive(Component)]
-pub struct Canon;
-
-#[derive(Component, Default)]
-struct CanonTarget(Vec2);
-
 #[derive(Component, Default)]
 pub struct EngineDir(pub Vec2);
 
-#[derive(Component)]
-struct RobotBody;
+/// Last steering contributions, kept for the debug overlay.
+#[derive(Component, Default)]
+pub struct SteeringForces {
+    pub walls: Vec2,
+    pub robots: Vec2,
+}
 
-#[derive(Component)]
-pub struct Leg;
+/// Tool that last changed the robot's heading.
+#[derive(Component, Default)]
+pub struct LastRedirect(pub Option<(ToolKind, Entity)>);
 
 #[derive(Component)]
-pub struct WheelTrain;
+pub struct SpawnTime(pub Duration);
 
+/// Orientation of the wheel train, which turns towards the direction of motion.
 #[derive(Component, Default)]
-pub struct LastPos(Vec2);
+pub struct WheelTrain {
+    angle: f32,
+    last_pos: Vec2,
+}
 
+/// Wheel angles relative to the train. Not updated while the wheels are not drawn.
 #[derive(Component)]
-pub struct Wheel;
+pub struct Wheels {
+    angles: [f32; 6],
+    last_pos: [Vec2; 6],
+}
 
-#[derive(Component)]
-#[component(storage = "SparseSet")]
-pub struct RobotTarget(Entity);
+impl Wheels {
+    /// Wheels of a robot standing at `pos`, so they don't turn towards the level origin
+    /// on the first frame.
+    fn at(pos: Vec2) -> Self {
+        Self {
+            angles: [0.; 6],
+            last_pos: WHEEL_POSITIONS.map(|wheel_pos| pos + wheel_pos),
+        }
+    }
+}
 
-#[derive(Component, Default)]
-pub struct CanonCooldown(Timer);
+/// Level of detail of robots. Wheels are dropped when the camera is zoomed out past
+/// `wheels_max_scale`.
+#[derive(Resource)]
+pub struct RobotDetail {
+    pub wheels_max_scale: f32,
+    pub wheels: bool,
+}
 
-#[derive(Component)]
-pub struct NuzzleFlash(Timer);
+impl Default for RobotDetail {
+    fn default() -> Self {
+        Self {
+            wheels_max_scale: DEFAULT_WHEELS_MAX_SCALE,
+            wheels: true,
+        }
+    }
+}
 
-#[derive(Event)]
-pub struct FireEvent {
-    pub from_pos: Vec3,
-    pub to_target: Vec3,
-    pub team: Team,
+/// Damage dealt by dying robots to every robot and portal around them, friends included.
+/// Set from the `death_splash_damage` and `death_splash_radius` level fields, off while
+/// `damage` is 0.
+#[derive(Resource)]
+pub struct DeathSplash {
+    pub damage: f32,
+    pub radius: f32,
+}
+
+impl Default for DeathSplash {
+    fn default() -> Self {
+        Self {
+            damage: 0.,
+            radius: DEFAULT_DEATH_SPLASH_RADIUS,
+        }
+    }
 }
 
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct RobotTarget(pub Entity);
+
 // #[derive(Component)]
 // pub struct RobotSteeringSensor;
 
@@ -139,6 +182,8 @@ pub struct SpawnRobotEvent {
     pub pos: Vec2,
     pub dir: Vec2,
     pub team: Team,
+    /// Weapons to mount, a canon alone when empty.
+    pub weapons: Vec<&'static Weapon>,
 }
 
 fn check_spawn_robot(
@@ -146,283 +191,126 @@ fn check_spawn_robot(
     mut ev_spawn_robot: EventReader<SpawnRobotEvent>,
     assets: Res<TextureAssets>,
     q_level: Query<Entity, With<LevelIid>>,
+    mut ev_robot_spawned: EventWriter<RobotSpawned>,
+    time: Res<Time>,
 ) {
     if let Ok(e_level) = q_level.get_single() {
         for ev in ev_spawn_robot.read() {
             //info!("spawn robot event read {:?}", ev.pos);
-            let pos = ev.pos.extend(4.0);
-            // info!("team: {}", ev.team as u32);
-            let robot_id = cmd
-                .spawn((
-                    ev.team,
-                    Robot,
-                    Life {
-                        max_hp: ROBOT_START_HP,
-                        curr_hp: ROBOT_START_HP,
-                    },
-                    EngineDir::default(),
-                    (
-                        RigidBody::Dynamic,
-                        Collider::ball(ROBOT_COLLIDER_RADIUS),
-                        AdditionalMassProperties::Mass(ROBOT_COLLIDER_MASS),
-                        ExternalForce {
-                            force: vec2(0.0, 0.0),
-                            torque: 0.0,
-                        },
-                        ExternalImpulse::default(),
-                        Damping {
-                            linear_damping: ROBOT_DAMPING,
-                            angular_damping: 0.0,
-                        },
-                        LockedAxes::ROTATION_LOCKED,
-                        Friction {
-                            coefficient: 0.0,
-                            ..Default::default()
-                        },
-                        Restitution {
-                            coefficient: 0.5,
-                            ..Default::default()
-                        },
-                        match ev.team {
-                            Team::Player => coll_groups(
-                                ObjectGroup::PLAYER_ROBOT,
-                                ObjectGroup::ENEMY_ROBOT
-                                    | ObjectGroup::WALL
-                                    | ObjectGroup::PLAYER_PORTAL_SENSOR
-                                    | ObjectGroup::PLAYER_ROBOT
-                                    | ObjectGroup::PLAYER_ARROW_SENSOR
-                                    | ObjectGroup::ROBOT_STEERING_SENSOR
-                                    | ObjectGroup::ENEMY_TARGETING_SENSOR
-                                    | ObjectGroup::ENEMY_BULLET
-                                    | ObjectGroup::ENEMY_PORTAL
-                                    | ObjectGroup::PLAYER_FORK_SENSOR
-                                    | ObjectGroup::PLAYER_GROUPER_SENSOR
-                                    | ObjectGroup::PLAYER_DEFENDER_SENSOR,
-                            ),
-                            Team::Enemy => coll_groups(
-                                ObjectGroup::ENEMY_ROBOT,
-                                ObjectGroup::PLAYER_ROBOT
-                                    | ObjectGroup::WALL
-                                    | ObjectGroup::ENEMY_PORTAL_SENSOR
-                                    | ObjectGroup::ENEMY_ROBOT
-                                    | ObjectGroup::ENEMY_ARROW_SENSOR
-                                    | ObjectGroup::ROBOT_STEERING_SENSOR
-                                    | ObjectGroup::PLAYER_TARGETING_SENSOR
-                                    | ObjectGroup::PLAYER_BULLET
-                                    | ObjectGroup::PLAYER_PORTAL
-                                    | ObjectGroup::ENEMY_FORK_SENSOR
-                                    | ObjectGroup::ENEMY_GROUPER_SENSOR
-                                    | ObjectGroup::ENEMY_DEFENDER_SENSOR,
-                            ),
-                        },
-                    ),
-                    TransformBundle::from_transform(Transform::from_translation(pos)),
-                    VisibilityBundle::default(),
-                ))
-                .with_children(|cmd| {
-                    cmd.spawn((
-                        RobotBody,
-                        SpriteBundle {
-                            texture: assets.robot_body.clone(),
-                            transform: Transform::from_translation(vec3(0., 0., 1.)),
-                            sprite: Sprite {
-                                color: ev.team.tint(),
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        },
-                    ));
-                    cmd.spawn((
-                        Turret,
-                        SpriteBundle {
-                            texture: assets.robot_turret.clone(),
-                            transform: Transform::from_translation(vec3(0.0, 0.0, 1.2)),
-                            ..Default::default()
-                        },
-                    ));
-                    cmd.spawn((
-                        Canon,
-                        CanonCooldown({
-                            let mut t = Timer::new(CANON_COOLDOWN, TimerMode::Repeating);
-                            t.tick(CANON_COOLDOWN);
-                            t
-                        }),
-                        SpriteBundle {
-                            texture: assets.robot_canon.clone(),
-                            transform: Transform::from_translation(vec3(0.0, 0.0, 1.1)),
-                            ..Default::default()
-                        },
-                        CanonTarget::default(),
-                        ev.team,
-                    ))
-                    .with_children(|cmd| {
-                        cmd.spawn((
-                            NuzzleFlash({
-                                let mut result =
-                                    Timer::new(Duration::from_millis(100), TimerMode::Once);
-                                result.set_elapsed(Duration::from_millis(100));
-                                result
-                            }),
-                            SpriteBundle {
-                                texture: assets.nuzzle_flash.clone(),
-                                transform: Transform::from_xyz(8., 0., 2.)
-                                    .with_scale(Vec3::splat(0.5)),
-                                visibility: Visibility::Hidden,
-                                ..Default::default()
-                            },
-                        ));
-                    });
-                    cmd.spawn((
-                        LastPos::default(),
-                        SpriteBundle {
-                            texture: assets.robot_train.clone(),
-                            transform: Transform::from_translation(vec3(0.0, 0.0, 0.9)),
-                            ..Default::default()
-                        },
-                        WheelTrain,
-                    ))
-                    .with_children(|cmd| {
-                        for wp in WHEEL_POSITIONS {
-                            cmd.spawn((
-                                Wheel,
-                                SpriteBundle {
-                                    transform: Transform::from_translation(wp.extend(0.8)),
-                                    sprite: Sprite {
-                                        anchor: Anchor::Center,
-                                        custom_size: Some(vec2(4.0, 2.0)),
-                                        color: Color::BLACK,
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                },
-                                LastPos(wp),
-                                //LastPos::default(),
-                            ));
-                        }
-                    });
-                })
-                .id();
+            let robot_id = spawn_robot(
+                &mut cmd,
+                &assets,
+                ev.pos,
+                ev.team,
+                time.elapsed(),
+                Weapons::ready(&ev.weapons),
+                0.,
+            );
             cmd.entity(e_level).add_child(robot_id);
+            ev_robot_spawned.send(RobotSpawned {
+                robot: robot_id,
+                team: ev.team,
+                position: ev.pos,
+            });
         }
     }
 }
 
-fn set_canon_target(
-    mut cmd: Commands,
-    mut canon_q: Query<(&GlobalTransform, &mut CanonTarget, &Parent), With<Canon>>,
-    robot_q: Query<(&EngineDir, Option<&RobotTarget>, &GlobalTransform), With<Robot>>,
-    other_q: Query<&GlobalTransform>,
-) {
-    for (cannon_gtr, mut canon_target_dir, parent) in canon_q.iter_mut() {
-        if let Ok((engine_dir, robot_target, _)) = robot_q.get(parent.get()) {
-            canon_target_dir.0 = engine_dir.0;
-            if let Some(RobotTarget(e_other)) = robot_target {
-                if let Ok(other_gtr) = other_q.get(*e_other) {
-                    let dir_vec =
-                        other_gtr.translation().truncate() - cannon_gtr.translation().truncate();
-                    if dir_vec.length() > 200. {
-                        cmd.entity(parent.get()).remove::<RobotTarget>();
-                    } else {
-                        canon_target_dir.0 = dir_vec.normalize();
-                    }
-                }
-            }
-        }
-    }
-}
-
-fn fire_canon(
-    //mut cmd: Commands,
-    mut canon_q: Query<
+/// Spawns a robot as a single entity. The caller parents it to the level.
+pub fn spawn_robot(
+    cmd: &mut Commands,
+    assets: &TextureAssets,
+    pos: Vec2,
+    team: Team,
+    spawn_time: Duration,
+    weapons: Weapons,
+    angle: f32,
+) -> Entity {
+    let pos = pos.extend(4.0);
+    cmd.spawn((
+        team,
+        Robot,
+        Life {
+            max_hp: ROBOT_START_HP,
+            curr_hp: ROBOT_START_HP,
+        },
+        EngineDir::default(),
+        SteeringForces::default(),
+        LastRedirect::default(),
+        SpawnTime(spawn_time),
+        StatusEffects::default(),
         (
-            &GlobalTransform,
-            &Parent,
-            &mut CanonCooldown,
-            &Children,
-            &Team,
+            RigidBody::Dynamic,
+            Collider::ball(ROBOT_COLLIDER_RADIUS),
+            AdditionalMassProperties::Mass(ROBOT_COLLIDER_MASS),
+            ExternalForce {
+                force: vec2(0.0, 0.0),
+                torque: 0.0,
+            },
+            ExternalImpulse::default(),
+            Velocity::zero(),
+            Damping {
+                linear_damping: ROBOT_DAMPING,
+                angular_damping: 0.0,
+            },
+            LockedAxes::ROTATION_LOCKED,
+            Friction {
+                coefficient: 0.0,
+                ..Default::default()
+            },
+            Restitution {
+                coefficient: 0.5,
+                ..Default::default()
+            },
+            match team {
+                Team::Player => coll_groups(
+                    ObjectGroup::PLAYER_ROBOT,
+                    ObjectGroup::ENEMY_ROBOT
+                        | ObjectGroup::WALL
+                        | ObjectGroup::PLAYER_PORTAL_SENSOR
+                        | ObjectGroup::PLAYER_ROBOT
+                        | ObjectGroup::PLAYER_ARROW_SENSOR
+                        | ObjectGroup::ROBOT_STEERING_SENSOR
+                        | ObjectGroup::ENEMY_TARGETING_SENSOR
+                        | ObjectGroup::ENEMY_BULLET
+                        | ObjectGroup::ENEMY_PORTAL
+                        | ObjectGroup::PLAYER_FORK_SENSOR
+                        | ObjectGroup::PLAYER_GROUPER_SENSOR
+                        | ObjectGroup::PLAYER_DEFENDER_SENSOR,
+                ),
+                Team::Enemy => coll_groups(
+                    ObjectGroup::ENEMY_ROBOT,
+                    ObjectGroup::PLAYER_ROBOT
+                        | ObjectGroup::WALL
+                        | ObjectGroup::ENEMY_PORTAL_SENSOR
+                        | ObjectGroup::ENEMY_ROBOT
+                        | ObjectGroup::ENEMY_ARROW_SENSOR
+                        | ObjectGroup::ROBOT_STEERING_SENSOR
+                        | ObjectGroup::PLAYER_TARGETING_SENSOR
+                        | ObjectGroup::PLAYER_BULLET
+                        | ObjectGroup::PLAYER_PORTAL
+                        | ObjectGroup::ENEMY_FORK_SENSOR
+                        | ObjectGroup::ENEMY_GROUPER_SENSOR
+                        | ObjectGroup::ENEMY_DEFENDER_SENSOR,
+                ),
+            },
         ),
-        With<Canon>,
-    >,
-    robot_q: Query<Option<&RobotTarget>, With<Robot>>,
-    other_q: Query<&GlobalTransform, &Transform>,
-    mut nuzzle_q: Query<(&mut NuzzleFlash, &GlobalTransform)>,
-    time: Res<Time>,
-    mut ev_fire: EventWriter<FireEvent>,
-    q_level: Query<&GlobalTransform, With<LevelIid>>,
-    level_state: Res<State<LevelState>>,
-) {
-    if *level_state != LevelState::Playing {
-        return;
-    }
-    for (canon_gtr, parent, mut cooldown, children, team) in &mut canon_q {
-        cooldown.0.tick(time.delta());
-        if cooldown.0.finished() {
-            let variance = CANON_VARIANCE.as_secs_f32();
-            let cd = CANON_COOLDOWN.as_secs_f32();
-            let ms = variance * rand::thread_rng().gen::<f32>() - variance / 2.;
-            cooldown.0.set_duration(Duration::from_secs_f32(cd + ms));
-            cooldown.0.reset();
-            if let Ok(Some(RobotTarget(e_other))) = robot_q.get(parent.get()) {
-                if let Ok(other_gtr) = other_q.get(*e_other) {
-                    let firing_to = (other_gtr.translation() - canon_gtr.translation()).normalize();
-                    let curr_dir = (canon_gtr.transform_point(vec3(1.0, 0.0, 0.0))
-                        - canon_gtr.translation())
-                    .normalize();
-                    let quat =
-                        Quat::from_rotation_arc_2d(curr_dir.truncate(), firing_to.truncate());
-                    let (_axis, angle) = quat.to_axis_angle();
-                    if angle.abs() < 0.1 {
-                        for child in children.iter() {
-                            if let Ok((mut nuzzle, nuzzle_gtr)) = nuzzle_q.get_mut(*child) {
-                                if let Ok(level_gtr) = q_level.get_single() {
-                                    nuzzle.0.reset();
-                                    ev_fire.send(FireEvent {
-                                        from_pos: nuzzle_gtr.translation()
-                                            - level_gtr.translation(),
-                                        to_target: other_gtr.translation()
-                                            - level_gtr.translation(),
-                                        team: *team,
-                                    });
-                                }
-                            }
-                        }
-                        cooldown.0.reset();
-                    }
-                }
-            }
-        }
-    }
-}
-
-fn flash_nuzzle(mut q_nuzzle: Query<(&mut NuzzleFlash, &mut Visibility)>, time: Res<Time>) {
-    for (mut nuzzle, mut visibility) in &mut q_nuzzle {
-        nuzzle.0.tick(time.delta());
-        if nuzzle.0.finished() {
-            *visibility = Visibility::Hidden;
-        } else {
-            *visibility = Visibility::Visible;
-        }
-    }
-}
-
-fn rotate_canon(
-    mut q_canon: Query<(&GlobalTransform, &mut Transform, &CanonTarget), With<Canon>>,
-    time: Res<Time>,
-) {
-    for (global_tr, mut tr, target_dir) in q_canon.iter_mut() {
-        // cannon rotation
-        const RIGHT: Vec3 = vec3(1.0, 0.0, 0.0);
-        let curr_vec = global_tr.transform_point(RIGHT) - global_tr.translation();
-        let max_rotation = CANON_ROTATION_SPEED * time.delta_seconds();
-        let mut quat = Quat::from_rotation_arc_2d(curr_vec.truncate(), target_dir.0);
-        let (axis, angle) = quat.to_axis_angle();
-        if angle.abs() <= max_rotation {
-            tr.rotate(quat);
-        } else {
-            quat = Quat::from_axis_angle(axis, max_rotation * angle.signum());
-            tr.rotate(quat);
-        }
-    }
+        (
+            weapons,
+            WheelTrain {
+                angle: 0.,
+                last_pos: pos.truncate(),
+            },
+            Wheels::at(pos.truncate()),
+            SpriteAnimation::new(ROBOT_IDLE),
+        ),
+        TransformBundle::from_transform(
+            Transform::from_translation(pos).with_rotation(Quat::from_rotation_z(angle)),
+        ),
+        VisibilityBundle::default(),
+        Aabb::from_min_max(Vec3::splat(-ROBOT_EXTENTS), Vec3::splat(ROBOT_EXTENTS)),
+    ))
+    .id()
 }
 
 fn reset_robot_strength(mut q_robot: Query<&mut ExternalForce, With<Robot>>) {
@@ -432,75 +320,187 @@ fn reset_robot_strength(mut q_robot: Query<&mut ExternalForce, With<Robot>>) {
 }
 
 fn apply_engine_dir(
-    mut q_robot: Query<(&EngineDir, &mut ExternalForce, Option<&RobotTarget>), With<Robot>>,
+    mut q_robot: Query<
+        (
+            &EngineDir,
+            &mut ExternalForce,
+            Option<&RobotTarget>,
+            &StatusEffects,
+        ),
+        With<Robot>,
+    >,
     level_state: Res<State<LevelState>>,
 ) {
     if *level_state == LevelState::Playing {
-        for (engine_dir, mut external_force, robot_target) in q_robot.iter_mut() {
-            if robot_target.is_none() {
-                external_force.force += engine_dir.0 * ROBOT_MOVEMENT_STRENGTH;
+        for (engine_dir, mut external_force, robot_target, effects) in q_robot.iter_mut() {
+            if robot_target.is_none() && !effects.stunned() {
+                external_force.force +=
+                    engine_dir.0 * ROBOT_MOVEMENT_STRENGTH * effects.speed_factor();
             }
         }
     }
 }
 
-fn set_last_pos(mut q_entity: Query<(&mut LastPos, &GlobalTransform)>) {
-    for (mut last_pos, global_tr) in q_entity.iter_mut() {
-        last_pos.0 = global_tr.translation().truncate();
+fn update_robot_detail(
+    mut detail: ResMut<RobotDetail>,
+    camera_target_scale: Res<CameraTargetScale>,
+) {
+    let wheels = camera_target_scale.get() <= detail.wheels_max_scale;
+    if detail.wheels != wheels {
+        detail.wheels = wheels;
     }
 }
 
-fn rotate_wheel_train(
-    mut q_wheel_train: Query<(&LastPos, &mut Transform, &GlobalTransform), With<WheelTrain>>,
-    time: Res<Time>,
-) {
-    for (last_pos, mut tr, global_tr) in q_wheel_train.iter_mut() {
-        let dir = global_tr.translation().truncate() - last_pos.0;
+// positions are in level coordinates like the spawn position
+fn rotate_wheel_train(mut q_wheel_train: Query<(&mut WheelTrain, &Transform)>, time: Res<Time>) {
+    for (mut train, tr) in q_wheel_train.iter_mut() {
+        let pos = tr.translation.truncate();
+        let dir = pos - train.last_pos;
         let speed = dir.length() / time.delta_seconds();
         if speed > 3.0 {
-            let ndir = dir.normalize();
-            let old_dir = (global_tr.transform_point(vec3(1.0, 0.0, 0.0))
-                - global_tr.translation())
-            .normalize()
-            .truncate();
-            let mut angle = old_dir.angle_between(ndir);
+            let mut angle = Vec2::from_angle(train.angle).angle_between(dir.normalize());
             let max_angle = TRAIN_ROTATION_SPEED * time.delta_seconds();
-            // info!("{} {}", angle, max_angle);
             if angle.abs() > max_angle {
                 angle = max_angle * angle.signum();
             }
-            tr.rotate(Quat::from_rotation_z(angle));
-            // tr.rotation = Quat::from_rotation_arc_2d(vec2(1.0, 0.0), dir.normalize());
-        } else {
-            //tr.rotation = Quat::IDENTITY;
+            train.angle += angle;
         }
+        train.last_pos = pos;
     }
 }
 
-fn rotate_wheel(
-    mut q_wheel: Query<(&LastPos, &mut Transform, &GlobalTransform), With<Wheel>>,
+fn rotate_wheels(
+    mut q_wheels: Query<(&mut Wheels, &WheelTrain, &Transform)>,
+    detail: Res<RobotDetail>,
     time: Res<Time>,
 ) {
-    for (last_pos, mut tr, global_tr) in q_wheel.iter_mut() {
-        let dir = global_tr.translation().truncate() - last_pos.0;
-        let speed = dir.length() / time.delta_seconds();
-        if speed > 3.0 {
-            let ndir = dir.normalize();
-            let old_dir = (global_tr.transform_point(vec3(1.0, 0.0, 0.0))
-                - global_tr.translation())
-            .normalize()
-            .truncate();
-            let angle = old_dir.angle_between(ndir);
-            tr.rotate(Quat::from_rotation_z(angle));
-        } else {
-            //tr.rotation = Quat::IDENTITY;
+    if !detail.wheels {
+        return;
+    }
+    for (mut wheels, train, tr) in q_wheels.iter_mut() {
+        let train_rotation = Vec2::from_angle(train.angle);
+        for (i, wheel_pos) in WHEEL_POSITIONS.iter().enumerate() {
+            let pos = tr.translation.truncate() + train_rotation.rotate(*wheel_pos);
+            let dir = pos - wheels.last_pos[i];
+            let speed = dir.length() / time.delta_seconds();
+            // wheels point where they move
+            if speed > 3.0 {
+                wheels.angles[i] = dir.y.atan2(dir.x) - train.angle;
+            }
+            wheels.last_pos[i] = pos;
+        }
+    }
+}
+
+/// Draws each robot as several sprites extracted from the one entity, like text glyphs.
+fn extract_robot_sprites(
+    mut cmd: Commands,
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    assets: Extract<Option<Res<TextureAssets>>>,
+    texture_atlases: Extract<Res<Assets<TextureAtlas>>>,
+    detail: Extract<Res<RobotDetail>>,
+    q_robot: Extract<
+        Query<(
+            Entity,
+            &ViewVisibility,
+            &GlobalTransform,
+            &Team,
+            &Weapons,
+            &WheelTrain,
+            &Wheels,
+            &SpriteAnimation,
+        )>,
+    >,
+) {
+    let Some(assets) = assets.as_ref() else {
+        return;
+    };
+    let Some(body_atlas) = texture_atlases.get(&assets.robot_body) else {
+        return;
+    };
+    for (e_robot, view_visibility, gtr, team, weapons, train, wheels, animation) in q_robot.iter() {
+        if !view_visibility.get() {
+            continue;
         }
+        let mut add_sprite = |image: &Handle<Image>,
+                              rect: Option<Rect>,
+                              tr: Transform,
+                              color: Color,
+                              custom_size: Option<Vec2>| {
+            extracted_sprites.sprites.insert(
+                cmd.spawn_empty().id(),
+                ExtractedSprite {
+                    transform: gtr.mul_transform(tr),
+                    color,
+                    rect,
+                    custom_size,
+                    image_handle_id: image.id(),
+                    flip_x: false,
+                    flip_y: false,
+                    anchor: Anchor::Center.as_vec(),
+                    original_entity: Some(e_robot),
+                },
+            );
+        };
+        let train_tr = Transform::from_translation(vec3(0.0, 0.0, 0.9))
+            .with_rotation(Quat::from_rotation_z(train.angle));
+        add_sprite(&assets.robot_train, None, train_tr, Color::WHITE, None);
+        if detail.wheels {
+            for (wheel_pos, angle) in WHEEL_POSITIONS.iter().zip(wheels.angles) {
+                let wheel_tr = Transform::from_translation(wheel_pos.extend(0.8))
+                    .with_rotation(Quat::from_rotation_z(angle));
+                add_sprite(
+                    &Handle::default(),
+                    None,
+                    train_tr * wheel_tr,
+                    Color::BLACK,
+                    Some(vec2(4.0, 2.0)),
+                );
+            }
+        }
+        add_sprite(
+            &body_atlas.texture,
+            body_atlas.textures.get(animation.index()).copied(),
+            Transform::from_translation(vec3(0., 0., 1.)),
+            team.tint(),
+            None,
+        );
+        for (index, mount) in weapons.0.iter().enumerate() {
+            let canon_tr =
+                Transform::from_translation(mount_offset(index, weapons.0.len()).extend(1.1))
+                    .with_rotation(Quat::from_rotation_z(mount.angle));
+            add_sprite(
+                &assets.robot_canon,
+                None,
+                canon_tr,
+                mount.weapon.color,
+                None,
+            );
+            if mount.flashing() {
+                let nuzzle_tr =
+                    Transform::from_xyz(NUZZLE_OFFSET, 0., 2.).with_scale(Vec3::splat(0.5));
+                add_sprite(
+                    &assets.nuzzle_flash,
+                    None,
+                    canon_tr * nuzzle_tr,
+                    Color::WHITE,
+                    None,
+                );
+            }
+        }
+        add_sprite(
+            &assets.robot_turret,
+            None,
+            Transform::from_translation(vec3(0.0, 0.0, 1.2)),
+            Color::WHITE,
+            None,
+        );
     }
 }
 
 fn steering_forces(
     rapier_context: Res<RapierContext>,
-    mut q_robot_force: Query<(Entity, &mut ExternalForce), With<Robot>>,
+    mut q_robot_force: Query<(Entity, &mut ExternalForce, &mut SteeringForces), With<Robot>>,
     q_robot: Query<&GlobalTransform, With<Robot>>,
     q_wall: Query<&GlobalTransform, With<WallCollider>>,
     // q_other: Query<&GlobalTransform, With<Collider>>,
@@ -514,9 +514,10 @@ fn steering_forces(
         ..Default::default()
     };
     let shape = Collider::ball(radius);
-    for (e_robot, mut ext_force) in &mut q_robot_force {
+    for (e_robot, mut ext_force, mut steering) in &mut q_robot_force {
         let gtr = q_robot.get(e_robot).unwrap();
         let shape_pos = gtr.translation().truncate();
+        *steering = SteeringForces::default();
         rapier_context.intersections_with_shape(shape_pos, 0.0f32, &shape, filter, |entity| {
             if entity != e_robot {
                 if let Ok(wall_gtr) = q_wall.get(entity) {
@@ -525,12 +526,14 @@ fn steering_forces(
                     let strength = (1.5 * radius - len).abs().powf(1.1) * 1750.;
                     let unit_vec = vec.normalize_or_zero();
                     ext_force.force += unit_vec * strength;
+                    steering.walls += unit_vec * strength;
                 } else if let Ok(other_gtr) = q_robot.get(entity) {
                     let vec = gtr.translation().truncate() - other_gtr.translation().truncate();
                     let len = vec.length();
                     let strength = (1.0 * radius - len).abs().powf(1.3) * 3000.;
                     let unit_vec = vec.normalize_or_zero();
                     ext_force.force += unit_vec * strength;
+                    steering.robots += unit_vec * strength;
                 }
             }
             true
@@ -550,12 +553,21 @@ fn last_strength_check(mut q_robot: Query<&mut ExternalForce, With<Robot>>) {
 fn check_for_target(
     mut cmd: Commands,
     rapier_context: Res<RapierContext>,
-    q_robot: Query<(Entity, &GlobalTransform, &Team), With<Robot>>,
+    q_robot: Query<(Entity, &GlobalTransform, &Team, &Weapons), With<Robot>>,
     q_other: Query<(Entity, &GlobalTransform, &Team)>,
 ) {
-    let radius = 96.;
-    let shape = Collider::ball(radius);
-    for (e_robot, robot_gtr, robot_team) in &q_robot {
+    // most robots share a loadout, so few shapes are needed
+    let mut shapes: Vec<(f32, Collider)> = vec![];
+    for (e_robot, robot_gtr, robot_team, weapons) in &q_robot {
+        let range = weapons.range();
+        let shape = match shapes.iter().find(|(radius, _)| *radius == range) {
+            Some((_, shape)) => shape.clone(),
+            None => {
+                let shape = Collider::ball(range);
+                shapes.push((range, shape.clone()));
+                shape
+            }
+        };
         let filter = QueryFilter {
             groups: Some(match *robot_team {
                 Team::Player => coll_groups(
@@ -577,8 +589,12 @@ fn check_for_target(
             filter,
             |e_other| {
                 if let Ok((_, other_gtr, _)) = q_other.get(e_other) {
-                    let dist = other_gtr.translation().distance(robot_gtr.translation());
-                    if min.is_none() || min.unwrap().1 > dist {
+                    let dist = other_gtr
+                        .translation()
+                        .truncate()
+                        .distance(robot_gtr.translation().truncate());
+                    // the shape reaches colliders touching it, weapons need the center in range
+                    if dist <= range && (min.is_none() || min.unwrap().1 > dist) {
                         min = Some((e_other, dist));
                     }
                 }
@@ -594,14 +610,31 @@ fn check_for_target(
 
 fn check_dead(
     mut cmd: Commands,
-    q_robot: Query<(Entity, &Transform), (With<Robot>, With<Dead>)>,
+    q_robot: Query<(Entity, &Transform, &Team, Option<&LastHitBy>), (With<Robot>, With<Dead>)>,
     mut ev_explosion: EventWriter<ExplosionEvent>,
+    mut ev_robot_killed: EventWriter<RobotKilled>,
+    death_splash: Res<DeathSplash>,
 ) {
-    for (e_robot, tr) in &q_robot {
+    for (e_robot, tr, team, last_hit_by) in &q_robot {
         cmd.entity(e_robot).despawn_recursive();
+        ev_robot_killed.send(RobotKilled {
+            victim: e_robot,
+            team: *team,
+            killer: last_hit_by.map(|hit| hit.shooter),
+            killer_team: last_hit_by.map(|hit| hit.team),
+            position: tr.translation.truncate(),
+        });
         ev_explosion.send(ExplosionEvent {
-            location: tr.translation.truncate(),
-            ..Default::default()
+            damage: (death_splash.damage > 0.).then_some(SplashDamage {
+                damage: death_splash.damage,
+                radius: death_splash.radius,
+                falloff: Falloff::Linear,
+                team: *team,
+                friendly_fire: true,
+                source: e_robot,
+                effect: None,
+            }),
+            ..ExplosionEvent::new(ROBOT_DEATH_EXPLOSION, tr.translation.truncate())
         });
     }
 }
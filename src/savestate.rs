@@ -0,0 +1,1186 @@
+use std::{f32::consts::PI, str::FromStr, time::Duration};
+
+use bevy::{
+    ecs::{event::Events, system::SystemParam},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::{utils::translation_to_grid_coords, LevelIid};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    arrow::{spawn_arrow, Arrow},
+    bullet::{bullet_bundle, Bullet},
+    controls::{Action, Actions},
+    defender::{spawn_defender, Defender, DefenderShield},
+    fork::{spawn_fork, Fork},
+    game::{GameState, LevelState},
+    game_ui::{ChangeLevelEvent, ResetLevelEvent},
+    gameplay_events::ToolKind,
+    grouper::{spawn_grouper, Grouper},
+    hp::Life,
+    intro::CameraIntro,
+    inventory::Inventory,
+    levels::{LevelIndex, LevelLoadedEvent, LevelSize},
+    load::TextureAssets,
+    physics::Team,
+    pool::Pooled,
+    portal::{Portal, PortalSensor},
+    robot::{spawn_robot, EngineDir, LastRedirect, Robot, RobotTarget, SpawnTime},
+    status_effect::{StatusEffect, StatusEffects, StatusKind},
+    storage,
+    weapon::{Weapon, WeaponMount, Weapons},
+};
+
+pub const QUICKSAVE_FILE: &str = "quicksave.txt";
+// portals come from the level file, so a saved one must be found at the same place
+const PORTAL_MATCH_DISTANCE: f32 = 1.;
+
+pub struct SaveStatePlugin;
+
+impl Plugin for SaveStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveStateEvent>()
+            .add_event::<LoadStateEvent>()
+            .insert_resource(PendingLoad::default())
+            .insert_resource(RestoredRobots::default())
+            .add_systems(
+                PreUpdate,
+                skip_restored_contacts.run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (quick_save_load, save_state, load_state, wait_level_loaded)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                OnEnter(LevelState::Planning),
+                apply_pending_load.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Writes the running level to the given file.
+#[derive(Event)]
+pub struct SaveStateEvent(pub String);
+
+/// Respawns the level of the given save file and restores the save on top of it.
+#[derive(Event)]
+pub struct LoadStateEvent(pub String);
+
+#[derive(Resource, Default)]
+struct PendingLoad {
+    state: Option<SaveState>,
+    // the save is restored once the reloaded level reaches planning
+    level_loaded: bool,
+}
+
+/// Robots spawned by the last restore, until their first contacts are filtered.
+#[derive(Resource, Default)]
+pub struct RestoredRobots(HashSet<Entity>);
+
+/// Snapshot of a running level. Entities are the ids of the saving session, they only
+/// link robots, portals and tools back together on load.
+#[derive(Default)]
+pub struct SaveState {
+    level: usize,
+    playing: bool,
+    inventory: [u32; 4],
+    portals: Vec<SavedPortal>,
+    robots: Vec<SavedRobot>,
+    tools: Vec<SavedTool>,
+    bullets: Vec<SavedBullet>,
+    effects: Vec<SavedEffect>,
+}
+
+struct SavedPortal {
+    id: Entity,
+    team: Team,
+    pos: Vec2,
+    hp: f32,
+    max_hp: f32,
+    spawn_elapsed: f32,
+}
+
+struct SavedRobot {
+    id: Entity,
+    team: Team,
+    pos: Vec2,
+    vel: Vec2,
+    angle: f32,
+    angular_vel: f32,
+    hp: f32,
+    max_hp: f32,
+    engine_dir: Vec2,
+    age: f32,
+    target: Option<Entity>,
+    last_redirect: Option<(ToolKind, Entity)>,
+    weapons: Vec<SavedWeapon>,
+}
+
+struct SavedWeapon {
+    weapon: &'static Weapon,
+    cooldown_duration: f32,
+    cooldown_elapsed: f32,
+    angle: f32,
+    burst_left: usize,
+    burst_elapsed: f32,
+}
+
+struct SavedTool {
+    id: Entity,
+    team: Team,
+    pos: Vec3,
+    angle: f32,
+    kind: SavedToolKind,
+}
+
+enum SavedToolKind {
+    Arrow(Vec2),
+    Fork {
+        dirs: [Vec2; 2],
+        forked_count: usize,
+    },
+    Grouper {
+        dir: Vec2,
+        group: Vec<Entity>,
+    },
+    Defender {
+        dir: Vec2,
+        group: Vec<Entity>,
+    },
+}
+
+struct SavedBullet {
+    team: Team,
+    pos: Vec2,
+    angle: f32,
+    elapsed: f32,
+    shooter: Entity,
+    weapon: &'static Weapon,
+    lifetime: f32,
+}
+
+struct SavedEffect {
+    target: Entity,
+    effect: StatusEffect,
+}
+
+// One object per line, values separated by spaces:
+//   level <index> <planning|playing>
+//   inventory <arrows> <forks> <groupers> <defenders>
+//   portal <id> <team> <x> <y> <hp> <max hp> <spawn timer elapsed>
+//   robot <id> <team> <x> <y> <vx> <vy> <angle> <angular vel> <hp> <max hp> <engine x>
+//         <engine y> <age> <target id|-> <redirect tool> <tool id>|-
+//         [<weapon> <cooldown duration> <cooldown elapsed> <angle> <burst left> <burst elapsed>]...
+//   arrow <id> <team> <x> <y> <z> <angle> <dir x> <dir y>
+//   fork <id> <team> <x> <y> <z> <angle> <dir1 x> <dir1 y> <dir2 x> <dir2 y> <forked count>
+//   grouper|defender <id> <team> <x> <y> <z> <angle> <dir x> <dir y> <robot ids...>
+//   bullet <team> <x> <y> <angle> <elapsed> <shooter id> <weapon> <lifetime>
+//   effect <robot or portal id> <kind> <strength> <remaining>
+impl SaveState {
+    fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "level {} {}",
+                self.level,
+                if self.playing { "playing" } else { "planning" }
+            ),
+            format!(
+                "inventory {} {} {} {}",
+                self.inventory[0], self.inventory[1], self.inventory[2], self.inventory[3]
+            ),
+        ];
+        for portal in &self.portals {
+            lines.push(format!(
+                "portal {} {} {} {} {} {} {}",
+                portal.id.to_bits(),
+                team_name(portal.team),
+                portal.pos.x,
+                portal.pos.y,
+                portal.hp,
+                portal.max_hp,
+                portal.spawn_elapsed
+            ));
+        }
+        for robot in &self.robots {
+            lines.push(format!(
+                "robot {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}{}",
+                robot.id.to_bits(),
+                team_name(robot.team),
+                robot.pos.x,
+                robot.pos.y,
+                robot.vel.x,
+                robot.vel.y,
+                robot.angle,
+                robot.angular_vel,
+                robot.hp,
+                robot.max_hp,
+                robot.engine_dir.x,
+                robot.engine_dir.y,
+                robot.age,
+                robot
+                    .target
+                    .map_or("-".to_string(), |target| target.to_bits().to_string()),
+                robot
+                    .last_redirect
+                    .map_or("-".to_string(), |(kind, tool)| format!(
+                        "{} {}",
+                        tool_kind_name(kind),
+                        tool.to_bits()
+                    )),
+                robot
+                    .weapons
+                    .iter()
+                    .map(|saved| format!(
+                        " {} {} {} {} {} {}",
+                        saved.weapon.name,
+                        saved.cooldown_duration,
+                        saved.cooldown_elapsed,
+                        saved.angle,
+                        saved.burst_left,
+                        saved.burst_elapsed
+                    ))
+                    .collect::<String>()
+            ));
+        }
+        for tool in &self.tools {
+            let (name, values) = match &tool.kind {
+                SavedToolKind::Arrow(dir) => ("arrow", format!("{} {}", dir.x, dir.y)),
+                SavedToolKind::Fork { dirs, forked_count } => (
+                    "fork",
+                    format!(
+                        "{} {} {} {} {}",
+                        dirs[0].x, dirs[0].y, dirs[1].x, dirs[1].y, forked_count
+                    ),
+                ),
+                SavedToolKind::Grouper { dir, group } => (
+                    "grouper",
+                    format!("{} {}{}", dir.x, dir.y, entity_list(group)),
+                ),
+                SavedToolKind::Defender { dir, group } => (
+                    "defender",
+                    format!("{} {}{}", dir.x, dir.y, entity_list(group)),
+                ),
+            };
+            lines.push(format!(
+                "{} {} {} {} {} {} {} {}",
+                name,
+                tool.id.to_bits(),
+                team_name(tool.team),
+                tool.pos.x,
+                tool.pos.y,
+                tool.pos.z,
+                tool.angle,
+                values
+            ));
+        }
+        for bullet in &self.bullets {
+            lines.push(format!(
+                "bullet {} {} {} {} {} {} {} {}",
+                team_name(bullet.team),
+                bullet.pos.x,
+                bullet.pos.y,
+                bullet.angle,
+                bullet.elapsed,
+                bullet.shooter.to_bits(),
+                bullet.weapon.name,
+                bullet.lifetime
+            ));
+        }
+        for saved in &self.effects {
+            lines.push(format!(
+                "effect {} {} {} {}",
+                saved.target.to_bits(),
+                saved.effect.kind.name(),
+                saved.effect.strength,
+                saved.effect.remaining
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut state = SaveState::default();
+        for (index, line) in text.lines().enumerate() {
+            state
+                .parse_line(line)
+                .map_err(|err| format!("line {}: {}", index + 1, err))?;
+        }
+        Ok(state)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let mut words = Words(line.split_whitespace());
+        match words.0.next() {
+            None => {}
+            Some("level") => {
+                self.level = words.value()?;
+                self.playing = match words.0.next() {
+                    Some("planning") => false,
+                    Some("playing") => true,
+                    other => return Err(format!("invalid level state {:?}", other)),
+                };
+            }
+            Some("inventory") => {
+                for count in &mut self.inventory {
+                    *count = words.value()?;
+                }
+            }
+            Some("portal") => self.portals.push(SavedPortal {
+                id: words.entity()?,
+                team: words.team()?,
+                pos: words.vec2()?,
+                hp: words.value()?,
+                max_hp: words.value()?,
+                spawn_elapsed: words.value()?,
+            }),
+            Some("robot") => self.robots.push(SavedRobot {
+                id: words.entity()?,
+                team: words.team()?,
+                pos: words.vec2()?,
+                vel: words.vec2()?,
+                angle: words.value()?,
+                angular_vel: words.value()?,
+                hp: words.value()?,
+                max_hp: words.value()?,
+                engine_dir: words.vec2()?,
+                age: words.value()?,
+                target: match words.0.next() {
+                    Some("-") | None => None,
+                    Some(word) => Some(parse_entity(word)?),
+                },
+                last_redirect: match words.0.next() {
+                    Some("-") | None => None,
+                    Some(word) => Some((parse_tool_kind(word)?, words.entity()?)),
+                },
+                weapons: words.weapons()?,
+            }),
+            Some(name @ ("arrow" | "fork" | "grouper" | "defender")) => {
+                let id = words.entity()?;
+                let team = words.team()?;
+                let pos = Vec3::new(words.value()?, words.value()?, words.value()?);
+                let angle = words.value()?;
+                let kind = match name {
+                    "arrow" => SavedToolKind::Arrow(words.vec2()?),
+                    "fork" => SavedToolKind::Fork {
+                        dirs: [words.vec2()?, words.vec2()?],
+                        forked_count: words.value()?,
+                    },
+                    "grouper" => SavedToolKind::Grouper {
+                        dir: words.vec2()?,
+                        group: words.entities()?,
+                    },
+                    _ => SavedToolKind::Defender {
+                        dir: words.vec2()?,
+                        group: words.entities()?,
+                    },
+                };
+                self.tools.push(SavedTool {
+                    id,
+                    team,
+                    pos,
+                    angle,
+                    kind,
+                });
+            }
+            Some("bullet") => self.bullets.push(SavedBullet {
+                team: words.team()?,
+                pos: words.vec2()?,
+                angle: words.value()?,
+                elapsed: words.value()?,
+                shooter: words.entity()?,
+                weapon: words.weapon()?,
+                lifetime: words.value()?,
+            }),
+            Some("effect") => self.effects.push(SavedEffect {
+                target: words.entity()?,
+                effect: StatusEffect::new(words.status_kind()?, words.value()?, words.value()?),
+            }),
+            Some(other) => return Err(format!("unknown object '{}'", other)),
+        }
+        Ok(())
+    }
+}
+
+struct Words<'a>(std::str::SplitWhitespace<'a>);
+
+impl Words<'_> {
+    fn value<T: FromStr>(&mut self) -> Result<T, String> {
+        let word = self.0.next().ok_or_else(|| "missing value".to_string())?;
+        word.parse()
+            .map_err(|_| format!("invalid value '{}'", word))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, String> {
+        Ok(Vec2::new(self.value()?, self.value()?))
+    }
+
+    fn team(&mut self) -> Result<Team, String> {
+        match self.0.next() {
+            Some("player") => Ok(Team::Player),
+            Some("enemy") => Ok(Team::Enemy),
+            other => Err(format!("invalid team {:?}", other)),
+        }
+    }
+
+    fn entity(&mut self) -> Result<Entity, String> {
+        self.value().map(Entity::from_bits)
+    }
+
+    fn entities(&mut self) -> Result<Vec<Entity>, String> {
+        self.0.by_ref().map(parse_entity).collect()
+    }
+
+    fn weapon(&mut self) -> Result<&'static Weapon, String> {
+        let word = self.0.next().ok_or_else(|| "missing weapon".to_string())?;
+        Weapon::by_name(word).ok_or_else(|| format!("unknown weapon '{}'", word))
+    }
+
+    fn status_kind(&mut self) -> Result<StatusKind, String> {
+        let word = self.0.next().ok_or_else(|| "missing effect".to_string())?;
+        StatusKind::parse(word).ok_or_else(|| format!("unknown effect '{}'", word))
+    }
+
+    fn weapons(&mut self) -> Result<Vec<SavedWeapon>, String> {
+        let mut weapons = vec![];
+        while let Some(word) = self.0.next() {
+            weapons.push(SavedWeapon {
+                weapon: Weapon::by_name(word)
+                    .ok_or_else(|| format!("unknown weapon '{}'", word))?,
+                cooldown_duration: self.value()?,
+                cooldown_elapsed: self.value()?,
+                angle: self.value()?,
+                burst_left: self.value()?,
+                burst_elapsed: self.value()?,
+            });
+        }
+        Ok(weapons)
+    }
+}
+
+fn parse_entity(word: &str) -> Result<Entity, String> {
+    word.parse()
+        .map(Entity::from_bits)
+        .map_err(|_| format!("invalid entity '{}'", word))
+}
+
+fn parse_tool_kind(word: &str) -> Result<ToolKind, String> {
+    match word {
+        "arrow" => Ok(ToolKind::Arrow),
+        "fork" => Ok(ToolKind::Fork),
+        "grouper" => Ok(ToolKind::Grouper),
+        "defender" => Ok(ToolKind::Defender),
+        _ => Err(format!("invalid tool '{}'", word)),
+    }
+}
+
+fn tool_kind_name(kind: ToolKind) -> &'static str {
+    match kind {
+        ToolKind::Arrow => "arrow",
+        ToolKind::Fork => "fork",
+        ToolKind::Grouper => "grouper",
+        ToolKind::Defender => "defender",
+    }
+}
+
+fn team_name(team: Team) -> &'static str {
+    match team {
+        Team::Player => "player",
+        Team::Enemy => "enemy",
+    }
+}
+
+fn entity_list(entities: &[Entity]) -> String {
+    entities
+        .iter()
+        .map(|entity| format!(" {}", entity.to_bits()))
+        .collect()
+}
+
+fn quick_save_load(
+    actions: Actions,
+    mut ev_save_state: EventWriter<SaveStateEvent>,
+    mut ev_load_state: EventWriter<LoadStateEvent>,
+) {
+    if actions.just_pressed(Action::QuickSave) {
+        ev_save_state.send(SaveStateEvent(QUICKSAVE_FILE.to_string()));
+    }
+    if actions.just_pressed(Action::QuickLoad) {
+        ev_load_state.send(LoadStateEvent(QUICKSAVE_FILE.to_string()));
+    }
+}
+
+/// Reads the running level into a `SaveState`, also used to copy it into the ghost preview.
+#[derive(SystemParam)]
+pub struct LevelSnapshot<'w, 's> {
+    level_index: Res<'w, LevelIndex>,
+    inventory: Res<'w, Inventory>,
+    time: Res<'w, Time>,
+    q_portal: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Team,
+            &'static Life,
+            &'static Portal,
+        ),
+    >,
+    q_robot: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Team,
+            &'static Life,
+            &'static EngineDir,
+            &'static Velocity,
+            &'static SpawnTime,
+            &'static Weapons,
+            Option<&'static RobotTarget>,
+            &'static LastRedirect,
+        ),
+        With<Robot>,
+    >,
+    q_tool: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Team,
+            AnyOf<(
+                &'static Arrow,
+                &'static Fork,
+                &'static Grouper,
+                &'static Defender,
+            )>,
+        ),
+    >,
+    q_bullet: Query<'w, 's, (&'static Transform, &'static Team, &'static Bullet), Without<Pooled>>,
+    q_effects: Query<'w, 's, (Entity, &'static StatusEffects)>,
+}
+
+impl LevelSnapshot<'_, '_> {
+    pub fn capture(&self, playing: bool) -> SaveState {
+        SaveState {
+            level: self.level_index.0,
+            playing,
+            inventory: [
+                self.inventory.arrow_count,
+                self.inventory.fork_count,
+                self.inventory.grouper_count,
+                self.inventory.defender_count,
+            ],
+            portals: self
+                .q_portal
+                .iter()
+                .map(|(entity, tr, team, life, portal)| SavedPortal {
+                    id: entity,
+                    team: *team,
+                    pos: tr.translation.truncate(),
+                    hp: life.curr_hp,
+                    max_hp: life.max_hp,
+                    spawn_elapsed: portal.spawn_timer.elapsed_secs(),
+                })
+                .collect(),
+            robots: self
+                .q_robot
+                .iter()
+                .map(
+                    |(
+                        entity,
+                        tr,
+                        team,
+                        life,
+                        engine_dir,
+                        velocity,
+                        spawn_time,
+                        weapons,
+                        target,
+                        last_redirect,
+                    )| {
+                        SavedRobot {
+                            id: entity,
+                            team: *team,
+                            pos: tr.translation.truncate(),
+                            vel: velocity.linvel,
+                            angle: tr.rotation.to_euler(EulerRot::ZYX).0,
+                            angular_vel: velocity.angvel,
+                            hp: life.curr_hp,
+                            max_hp: life.max_hp,
+                            engine_dir: engine_dir.0,
+                            age: (self.time.elapsed() - spawn_time.0).as_secs_f32(),
+                            target: target.map(|target| target.0),
+                            last_redirect: last_redirect.0,
+                            weapons: weapons
+                                .0
+                                .iter()
+                                .map(|mount| SavedWeapon {
+                                    weapon: mount.weapon,
+                                    cooldown_duration: mount.cooldown.duration().as_secs_f32(),
+                                    cooldown_elapsed: mount.cooldown.elapsed_secs(),
+                                    angle: mount.angle,
+                                    burst_left: mount.burst_left,
+                                    burst_elapsed: mount.burst_timer.elapsed_secs(),
+                                })
+                                .collect(),
+                        }
+                    },
+                )
+                .collect(),
+            tools: self
+                .q_tool
+                .iter()
+                .map(|(entity, tr, team, (arrow, fork, grouper, defender))| {
+                    let kind = match (arrow, fork, grouper, defender) {
+                        (Some(arrow), ..) => SavedToolKind::Arrow(arrow.dir),
+                        (_, Some(fork), ..) => SavedToolKind::Fork {
+                            dirs: fork.dirs,
+                            forked_count: fork.forked_count,
+                        },
+                        (_, _, Some(grouper), _) => SavedToolKind::Grouper {
+                            dir: grouper.dir,
+                            group: grouper.group.iter().copied().collect(),
+                        },
+                        (.., Some(defender)) => SavedToolKind::Defender {
+                            dir: defender.dir,
+                            group: defender.group.iter().copied().collect(),
+                        },
+                        _ => unreachable!(),
+                    };
+                    SavedTool {
+                        id: entity,
+                        team: *team,
+                        pos: tr.translation,
+                        angle: tr.rotation.to_euler(EulerRot::ZYX).0,
+                        kind,
+                    }
+                })
+                .collect(),
+            bullets: self
+                .q_bullet
+                .iter()
+                .map(|(tr, team, bullet)| SavedBullet {
+                    team: *team,
+                    pos: tr.translation.truncate(),
+                    angle: bullet.angle,
+                    elapsed: bullet.timer.elapsed_secs(),
+                    shooter: bullet.shooter,
+                    weapon: bullet.weapon,
+                    lifetime: bullet.timer.duration().as_secs_f32(),
+                })
+                .collect(),
+            effects: self
+                .q_effects
+                .iter()
+                .flat_map(|(entity, effects)| {
+                    effects.0.iter().map(move |effect| SavedEffect {
+                        target: entity,
+                        effect: *effect,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+fn save_state(
+    mut ev_save_state: EventReader<SaveStateEvent>,
+    level_state: Res<State<LevelState>>,
+    snapshot: LevelSnapshot,
+) {
+    for SaveStateEvent(file) in ev_save_state.read() {
+        let playing = match level_state.get() {
+            LevelState::Planning => false,
+            LevelState::Playing => true,
+            _ => {
+                warn!("can only save while planning or playing");
+                continue;
+            }
+        };
+        let state = snapshot.capture(playing);
+        match storage::write(file, &state.to_text()) {
+            Ok(()) => info!(
+                "saved {} robots and {} tools to {}",
+                state.robots.len(),
+                state.tools.len(),
+                file
+            ),
+            Err(err) => warn!("could not save to {}: {}", file, err),
+        }
+    }
+}
+
+fn load_state(
+    mut ev_load_state: EventReader<LoadStateEvent>,
+    mut pending: ResMut<PendingLoad>,
+    level_index: Res<LevelIndex>,
+    mut ev_change_level: EventWriter<ChangeLevelEvent>,
+    mut ev_reset_level: EventWriter<ResetLevelEvent>,
+    mut intro: ResMut<CameraIntro>,
+) {
+    for LoadStateEvent(file) in ev_load_state.read() {
+        match storage::read(file)
+            .ok_or_else(|| "no such save".to_string())
+            .and_then(|text| SaveState::parse(&text))
+        {
+            Ok(state) => {
+                // always start from a fresh level so destroyed portals come back
+                if state.level == level_index.0 {
+                    ev_reset_level.send(ResetLevelEvent);
+                } else {
+                    ev_change_level.send(ChangeLevelEvent::Index(state.level));
+                }
+                // the save continues the level, the fly-over would only delay it
+                intro.mark_seen(state.level);
+                pending.state = Some(state);
+                pending.level_loaded = false;
+            }
+            Err(err) => warn!("could not load {}: {}", file, err),
+        }
+    }
+}
+
+fn wait_level_loaded(
+    mut pending: ResMut<PendingLoad>,
+    mut ev_level_loaded: EventReader<LevelLoadedEvent>,
+) {
+    if ev_level_loaded.read().count() > 0 && pending.state.is_some() {
+        pending.level_loaded = true;
+    }
+}
+
+// the level goes through the intro first, which also gives the enemy tools spawned on load
+// a frame to be set up
+fn apply_pending_load(mut pending: ResMut<PendingLoad>, mut restore: LevelRestore) {
+    if !pending.level_loaded {
+        return;
+    }
+    pending.level_loaded = false;
+    let Some(state) = pending.state.take() else {
+        return;
+    };
+    if restore.restore(&state) {
+        info!(
+            "restored {} robots and {} tools",
+            state.robots.len(),
+            state.tools.len()
+        );
+    }
+}
+
+/// Puts a `SaveState` back into a freshly loaded level, also used to fill the ghost preview.
+#[derive(SystemParam)]
+pub struct LevelRestore<'w, 's> {
+    cmd: Commands<'w, 's>,
+    assets: Res<'w, TextureAssets>,
+    time: Res<'w, Time>,
+    level_size: Res<'w, LevelSize>,
+    q_level: Query<'w, 's, Entity, With<LevelIid>>,
+    q_portal: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Team,
+            &'static mut Life,
+            &'static mut Portal,
+        ),
+    >,
+    q_clear: Query<
+        'w,
+        's,
+        Entity,
+        Or<(
+            With<Robot>,
+            With<Bullet>,
+            With<Arrow>,
+            With<Fork>,
+            With<Grouper>,
+            With<Defender>,
+        )>,
+    >,
+    inventory: ResMut<'w, Inventory>,
+    next_level_state: ResMut<'w, NextState<LevelState>>,
+    defender_shield: Res<'w, DefenderShield>,
+    restored: ResMut<'w, RestoredRobots>,
+}
+
+impl LevelRestore<'_, '_> {
+    /// Returns false when there is no level to restore into.
+    pub fn restore(&mut self, state: &SaveState) -> bool {
+        let (Ok(e_level), Some(size_info)) = (self.q_level.get_single(), self.level_size.0) else {
+            return false;
+        };
+
+        for entity in &self.q_clear {
+            self.cmd.entity(entity).despawn_recursive();
+        }
+        let mut ids = HashMap::new();
+
+        let mut portals: Vec<_> = self.q_portal.iter_mut().collect();
+        for saved in &state.portals {
+            let Some(index) = portals.iter().position(|(_, tr, team, _, _)| {
+                **team == saved.team
+                    && tr.translation.truncate().distance(saved.pos) < PORTAL_MATCH_DISTANCE
+            }) else {
+                warn!("no {:?} portal at {} in this level", saved.team, saved.pos);
+                continue;
+            };
+            let (entity, _, _, mut life, mut portal) = portals.swap_remove(index);
+            life.curr_hp = saved.hp;
+            life.max_hp = saved.max_hp;
+            portal
+                .spawn_timer
+                .set_elapsed(Duration::from_secs_f32(saved.spawn_elapsed));
+            ids.insert(saved.id, entity);
+        }
+        // the others were destroyed before saving
+        for (entity, ..) in portals {
+            self.cmd.entity(entity).despawn_recursive();
+        }
+
+        for saved in &state.robots {
+            let weapons = saved
+                .weapons
+                .iter()
+                .map(|saved| {
+                    let mut mount = WeaponMount::ready(saved.weapon);
+                    mount
+                        .cooldown
+                        .set_duration(Duration::from_secs_f32(saved.cooldown_duration));
+                    mount
+                        .cooldown
+                        .set_elapsed(Duration::from_secs_f32(saved.cooldown_elapsed));
+                    mount.angle = saved.angle;
+                    mount.burst_left = saved.burst_left;
+                    mount
+                        .burst_timer
+                        .set_elapsed(Duration::from_secs_f32(saved.burst_elapsed));
+                    mount
+                })
+                .collect();
+            let robot = spawn_robot(
+                &mut self.cmd,
+                &self.assets,
+                saved.pos,
+                saved.team,
+                self.time
+                    .elapsed()
+                    .saturating_sub(Duration::from_secs_f32(saved.age)),
+                Weapons(weapons),
+                saved.angle,
+            );
+            self.cmd.entity(robot).insert((
+                Velocity {
+                    linvel: saved.vel,
+                    angvel: saved.angular_vel,
+                },
+                Life {
+                    max_hp: saved.max_hp,
+                    curr_hp: saved.hp,
+                },
+                EngineDir(saved.engine_dir),
+            ));
+            self.cmd.entity(e_level).add_child(robot);
+            ids.insert(saved.id, robot);
+            self.restored.0.insert(robot);
+        }
+        for saved in &state.robots {
+            let target = saved.target.and_then(|target| ids.get(&target));
+            if let (Some(robot), Some(target)) = (ids.get(&saved.id), target) {
+                self.cmd.entity(*robot).insert(RobotTarget(*target));
+            }
+        }
+
+        for saved in &state.tools {
+            let tr = Transform::from_translation(saved.pos)
+                .with_rotation(Quat::from_rotation_z(saved.angle));
+            let grid_coords =
+                translation_to_grid_coords(saved.pos.truncate(), size_info.tile_size_vec());
+            let player = saved.team == Team::Player;
+            let remap = |group: &[Entity]| {
+                group
+                    .iter()
+                    .filter_map(|entity| ids.get(entity).copied())
+                    .collect::<Vec<_>>()
+            };
+            let tool = match &saved.kind {
+                SavedToolKind::Arrow(dir) => {
+                    let texture = player.then(|| self.assets.arrow.clone());
+                    spawn_arrow(&mut self.cmd, tr, *dir, saved.team, texture, grid_coords)
+                }
+                SavedToolKind::Fork { dirs, forked_count } => {
+                    // spawn_fork turns its direction 45° both ways
+                    let dir = Vec2::from_angle(-PI / 4.).rotate(dirs[0]);
+                    let texture = player.then(|| self.assets.fork.clone());
+                    let fork = spawn_fork(&mut self.cmd, tr, dir, saved.team, texture, grid_coords);
+                    self.cmd.entity(fork).insert(Fork {
+                        dirs: *dirs,
+                        forked_count: *forked_count,
+                    });
+                    fork
+                }
+                SavedToolKind::Grouper { dir, group } => {
+                    let texture = player.then(|| self.assets.grouper.clone());
+                    let grouper =
+                        spawn_grouper(&mut self.cmd, tr, *dir, saved.team, texture, grid_coords);
+                    self.cmd.entity(grouper).insert(Grouper {
+                        dir: *dir,
+                        group: remap(group).into_iter().collect(),
+                    });
+                    grouper
+                }
+                SavedToolKind::Defender { dir, group } => {
+                    let texture = player.then(|| self.assets.defender.clone());
+                    let defender = spawn_defender(
+                        &mut self.cmd,
+                        tr,
+                        *dir,
+                        saved.team,
+                        texture,
+                        grid_coords,
+                        self.defender_shield.0,
+                    );
+                    self.cmd.entity(defender).insert(Defender {
+                        dir: *dir,
+                        group: remap(group).into_iter().collect(),
+                    });
+                    defender
+                }
+            };
+            self.cmd.entity(e_level).add_child(tool);
+            ids.insert(saved.id, tool);
+        }
+        // tools are restored after the robots they redirected
+        for saved in &state.robots {
+            let last_redirect = saved
+                .last_redirect
+                .and_then(|(kind, tool)| Some((kind, *ids.get(&tool)?)));
+            if let (Some(robot), Some(last_redirect)) = (ids.get(&saved.id), last_redirect) {
+                self.cmd
+                    .entity(*robot)
+                    .insert(LastRedirect(Some(last_redirect)));
+            }
+        }
+
+        for saved in &state.bullets {
+            let shooter = ids
+                .get(&saved.shooter)
+                .copied()
+                .unwrap_or(Entity::PLACEHOLDER);
+            let mut bullet = Bullet::new(
+                saved.angle,
+                shooter,
+                saved.weapon,
+                Duration::from_secs_f32(saved.lifetime),
+            );
+            bullet
+                .timer
+                .set_elapsed(Duration::from_secs_f32(saved.elapsed));
+            let e_bullet = self
+                .cmd
+                .spawn(bullet_bundle(&self.assets, bullet, saved.pos, saved.team))
+                .id();
+            self.cmd.entity(e_level).add_child(e_bullet);
+        }
+
+        let mut effects: HashMap<Entity, Vec<StatusEffect>> = HashMap::new();
+        for saved in &state.effects {
+            if let Some(target) = ids.get(&saved.target) {
+                effects.entry(*target).or_default().push(saved.effect);
+            }
+        }
+        for (target, effects) in effects {
+            self.cmd.entity(target).insert(StatusEffects(effects));
+        }
+
+        self.inventory.arrow_count = state.inventory[0];
+        self.inventory.fork_count = state.inventory[1];
+        self.inventory.grouper_count = state.inventory[2];
+        self.inventory.defender_count = state.inventory[3];
+        if state.playing {
+            self.next_level_state.set(LevelState::Playing);
+        }
+
+        true
+    }
+}
+
+/// Rapier reports restored robots as entering the sensors they already sit in. Tools
+/// already redirected them, portals still have to push them out, and every other contact
+/// is kept.
+pub fn skip_restored_contacts(
+    mut restored: ResMut<RestoredRobots>,
+    mut collision_events: ResMut<Events<CollisionEvent>>,
+    q_tool_sensor: Query<(), (With<Sensor>, Without<PortalSensor>)>,
+) {
+    if restored.0.is_empty() {
+        return;
+    }
+    let events: Vec<_> = collision_events.drain().collect();
+    for ev in events {
+        let skip = match ev {
+            CollisionEvent::Started(e1, e2, _) => {
+                (restored.0.contains(&e1) && q_tool_sensor.contains(e2))
+                    || (restored.0.contains(&e2) && q_tool_sensor.contains(e1))
+            }
+            CollisionEvent::Stopped(..) => false,
+        };
+        if !skip {
+            collision_events.send(ev);
+        }
+    }
+    restored.0.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weapon::{CANON, GATLING};
+
+    fn sample_state() -> SaveState {
+        let portal = Entity::from_raw(1);
+        let robot = Entity::from_raw(2);
+        let target = Entity::from_raw(3);
+        let grouper = Entity::from_raw(4);
+        SaveState {
+            level: 3,
+            playing: true,
+            inventory: [1, 2, 0, 4],
+            portals: vec![SavedPortal {
+                id: portal,
+                team: Team::Enemy,
+                pos: Vec2::new(48., 16.),
+                hp: 12.5,
+                max_hp: 50.,
+                spawn_elapsed: 0.25,
+            }],
+            robots: vec![SavedRobot {
+                id: robot,
+                team: Team::Player,
+                pos: Vec2::new(10.5, -3.),
+                vel: Vec2::new(1., 2.),
+                angle: 0.5,
+                angular_vel: -1.5,
+                hp: 4.,
+                max_hp: 5.,
+                engine_dir: Vec2::new(0., 1.),
+                age: 7.75,
+                target: Some(target),
+                last_redirect: Some((ToolKind::Grouper, grouper)),
+                weapons: vec![
+                    SavedWeapon {
+                        weapon: &CANON,
+                        cooldown_duration: 1.25,
+                        cooldown_elapsed: 0.5,
+                        angle: 2.,
+                        burst_left: 0,
+                        burst_elapsed: 0.,
+                    },
+                    SavedWeapon {
+                        weapon: &GATLING,
+                        cooldown_duration: 1.5,
+                        cooldown_elapsed: 1.,
+                        angle: -0.25,
+                        burst_left: 2,
+                        burst_elapsed: 0.0625,
+                    },
+                ],
+            }],
+            tools: vec![
+                SavedTool {
+                    id: grouper,
+                    team: Team::Player,
+                    pos: Vec3::new(16., 32., 1.),
+                    angle: 1.5,
+                    kind: SavedToolKind::Grouper {
+                        dir: Vec2::new(-1., 0.),
+                        group: vec![robot],
+                    },
+                },
+                SavedTool {
+                    id: Entity::from_raw(5),
+                    team: Team::Enemy,
+                    pos: Vec3::new(0., 8., 0.),
+                    angle: 0.,
+                    kind: SavedToolKind::Fork {
+                        dirs: [Vec2::new(1., 0.), Vec2::new(0., -1.)],
+                        forked_count: 3,
+                    },
+                },
+            ],
+            bullets: vec![SavedBullet {
+                team: Team::Player,
+                pos: Vec2::new(20., 4.),
+                angle: 0.75,
+                elapsed: 0.125,
+                shooter: robot,
+                weapon: &GATLING,
+                lifetime: 0.5,
+            }],
+            effects: vec![
+                SavedEffect {
+                    target: robot,
+                    effect: StatusEffect::new(StatusKind::Burn, 0.3, 1.5),
+                },
+                SavedEffect {
+                    target: portal,
+                    effect: StatusEffect::new(StatusKind::Shield, 2., 8.25),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let text = sample_state().to_text();
+        let state = SaveState::parse(&text).unwrap();
+        assert_eq!(state.to_text(), text);
+
+        assert_eq!(state.level, 3);
+        assert!(state.playing);
+        assert_eq!(state.inventory, [1, 2, 0, 4]);
+        let robot = &state.robots[0];
+        assert_eq!(robot.angle, 0.5);
+        assert_eq!(robot.angular_vel, -1.5);
+        assert_eq!(robot.target, Some(Entity::from_raw(3)));
+        assert_eq!(
+            robot.last_redirect,
+            Some((ToolKind::Grouper, Entity::from_raw(4)))
+        );
+        assert_eq!(robot.weapons[1].weapon.name, "gatling");
+        assert_eq!(robot.weapons[1].angle, -0.25);
+        assert_eq!(robot.weapons[1].burst_left, 2);
+        assert_eq!(robot.weapons[1].burst_elapsed, 0.0625);
+        assert_eq!(state.tools[0].id, Entity::from_raw(4));
+        assert!(matches!(
+            &state.tools[0].kind,
+            SavedToolKind::Grouper { group, .. } if group == &[Entity::from_raw(2)]
+        ));
+        assert_eq!(state.bullets[0].shooter, Entity::from_raw(2));
+        assert_eq!(state.effects[1].target, Entity::from_raw(1));
+        assert_eq!(
+            state.effects[1].effect,
+            StatusEffect::new(StatusKind::Shield, 2., 8.25)
+        );
+    }
+
+    #[test]
+    fn parse_reports_the_bad_line() {
+        let err = SaveState::parse("level 1 playing\nrobot 2 player 1 2\n")
+            .err()
+            .unwrap();
+        assert!(err.starts_with("line 2:"), "{}", err);
+        assert!(SaveState::parse("level 1 paused\n").is_err());
+        assert!(SaveState::parse("robot 2 neutral\n").is_err());
+        assert!(SaveState::parse("wall 1 2\n").is_err());
+        assert!(SaveState::parse("effect 2 frozen 1 1\n").is_err());
+    }
+}
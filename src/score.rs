@@ -0,0 +1,238 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    arrow::Arrow,
+    console::ConsoleCommand,
+    defender::Defender,
+    fork::Fork,
+    game::{GameState, LevelState},
+    gameplay_events::RobotKilled,
+    grouper::Grouper,
+    hp::Life,
+    inventory::Inventory,
+    levels::{LevelIndex, LevelLoadedEvent},
+    physics::Team,
+    portal::Portal,
+    storage,
+};
+
+const BEST_SCORES_FILE: &str = "best_scores.txt";
+
+const TIME_BONUS_MAX: f32 = 1000.;
+const TIME_BONUS_PER_SECOND: f32 = 5.;
+const UNUSED_TOOL_BONUS: u32 = 250;
+const ROBOTS_LOST_BONUS_MAX: u32 = 1000;
+const ROBOT_LOST_PENALTY: u32 = 10;
+const PORTAL_HP_BONUS_MAX: f32 = 1000.;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LevelStats::default())
+            .insert_resource(LevelScore::default())
+            .insert_resource(StarThresholds::default())
+            .insert_resource(load_best_scores())
+            .add_systems(
+                Update,
+                ((reset_stats, mark_unranked).chain(), tick_level_time)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PostUpdate,
+                count_robots_lost.run_if(in_state(LevelState::Playing)),
+            )
+            .add_systems(OnEnter(LevelState::Win), compute_score);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LevelStats {
+    pub time: f32,
+    pub tools_given: u32,
+    pub robots_lost: u32,
+    /// Console cheats were used, the best score is left alone.
+    pub unranked: bool,
+}
+
+#[derive(Resource, Default, Clone, Copy)]
+pub struct LevelScore {
+    pub time: f32,
+    pub time_bonus: u32,
+    pub tools_given: u32,
+    pub tools_used: u32,
+    pub tools_bonus: u32,
+    pub robots_lost: u32,
+    pub robots_bonus: u32,
+    pub portal_hp: f32,
+    pub portal_bonus: u32,
+    pub total: u32,
+    pub stars: u32,
+    pub best: Option<BestScore>,
+    pub unranked: bool,
+}
+
+#[derive(Resource)]
+pub struct StarThresholds {
+    pub two_stars: u32,
+    pub three_stars: u32,
+}
+
+impl Default for StarThresholds {
+    fn default() -> Self {
+        Self {
+            two_stars: u32::MAX,
+            three_stars: u32::MAX,
+        }
+    }
+}
+
+impl StarThresholds {
+    pub fn stars(&self, score: u32) -> u32 {
+        if score >= self.three_stars {
+            3
+        } else if score >= self.two_stars {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct BestScore {
+    pub score: u32,
+    pub stars: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct BestScores(pub HashMap<usize, BestScore>);
+
+fn tool_count(inventory: &Inventory) -> u32 {
+    inventory.arrow_count
+        + inventory.fork_count
+        + inventory.grouper_count
+        + inventory.defender_count
+}
+
+fn reset_stats(
+    mut ev_level_loaded: EventReader<LevelLoadedEvent>,
+    mut stats: ResMut<LevelStats>,
+    inventory: Res<Inventory>,
+) {
+    for _ in ev_level_loaded.read() {
+        *stats = LevelStats {
+            tools_given: tool_count(&inventory),
+            ..Default::default()
+        };
+    }
+}
+
+fn mark_unranked(mut stats: ResMut<LevelStats>, mut ev_command: EventReader<ConsoleCommand>) {
+    if ev_command.read().any(ConsoleCommand::is_cheat) {
+        stats.unranked = true;
+    }
+}
+
+fn tick_level_time(
+    mut stats: ResMut<LevelStats>,
+    time: Res<Time>,
+    level_state: Res<State<LevelState>>,
+) {
+    if *level_state == LevelState::Playing {
+        stats.time += time.delta_seconds();
+    }
+}
+
+fn count_robots_lost(mut stats: ResMut<LevelStats>, mut ev_robot_killed: EventReader<RobotKilled>) {
+    for ev in ev_robot_killed.read() {
+        if ev.team == Team::Player {
+            stats.robots_lost += 1;
+        }
+    }
+}
+
+pub fn compute_score(
+    stats: Res<LevelStats>,
+    inventory: Res<Inventory>,
+    thresholds: Res<StarThresholds>,
+    level_index: Res<LevelIndex>,
+    q_portal: Query<(&Life, &Team), With<Portal>>,
+    q_tool: Query<&Team, Or<(With<Arrow>, With<Fork>, With<Grouper>, With<Defender>)>>,
+    mut score: ResMut<LevelScore>,
+    mut best_scores: ResMut<BestScores>,
+) {
+    let tools_placed = q_tool.iter().filter(|team| **team == Team::Player).count() as u32;
+    let tools_given = stats.tools_given.max(tools_placed + tool_count(&inventory));
+    let (curr_hp, max_hp) = q_portal
+        .iter()
+        .filter(|(_, team)| **team == Team::Player)
+        .fold((0., 0.), |(curr, max), (life, _)| {
+            (curr + life.curr_hp.max(0.), max + life.max_hp)
+        });
+    let portal_hp = if max_hp > 0. { curr_hp / max_hp } else { 0. };
+
+    let time_bonus = (TIME_BONUS_MAX - stats.time * TIME_BONUS_PER_SECOND).max(0.) as u32;
+    let tools_bonus = (tools_given - tools_placed) * UNUSED_TOOL_BONUS;
+    let robots_bonus = ROBOTS_LOST_BONUS_MAX.saturating_sub(stats.robots_lost * ROBOT_LOST_PENALTY);
+    let portal_bonus = (portal_hp * PORTAL_HP_BONUS_MAX) as u32;
+    let total = time_bonus + tools_bonus + robots_bonus + portal_bonus;
+    let stars = thresholds.stars(total);
+
+    let previous_best = best_scores.0.get(&level_index.0).copied();
+    if !stats.unranked && previous_best.is_none_or(|best| total > best.score) {
+        best_scores.0.insert(
+            level_index.0,
+            BestScore {
+                score: total,
+                stars,
+            },
+        );
+        save_best_scores(&best_scores);
+    }
+    info!(
+        "score on level {}: {} ({} stars)",
+        level_index.0, total, stars
+    );
+
+    *score = LevelScore {
+        time: stats.time,
+        time_bonus,
+        tools_given,
+        tools_used: tools_placed,
+        tools_bonus,
+        robots_lost: stats.robots_lost,
+        robots_bonus,
+        portal_hp,
+        portal_bonus,
+        total,
+        stars,
+        best: previous_best,
+        unranked: stats.unranked,
+    };
+}
+
+fn load_best_scores() -> BestScores {
+    let mut result = BestScores::default();
+    if let Some(content) = storage::read(BEST_SCORES_FILE) {
+        for line in content.lines() {
+            let values: Vec<_> = line.split_whitespace().map(str::parse::<u32>).collect();
+            if let [Ok(index), Ok(score), Ok(stars)] = values[..] {
+                result.0.insert(index as usize, BestScore { score, stars });
+            }
+        }
+    }
+    result
+}
+
+fn save_best_scores(best_scores: &BestScores) {
+    let mut levels: Vec<_> = best_scores.0.iter().collect();
+    levels.sort_by_key(|(index, _)| **index);
+    let content: String = levels
+        .into_iter()
+        .map(|(index, best)| format!("{} {} {}\n", index, best.score, best.stars))
+        .collect();
+    if let Err(err) = storage::write(BEST_SCORES_FILE, &content) {
+        warn!("could not save best scores: {}", err);
+    }
+}
@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Action, Actions},
+    game::GameState,
+    game_camera::{CameraTargetPos, MouseWorldCoords},
+    grouper::Grouper,
+    hp::Life,
+    mouse::{ClickSensorEvent, MouseState},
+    physics::Team,
+    portal::Portal,
+    robot::{EngineDir, LastRedirect, Robot, RobotTarget, SpawnTime},
+    weapon::Weapons,
+};
+
+const ROBOT_SELECT_RADIUS: f32 = 10.;
+const PORTAL_SELECT_RADIUS: f32 = 24.;
+const ROBOT_HIGHLIGHT_RADIUS: f32 = 10.;
+const PORTAL_HIGHLIGHT_RADIUS: f32 = 36.;
+const HIGHLIGHT_COLOR: Color = Color::WHITE;
+const PANEL_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.7);
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Selection::default())
+            .add_systems(
+                Update,
+                (
+                    (select_on_click, clear_selection)
+                        .chain()
+                        .after(crate::mouse::check_for_click_sensor),
+                    follow_selection.after(crate::controls::pan_camera),
+                    draw_selection,
+                    update_panel,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_panel);
+    }
+}
+
+/// Robot or portal shown in the inspection panel.
+#[derive(Resource, Default)]
+pub struct Selection {
+    pub entity: Option<Entity>,
+    pub follow: bool,
+}
+
+#[derive(Component)]
+struct SelectionPanel;
+
+fn select_on_click(
+    mut selection: ResMut<Selection>,
+    mut ev_click_sensor: EventReader<ClickSensorEvent>,
+    mouse_state: Res<MouseState>,
+    mouse_pos: Res<MouseWorldCoords>,
+    button: Res<Input<MouseButton>>,
+    actions: Actions,
+    q_selectable: Query<(Entity, &GlobalTransform, Has<Portal>), Or<(With<Robot>, With<Portal>)>>,
+) {
+    // a click on a tool picks the tool up instead
+    if ev_click_sensor.read().count() > 0 || *mouse_state != MouseState::Default {
+        return;
+    }
+    if !(button.just_released(MouseButton::Left) || actions.just_pressed(Action::Confirm)) {
+        return;
+    }
+    let Some(mouse_pos) = mouse_pos.0 else {
+        return;
+    };
+    let closest = q_selectable
+        .iter()
+        .filter_map(|(entity, gtr, is_portal)| {
+            let radius = if is_portal {
+                PORTAL_SELECT_RADIUS
+            } else {
+                ROBOT_SELECT_RADIUS
+            };
+            let distance = gtr.translation().truncate().distance(mouse_pos);
+            (distance < radius).then_some((entity, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((entity, _)) = closest {
+        selection.entity = Some(entity);
+    }
+}
+
+fn clear_selection(
+    mut selection: ResMut<Selection>,
+    actions: Actions,
+    mouse_state: Res<MouseState>,
+    q_selectable: Query<(), Or<(With<Robot>, With<Portal>)>>,
+) {
+    if let Some(entity) = selection.entity {
+        let cancelled = *mouse_state == MouseState::Default && actions.just_pressed(Action::Cancel);
+        if cancelled || !q_selectable.contains(entity) {
+            selection.entity = None;
+        }
+    }
+    if actions.just_pressed(Action::FollowSelection) {
+        selection.follow = !selection.follow;
+    }
+}
+
+/// Keeps the camera on the selection. A robot waiting in a grouper is followed with the
+/// rest of its group.
+pub fn follow_selection(
+    selection: Res<Selection>,
+    mut camera_target_pos: ResMut<CameraTargetPos>,
+    q_gtr: Query<&GlobalTransform>,
+    q_grouper: Query<&Grouper>,
+) {
+    if !selection.follow {
+        return;
+    }
+    let Some(entity) = selection.entity else {
+        return;
+    };
+    let positions: Vec<Vec2> = match q_grouper.iter().find(|g| g.group.contains(&entity)) {
+        Some(grouper) => grouper
+            .group
+            .iter()
+            .filter_map(|robot| q_gtr.get(*robot).ok())
+            .map(|gtr| gtr.translation().truncate())
+            .collect(),
+        None => q_gtr
+            .get(entity)
+            .map(|gtr| gtr.translation().truncate())
+            .into_iter()
+            .collect(),
+    };
+    if !positions.is_empty() {
+        let pos = positions.iter().sum::<Vec2>() / positions.len() as f32;
+        camera_target_pos.0.x = pos.x;
+        camera_target_pos.0.y = pos.y;
+    }
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    q_selectable: Query<(&GlobalTransform, Has<Portal>)>,
+) {
+    if let Some(Ok((gtr, is_portal))) = selection.entity.map(|entity| q_selectable.get(entity)) {
+        let radius = if is_portal {
+            PORTAL_HIGHLIGHT_RADIUS
+        } else {
+            ROBOT_HIGHLIGHT_RADIUS
+        };
+        gizmos
+            .circle_2d(gtr.translation().truncate(), radius, HIGHLIGHT_COLOR)
+            .segments(32);
+    }
+}
+
+fn update_panel(
+    mut cmd: Commands,
+    selection: Res<Selection>,
+    time: Res<Time>,
+    q_robot: Query<(
+        &Team,
+        &Life,
+        &EngineDir,
+        Option<&RobotTarget>,
+        &LastRedirect,
+        &SpawnTime,
+        &Weapons,
+    )>,
+    q_portal: Query<(&Team, &Life, &Portal)>,
+    q_panel: Query<Entity, With<SelectionPanel>>,
+    mut q_text: Query<&mut Text>,
+    q_children: Query<&Children>,
+) {
+    let follow = format!(
+        "follow [F]: {}",
+        if selection.follow { "on" } else { "off" }
+    );
+    let lines = match selection.entity {
+        Some(entity) => {
+            if let Ok((team, life, engine_dir, target, last_redirect, spawn_time, weapons)) =
+                q_robot.get(entity)
+            {
+                let mut lines = vec![
+                    format!("Robot {:?} ({:?})", entity, team),
+                    format!("hp: {:.1} / {:.1}", life.curr_hp, life.max_hp),
+                    format!("engine dir: ({:.2}, {:.2})", engine_dir.0.x, engine_dir.0.y),
+                    format!(
+                        "target: {}",
+                        target.map_or("none".to_string(), |target| format!("{:?}", target.0))
+                    ),
+                    format!(
+                        "redirected by: {}",
+                        last_redirect.0.map_or("none".to_string(), |(kind, tool)| {
+                            format!("{:?} {:?}", kind, tool)
+                        })
+                    ),
+                    format!("age: {:.1}s", (time.elapsed() - spawn_time.0).as_secs_f32()),
+                ];
+                for mount in &weapons.0 {
+                    lines.push(format!(
+                        "{} cooldown: {:.2}s",
+                        mount.weapon.name,
+                        mount.cooldown.remaining_secs()
+                    ));
+                }
+                lines.push(follow);
+                Some(lines)
+            } else if let Ok((team, life, portal)) = q_portal.get(entity) {
+                Some(vec![
+                    format!("Portal {:?} ({:?})", entity, team),
+                    format!("hp: {:.1} / {:.1}", life.curr_hp, life.max_hp),
+                    format!("next spawn: {:.2}s", portal.spawn_timer.remaining_secs()),
+                    follow,
+                ])
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+    match (lines, q_panel.get_single()) {
+        (Some(lines), Ok(e_panel)) => {
+            for child in q_children.iter_descendants(e_panel) {
+                if let Ok(mut text) = q_text.get_mut(child) {
+                    text.sections[0].value = lines.join("\n");
+                }
+            }
+        }
+        (Some(lines), Err(_)) => {
+            cmd.spawn((
+                SelectionPanel,
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        bottom: Val::Vh(2.),
+                        right: Val::Vh(2.),
+                        padding: UiRect::all(Val::Px(8.)),
+                        ..Default::default()
+                    },
+                    background_color: PANEL_BACKGROUND.into(),
+                    ..Default::default()
+                },
+            ))
+            .with_children(|cmd| {
+                cmd.spawn(TextBundle::from_section(
+                    lines.join("\n"),
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                ));
+            });
+        }
+        (None, Ok(e_panel)) => cmd.entity(e_panel).despawn_recursive(),
+        (None, Err(_)) => {}
+    }
+}
+
+fn despawn_panel(
+    mut cmd: Commands,
+    mut selection: ResMut<Selection>,
+    q_panel: Query<Entity, With<SelectionPanel>>,
+) {
+    selection.entity = None;
+    for entity in &q_panel {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
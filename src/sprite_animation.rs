@@ -0,0 +1,270 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::LevelIid;
+use bevy_rapier2d::prelude::Velocity;
+
+use crate::{
+    game::GameState,
+    gameplay_events::{PortalDamaged, RobotKilled, RobotSpawned},
+    load::TextureAssets,
+    physics::Team,
+    portal::Portal,
+    robot::{LastRedirect, Robot},
+    weapon::FireEvent,
+};
+
+// below this speed robots play their idle clip
+const ROBOT_MOVING_SPEED: f32 = 4.;
+// robots spawned closer than this to a portal came out of it
+const PORTAL_SPAWN_DISTANCE: f32 = 1.;
+
+pub const ROBOT_IDLE: SpriteClip = SpriteClip::looping(0, 2, 2.);
+pub const ROBOT_MOVE: SpriteClip = SpriteClip::looping(2, 4, 10.);
+pub const ROBOT_FIRE: SpriteClip = SpriteClip::once(6, 3, 20.);
+pub const ROBOT_DEATH: SpriteClip = SpriteClip::once(9, 4, 12.);
+pub const PORTAL_IDLE: SpriteClip = SpriteClip::looping(0, 4, 4.);
+pub const PORTAL_SPAWN: SpriteClip = SpriteClip::once(4, 4, 16.);
+pub const PORTAL_DAMAGED: SpriteClip = SpriteClip::once(8, 2, 12.);
+pub const TOOL_IDLE: SpriteClip = SpriteClip::looping(0, 1, 1.);
+pub const TOOL_PULSE: SpriteClip = SpriteClip::once(1, 4, 16.);
+
+/// Frame animation of sprite sheets. Bevy's `AnimationClip` only drives transforms, and
+/// scaling robots, portals or tools would scale their colliders too, so clips here pick
+/// atlas frames instead.
+pub struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (
+                    play_robot_clips,
+                    play_portal_clips,
+                    play_tool_clips,
+                    spawn_robot_wrecks,
+                ),
+                animate_sprites,
+                despawn_robot_wrecks,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Consecutive frames of a sprite sheet row.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpriteClip {
+    pub first: usize,
+    pub len: usize,
+    pub fps: f32,
+    pub looping: bool,
+}
+
+impl SpriteClip {
+    pub const fn looping(first: usize, len: usize, fps: f32) -> Self {
+        Self {
+            first,
+            len,
+            fps,
+            looping: true,
+        }
+    }
+
+    pub const fn once(first: usize, len: usize, fps: f32) -> Self {
+        Self {
+            first,
+            len,
+            fps,
+            looping: false,
+        }
+    }
+}
+
+/// Clip played on the entity's `TextureAtlasSprite`. Robots have no sprite, their body
+/// frame is read when they are extracted.
+#[derive(Component)]
+pub struct SpriteAnimation {
+    clip: SpriteClip,
+    frame: usize,
+    timer: Timer,
+    done: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(clip: SpriteClip) -> Self {
+        Self {
+            clip,
+            frame: 0,
+            timer: Timer::new(Duration::from_secs_f32(1. / clip.fps), TimerMode::Repeating),
+            done: false,
+        }
+    }
+
+    /// Switches to `clip` unless it is already playing.
+    pub fn play(&mut self, clip: SpriteClip) {
+        if self.clip != clip {
+            *self = Self::new(clip);
+        }
+    }
+
+    pub fn restart(&mut self, clip: SpriteClip) {
+        *self = Self::new(clip);
+    }
+
+    pub fn clip(&self) -> SpriteClip {
+        self.clip
+    }
+
+    pub fn index(&self) -> usize {
+        self.clip.first + self.frame
+    }
+
+    /// Whether a clip that doesn't loop showed its last frame.
+    pub fn finished(&self) -> bool {
+        self.done
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+        for _ in 0..self.timer.times_finished_this_tick() {
+            if self.frame + 1 < self.clip.len {
+                self.frame += 1;
+            } else if self.clip.looping {
+                self.frame = 0;
+            } else {
+                self.done = true;
+            }
+        }
+    }
+}
+
+/// Dying robot body, removed once its clip is over.
+#[derive(Component)]
+struct RobotWreck;
+
+fn animate_sprites(
+    mut q_animation: Query<(&mut SpriteAnimation, Option<&mut TextureAtlasSprite>)>,
+    time: Res<Time>,
+) {
+    for (mut animation, sprite) in &mut q_animation {
+        animation.tick(time.delta());
+        if let Some(mut sprite) = sprite {
+            let index = animation.index();
+            if sprite.index != index {
+                sprite.index = index;
+            }
+        }
+    }
+}
+
+fn play_robot_clips(
+    mut q_robot: Query<(&mut SpriteAnimation, &Velocity), With<Robot>>,
+    mut ev_fire: EventReader<FireEvent>,
+) {
+    for ev in ev_fire.read() {
+        if let Ok((mut animation, _)) = q_robot.get_mut(ev.shooter) {
+            animation.restart(ROBOT_FIRE);
+        }
+    }
+    for (mut animation, velocity) in &mut q_robot {
+        if animation.clip() == ROBOT_FIRE && !animation.finished() {
+            continue;
+        }
+        if velocity.linvel.length() > ROBOT_MOVING_SPEED {
+            animation.play(ROBOT_MOVE);
+        } else {
+            animation.play(ROBOT_IDLE);
+        }
+    }
+}
+
+fn play_portal_clips(
+    mut q_portal: Query<(&mut SpriteAnimation, &Transform, &Team), With<Portal>>,
+    mut ev_robot_spawned: EventReader<RobotSpawned>,
+    mut ev_portal_damaged: EventReader<PortalDamaged>,
+) {
+    for ev in ev_robot_spawned.read() {
+        for (mut animation, tr, team) in &mut q_portal {
+            if *team == ev.team
+                && tr.translation.truncate().distance(ev.position) < PORTAL_SPAWN_DISTANCE
+            {
+                animation.restart(PORTAL_SPAWN);
+            }
+        }
+    }
+    for ev in ev_portal_damaged.read() {
+        if let Ok((mut animation, ..)) = q_portal.get_mut(ev.portal) {
+            // a spawn flash hides the hit
+            if animation.clip() != PORTAL_SPAWN || animation.finished() {
+                animation.restart(PORTAL_DAMAGED);
+            }
+        }
+    }
+    for (mut animation, ..) in &mut q_portal {
+        if animation.finished() {
+            animation.play(PORTAL_IDLE);
+        }
+    }
+}
+
+fn play_tool_clips(
+    q_robot: Query<&LastRedirect, Changed<LastRedirect>>,
+    mut q_tool: Query<&mut SpriteAnimation, (Without<Robot>, Without<Portal>)>,
+) {
+    for last_redirect in &q_robot {
+        if let Some((_, e_tool)) = last_redirect.0 {
+            if let Ok(mut animation) = q_tool.get_mut(e_tool) {
+                animation.restart(TOOL_PULSE);
+            }
+        }
+    }
+    for mut animation in &mut q_tool {
+        if animation.clip() == TOOL_PULSE && animation.finished() {
+            animation.play(TOOL_IDLE);
+        }
+    }
+}
+
+fn spawn_robot_wrecks(
+    mut cmd: Commands,
+    mut ev_robot_killed: EventReader<RobotKilled>,
+    assets: Res<TextureAssets>,
+    q_level: Query<Entity, With<LevelIid>>,
+) {
+    let Ok(e_level) = q_level.get_single() else {
+        return;
+    };
+    for ev in ev_robot_killed.read() {
+        let wreck = cmd
+            .spawn((
+                RobotWreck,
+                SpriteSheetBundle {
+                    texture_atlas: assets.robot_body.clone(),
+                    sprite: TextureAtlasSprite {
+                        index: ROBOT_DEATH.first,
+                        color: ev.team.tint(),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(ev.position.extend(5.0)),
+                    ..Default::default()
+                },
+                SpriteAnimation::new(ROBOT_DEATH),
+            ))
+            .id();
+        cmd.entity(e_level).add_child(wreck);
+    }
+}
+
+fn despawn_robot_wrecks(
+    mut cmd: Commands,
+    q_wreck: Query<(Entity, &SpriteAnimation), With<RobotWreck>>,
+) {
+    for (entity, animation) in &q_wreck {
+        if animation.finished() {
+            cmd.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    game::GameState,
+    hp::Life,
+    load::TextureAssets,
+    portal::Portal,
+    robot::{LastRedirect, Robot},
+};
+
+const ICON_Z: f32 = 51.;
+const ICON_SCALE: f32 = 0.75;
+const ICON_SPACING: f32 = 7.;
+// above the health bars
+const ROBOT_ICON_OFFSET: f32 = 13.;
+const PORTAL_ICON_OFFSET: f32 = 42.;
+const ZONE_ALPHA: f32 = 0.25;
+const MAX_BURN_STACKS: usize = 3;
+
+pub struct StatusEffectPlugin;
+
+impl Plugin for StatusEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyStatusEvent>()
+            .add_systems(
+                Update,
+                (
+                    fixup_status_zones,
+                    (apply_status_events, apply_tool_effects, apply_zone_effects),
+                    tick_status_effects,
+                    update_status_icons,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_icons);
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum StatusKind {
+    Slow,
+    Stun,
+    Burn,
+    Poison,
+    Shield,
+    Haste,
+}
+
+/// What happens when an effect is applied to a unit that already has one of the same kind.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Stacking {
+    /// One effect, with the longest duration and the highest strength of both.
+    Refresh,
+    /// One effect, the durations add up.
+    Extend,
+    /// Each application counts, up to this many. The one closest to running out is dropped.
+    Stack(usize),
+}
+
+impl StatusKind {
+    pub fn stacking(self) -> Stacking {
+        match self {
+            StatusKind::Burn => Stacking::Stack(MAX_BURN_STACKS),
+            StatusKind::Poison => Stacking::Extend,
+            StatusKind::Slow | StatusKind::Stun | StatusKind::Shield | StatusKind::Haste => {
+                Stacking::Refresh
+            }
+        }
+    }
+
+    /// Frame in the `status_icons` sheet.
+    fn icon(self) -> usize {
+        match self {
+            StatusKind::Slow => 0,
+            StatusKind::Stun => 1,
+            StatusKind::Burn => 2,
+            StatusKind::Poison => 3,
+            StatusKind::Shield => 4,
+            StatusKind::Haste => 5,
+        }
+    }
+
+    pub fn parse(word: &str) -> Option<Self> {
+        match word {
+            "slow" => Some(StatusKind::Slow),
+            "stun" => Some(StatusKind::Stun),
+            "burn" => Some(StatusKind::Burn),
+            "poison" => Some(StatusKind::Poison),
+            "shield" => Some(StatusKind::Shield),
+            "haste" => Some(StatusKind::Haste),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusKind::Slow => "slow",
+            StatusKind::Stun => "stun",
+            StatusKind::Burn => "burn",
+            StatusKind::Poison => "poison",
+            StatusKind::Shield => "shield",
+            StatusKind::Haste => "haste",
+        }
+    }
+}
+
+/// `strength` is the fraction of speed lost to slow or gained from haste, the damage per
+/// second of burn and poison, and the damage a shield absorbs. Stun ignores it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub strength: f32,
+    /// Seconds left.
+    pub remaining: f32,
+}
+
+impl StatusEffect {
+    pub const fn new(kind: StatusKind, strength: f32, duration: f32) -> Self {
+        Self {
+            kind,
+            strength,
+            remaining: duration,
+        }
+    }
+}
+
+/// Effects active on a robot or portal.
+#[derive(Component, Default)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: StatusEffect) {
+        match effect.kind.stacking() {
+            Stacking::Refresh => self.refresh(effect),
+            Stacking::Extend => match self.0.iter_mut().find(|e| e.kind == effect.kind) {
+                Some(current) => {
+                    current.remaining += effect.remaining;
+                    current.strength = current.strength.max(effect.strength);
+                }
+                None => self.0.push(effect),
+            },
+            Stacking::Stack(max) => {
+                self.0.push(effect);
+                while self.count(effect.kind) > max {
+                    let weakest = self
+                        .0
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, e)| e.kind == effect.kind)
+                        .min_by(|(_, a), (_, b)| a.remaining.total_cmp(&b.remaining))
+                        .map(|(i, _)| i);
+                    if let Some(i) = weakest {
+                        self.0.remove(i);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Applies `effect` as if it could only refresh, for sources that reapply it every frame.
+    pub fn refresh(&mut self, effect: StatusEffect) {
+        match self.0.iter_mut().find(|e| e.kind == effect.kind) {
+            Some(current) => {
+                current.remaining = current.remaining.max(effect.remaining);
+                current.strength = current.strength.max(effect.strength);
+            }
+            None => self.0.push(effect),
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|e| e.kind == kind)
+    }
+
+    fn count(&self, kind: StatusKind) -> usize {
+        self.0.iter().filter(|e| e.kind == kind).count()
+    }
+
+    fn strongest(&self, kind: StatusKind) -> f32 {
+        self.0
+            .iter()
+            .filter(|e| e.kind == kind)
+            .map(|e| e.strength)
+            .fold(0., f32::max)
+    }
+
+    pub fn stunned(&self) -> bool {
+        self.has(StatusKind::Stun)
+    }
+
+    /// Multiplier of movement, and of the spawn rate of portals.
+    pub fn speed_factor(&self) -> f32 {
+        (1. - self.strongest(StatusKind::Slow).clamp(0., 1.))
+            * (1. + self.strongest(StatusKind::Haste).max(0.))
+    }
+
+    fn damage_per_second(&self) -> f32 {
+        self.0
+            .iter()
+            .filter(|e| matches!(e.kind, StatusKind::Burn | StatusKind::Poison))
+            .map(|e| e.strength)
+            .sum()
+    }
+
+    /// Lets shields take what they can of `damage`, returns the rest.
+    pub fn absorb(&mut self, mut damage: f32) -> f32 {
+        for shield in self.0.iter_mut().filter(|e| e.kind == StatusKind::Shield) {
+            let absorbed = shield.strength.min(damage);
+            shield.strength -= absorbed;
+            damage -= absorbed;
+        }
+        self.0
+            .retain(|e| e.kind != StatusKind::Shield || e.strength > 0.);
+        damage
+    }
+}
+
+/// Removes `damage` from `life`, shields first. Returns the damage that got through.
+pub fn deal_damage(life: &mut Life, effects: Option<Mut<StatusEffects>>, damage: f32) -> f32 {
+    let damage = match effects {
+        Some(mut effects) if effects.has(StatusKind::Shield) => effects.absorb(damage),
+        _ => damage,
+    };
+    life.curr_hp -= damage;
+    damage
+}
+
+#[derive(Event)]
+pub struct ApplyStatusEvent {
+    pub target: Entity,
+    pub effect: StatusEffect,
+}
+
+/// Given to robots the tool redirects.
+#[derive(Component)]
+pub struct ToolEffect(pub StatusEffect);
+
+/// Area giving its effect to every robot inside, topped up while they stay.
+#[derive(Component, Default, Clone)]
+pub struct StatusZone {
+    pub effect: Option<StatusEffect>,
+    pub half_size: Vec2,
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct StatusZoneBundle {
+    #[with(extract_status_zone)]
+    zone: StatusZone,
+}
+
+fn extract_status_zone(entity_instance: &EntityInstance) -> StatusZone {
+    let effect = entity_instance
+        .get_string_field("effect")
+        .ok()
+        .and_then(|word| StatusKind::parse(word));
+    if effect.is_none() {
+        warn!("status zone {} has no valid effect", entity_instance.iid);
+    }
+    StatusZone {
+        effect: effect.map(|kind| {
+            StatusEffect::new(
+                kind,
+                *entity_instance.get_float_field("strength").unwrap_or(&0.),
+                *entity_instance.get_float_field("duration").unwrap_or(&0.),
+            )
+        }),
+        half_size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32) / 2.,
+    }
+}
+
+#[derive(Component)]
+struct StatusIcon {
+    target: Entity,
+    kind: StatusKind,
+}
+
+/// LDtk scales entities to their size in the level, zones are drawn at their size instead.
+fn fixup_status_zones(
+    mut cmd: Commands,
+    q_zone: Query<(Entity, &StatusZone, &Transform), Added<StatusZone>>,
+) {
+    for (entity, zone, tr) in &q_zone {
+        let color = match zone.effect.map(|effect| effect.kind) {
+            Some(StatusKind::Slow) => Color::rgb(0.35, 0.6, 1.0),
+            Some(StatusKind::Stun) => Color::rgb(1.0, 0.9, 0.25),
+            Some(StatusKind::Burn) => Color::rgb(1.0, 0.5, 0.1),
+            Some(StatusKind::Poison) => Color::rgb(0.45, 0.85, 0.3),
+            Some(StatusKind::Shield) => Color::rgb(0.3, 0.9, 0.95),
+            Some(StatusKind::Haste) => Color::rgb(0.95, 0.95, 0.95),
+            None => Color::GRAY,
+        };
+        cmd.entity(entity).insert(SpriteBundle {
+            sprite: Sprite {
+                color: color.with_a(ZONE_ALPHA),
+                custom_size: Some(zone.half_size * 2.),
+                ..Default::default()
+            },
+            transform: tr.with_scale(Vec3::ONE),
+            ..Default::default()
+        });
+    }
+}
+
+fn apply_status_events(
+    mut ev_apply_status: EventReader<ApplyStatusEvent>,
+    mut q_effects: Query<&mut StatusEffects>,
+) {
+    for ev in ev_apply_status.read() {
+        if let Ok(mut effects) = q_effects.get_mut(ev.target) {
+            effects.apply(ev.effect);
+        }
+    }
+}
+
+fn apply_tool_effects(
+    mut q_robot: Query<(&LastRedirect, &mut StatusEffects), (With<Robot>, Changed<LastRedirect>)>,
+    q_tool: Query<&ToolEffect>,
+) {
+    for (last_redirect, mut effects) in &mut q_robot {
+        if let Some((_, e_tool)) = last_redirect.0 {
+            if let Ok(tool_effect) = q_tool.get(e_tool) {
+                effects.apply(tool_effect.0);
+            }
+        }
+    }
+}
+
+fn apply_zone_effects(
+    q_zone: Query<(&StatusZone, &GlobalTransform)>,
+    mut q_robot: Query<(&GlobalTransform, &mut StatusEffects), With<Robot>>,
+) {
+    for (zone, zone_gtr) in &q_zone {
+        let Some(effect) = zone.effect else {
+            continue;
+        };
+        let center = zone_gtr.translation().truncate();
+        for (robot_gtr, mut effects) in &mut q_robot {
+            let offset = (robot_gtr.translation().truncate() - center).abs();
+            if offset.x < zone.half_size.x && offset.y < zone.half_size.y {
+                effects.refresh(effect);
+            }
+        }
+    }
+}
+
+fn tick_status_effects(mut q_effects: Query<(&mut StatusEffects, &mut Life)>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+    for (mut effects, mut life) in &mut q_effects {
+        if effects.0.is_empty() {
+            continue;
+        }
+        let damage = effects.damage_per_second() * dt;
+        if damage > 0. {
+            let damage = effects.absorb(damage);
+            life.curr_hp -= damage;
+        }
+        for effect in &mut effects.0 {
+            effect.remaining -= dt;
+        }
+        effects.0.retain(|e| e.remaining > 0.);
+    }
+}
+
+/// One icon per kind of active effect, in a row above the unit.
+fn update_status_icons(
+    mut cmd: Commands,
+    q_target: Query<(Entity, &GlobalTransform, &StatusEffects, Has<Portal>)>,
+    mut q_icon: Query<(Entity, &StatusIcon, &mut Transform)>,
+    assets: Res<TextureAssets>,
+) {
+    let mut shown = HashSet::new();
+    for (e_icon, icon, mut tr) in &mut q_icon {
+        let Ok((_, gtr, effects, is_portal)) = q_target.get(icon.target) else {
+            cmd.entity(e_icon).despawn_recursive();
+            continue;
+        };
+        let kinds = active_kinds(effects);
+        let Some(slot) = kinds.iter().position(|kind| *kind == icon.kind) else {
+            cmd.entity(e_icon).despawn_recursive();
+            continue;
+        };
+        shown.insert((icon.target, icon.kind));
+        tr.translation = icon_position(gtr, is_portal, slot, kinds.len());
+    }
+    for (entity, gtr, effects, is_portal) in &q_target {
+        if effects.0.is_empty() {
+            continue;
+        }
+        let kinds = active_kinds(effects);
+        for (slot, kind) in kinds.iter().enumerate() {
+            if shown.contains(&(entity, *kind)) {
+                continue;
+            }
+            cmd.spawn((
+                StatusIcon {
+                    target: entity,
+                    kind: *kind,
+                },
+                SpriteSheetBundle {
+                    texture_atlas: assets.status_icons.clone(),
+                    sprite: TextureAtlasSprite::new(kind.icon()),
+                    transform: Transform::from_translation(icon_position(
+                        gtr,
+                        is_portal,
+                        slot,
+                        kinds.len(),
+                    ))
+                    .with_scale(Vec3::splat(ICON_SCALE)),
+                    ..Default::default()
+                },
+            ));
+        }
+    }
+}
+
+fn active_kinds(effects: &StatusEffects) -> Vec<StatusKind> {
+    let mut kinds: Vec<StatusKind> = vec![];
+    for effect in &effects.0 {
+        if !kinds.contains(&effect.kind) {
+            kinds.push(effect.kind);
+        }
+    }
+    kinds.sort_by_key(|kind| kind.icon());
+    kinds
+}
+
+fn icon_position(gtr: &GlobalTransform, is_portal: bool, slot: usize, count: usize) -> Vec3 {
+    let offset = if is_portal {
+        PORTAL_ICON_OFFSET
+    } else {
+        ROBOT_ICON_OFFSET
+    };
+    let x = (slot as f32 - (count - 1) as f32 / 2.) * ICON_SPACING;
+    (gtr.translation().truncate() + Vec2::new(x, offset)).extend(ICON_Z)
+}
+
+fn despawn_icons(mut cmd: Commands, q_icon: Query<Entity, With<StatusIcon>>) {
+    for entity in &q_icon {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
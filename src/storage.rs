@@ -0,0 +1,51 @@
+//! Small text files kept between sessions, in the platform data directory on desktop and
+//! in the browser's local storage on the web.
+
+#[cfg(not(target_arch = "wasm32"))]
+const APP_DIR: &str = "robo-swarm";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir() -> Option<std::path::PathBuf> {
+    use std::{env, path::PathBuf};
+
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.map(|base| base.join(APP_DIR))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(name: &str) -> Option<String> {
+    std::fs::read_to_string(data_dir()?.join(name)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(name: &str, content: &str) -> Result<(), String> {
+    let dir = data_dir().ok_or_else(|| "no data directory".to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    std::fs::write(dir.join(name), content).map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(name: &str) -> Option<String> {
+    local_storage()?.get_item(name).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(name: &str, content: &str) -> Result<(), String> {
+    local_storage()
+        .ok_or_else(|| "no local storage".to_string())?
+        .set_item(name, content)
+        .map_err(|err| format!("{:?}", err))
+}
@@ -0,0 +1,315 @@
+use bevy::{
+    input::{mouse::MouseMotion, touch::Touches, InputSystem},
+    prelude::*,
+};
+use bevy_rapier2d::plugin::RapierContext;
+
+use crate::{
+    game::GameState,
+    game_camera::{CameraTargetPos, CameraTargetScale, MainCamera, MouseWorldCoords},
+    levels::tools_unlocked,
+    mouse::{
+        send_click_sensor_events, ClickSensor, ClickSensorEvent, Drag, DragDropRequest, MouseState,
+    },
+};
+
+const LONG_PRESS_TIME: f32 = 0.5;
+// in screen pixels
+const LONG_PRESS_MAX_MOVE: f32 = 12.;
+
+pub struct TouchControlsPlugin;
+
+impl Plugin for TouchControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TouchCursor::default())
+            .insert_resource(TouchGesture::default())
+            .add_systems(PreUpdate, track_touch_cursor.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    pinch_and_pan,
+                    long_press_pickup.run_if(tools_unlocked),
+                    touch_drop,
+                    end_gesture,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Screen position of the last touch. It stays after the finger is lifted so that
+/// drops happen where the touch ended, and is cleared as soon as the mouse moves.
+#[derive(Resource, Default)]
+pub struct TouchCursor(pub Option<Vec2>);
+
+#[derive(Resource, Default)]
+struct TouchGesture {
+    // midpoint and distance of the two fingers on the last frame
+    pinch: Option<(Vec2, f32)>,
+    // more than one finger was used since all fingers were last lifted
+    multi_touch: bool,
+    press_time: f32,
+    long_press_done: bool,
+}
+
+fn track_touch_cursor(
+    touches: Res<Touches>,
+    mut motion_ev: EventReader<MouseMotion>,
+    mut touch_cursor: ResMut<TouchCursor>,
+) {
+    if let Some(touch) = touches.iter().next() {
+        touch_cursor.0 = Some(touch.position());
+    } else if let Some(touch) = touches.iter_just_released().next() {
+        touch_cursor.0 = Some(touch.position());
+    } else if motion_ev.read().count() > 0 {
+        touch_cursor.0 = None;
+    }
+}
+
+fn pinch_and_pan(
+    touches: Res<Touches>,
+    mut gesture: ResMut<TouchGesture>,
+    mut target_pos: ResMut<CameraTargetPos>,
+    mut target_scale: ResMut<CameraTargetScale>,
+    q_camera: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let fingers: Vec<Vec2> = touches.iter().map(|touch| touch.position()).collect();
+    if let [first, second, ..] = fingers[..] {
+        gesture.multi_touch = true;
+        let mid = (first + second) / 2.0;
+        let distance = first.distance(second);
+        if let Some((last_mid, last_distance)) = gesture.pinch {
+            let scale = q_camera.single().scale;
+            let delta = mid - last_mid;
+            // screen y goes down, world y goes up
+            target_pos.0 += Vec3::new(-delta.x, delta.y, 0.0) * scale;
+            if distance > 0.0 {
+                let current = target_scale.get();
+                target_scale.set(current * last_distance / distance);
+            }
+        }
+        gesture.pinch = Some((mid, distance));
+    } else {
+        gesture.pinch = None;
+    }
+}
+
+fn long_press_pickup(
+    touches: Res<Touches>,
+    mut gesture: ResMut<TouchGesture>,
+    mouse_state: Res<MouseState>,
+    mouse_pos: Res<MouseWorldCoords>,
+    rapier_context: Res<RapierContext>,
+    q_click_sensor: Query<Entity, With<ClickSensor>>,
+    mut ev_click_sensor: EventWriter<ClickSensorEvent>,
+    time: Res<Time>,
+) {
+    if touches.any_just_pressed() {
+        gesture.press_time = 0.0;
+        gesture.long_press_done = false;
+    }
+    let mut fingers = touches.iter();
+    let (Some(touch), None) = (fingers.next(), fingers.next()) else {
+        return;
+    };
+    if gesture.multi_touch
+        || gesture.long_press_done
+        || touch.distance().length() > LONG_PRESS_MAX_MOVE
+    {
+        return;
+    }
+    gesture.press_time += time.delta_seconds();
+    if gesture.press_time >= LONG_PRESS_TIME && *mouse_state == MouseState::Default {
+        gesture.long_press_done = true;
+        if let Some(point) = mouse_pos.0 {
+            send_click_sensor_events(
+                point,
+                &rapier_context,
+                &q_click_sensor,
+                &mut ev_click_sensor,
+            );
+        }
+    }
+}
+
+fn touch_drop(
+    mut cmd: Commands,
+    touches: Res<Touches>,
+    gesture: Res<TouchGesture>,
+    mouse_state: Res<MouseState>,
+    q_drag: Query<Entity, With<Drag>>,
+) {
+    if *mouse_state == MouseState::Dragging
+        && touches.any_just_released()
+        && touches.iter().next().is_none()
+        && !gesture.multi_touch
+    {
+        for entity in &q_drag {
+            cmd.entity(entity).insert(DragDropRequest);
+        }
+    }
+}
+
+fn end_gesture(touches: Res<Touches>, mut gesture: ResMut<TouchGesture>) {
+    if touches.iter().next().is_none() {
+        gesture.multi_touch = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        input::{
+            touch::{TouchInput, TouchPhase},
+            InputPlugin,
+        },
+        time::TimeUpdateStrategy,
+    };
+    use bevy_rapier2d::prelude::*;
+
+    use super::*;
+    use crate::{game::LevelState, levels::PuzzleLevel};
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            InputPlugin,
+            TransformPlugin,
+            RapierPhysicsPlugin::<NoUserData>::default(),
+            TouchControlsPlugin,
+        ))
+        .add_state::<GameState>()
+        .add_state::<LevelState>()
+        .add_event::<ClickSensorEvent>()
+        .insert_resource(NextState(Some(GameState::Playing)))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )))
+        .insert_resource(MouseState::default())
+        .insert_resource(MouseWorldCoords(None))
+        .insert_resource(CameraTargetPos(Vec3::ZERO))
+        .insert_resource(CameraTargetScale::new())
+        .insert_resource(PuzzleLevel(false));
+        app.world
+            .spawn((MainCamera, OrthographicProjection::default()));
+        app.update();
+        app
+    }
+
+    fn touch(app: &mut App, id: u64, phase: TouchPhase, x: f32, y: f32) {
+        app.world.send_event(TouchInput {
+            phase,
+            position: Vec2::new(x, y),
+            force: None,
+            id,
+        });
+    }
+
+    #[test]
+    fn pinch_zooms_and_pans() {
+        let mut app = test_app();
+        let scale = app.world.resource::<CameraTargetScale>().get();
+        touch(&mut app, 0, TouchPhase::Started, 100., 100.);
+        touch(&mut app, 1, TouchPhase::Started, 200., 100.);
+        app.update();
+        touch(&mut app, 1, TouchPhase::Moved, 300., 100.);
+        app.update();
+
+        assert!(app.world.resource::<CameraTargetScale>().get() < scale);
+        // the midpoint moved 50 pixels right, so the camera moves left
+        assert_eq!(app.world.resource::<CameraTargetPos>().0.x, -50.);
+        assert!(app.world.resource::<TouchGesture>().multi_touch);
+    }
+
+    #[test]
+    fn long_press_picks_up() {
+        let mut app = test_app();
+        let sensor = app
+            .world
+            .spawn((
+                ClickSensor,
+                Collider::ball(8.),
+                Sensor,
+                TransformBundle::default(),
+            ))
+            .id();
+        app.insert_resource(MouseWorldCoords(Some(Vec2::new(2., 2.))));
+        let mut reader = app
+            .world
+            .resource::<Events<ClickSensorEvent>>()
+            .get_reader();
+        let mut clicked = vec![];
+        touch(&mut app, 0, TouchPhase::Started, 100., 100.);
+        app.update();
+        clicked.extend(
+            reader
+                .read(app.world.resource::<Events<ClickSensorEvent>>())
+                .map(|ev| ev.0),
+        );
+        assert!(clicked.is_empty());
+        for _ in 0..6 {
+            app.update();
+            clicked.extend(
+                reader
+                    .read(app.world.resource::<Events<ClickSensorEvent>>())
+                    .map(|ev| ev.0),
+            );
+        }
+        assert_eq!(clicked, vec![sensor]);
+    }
+
+    #[test]
+    fn long_press_cancelled_by_moving() {
+        let mut app = test_app();
+        touch(&mut app, 0, TouchPhase::Started, 100., 100.);
+        app.update();
+        touch(
+            &mut app,
+            0,
+            TouchPhase::Moved,
+            100. + LONG_PRESS_MAX_MOVE * 2.,
+            100.,
+        );
+        for _ in 0..6 {
+            app.update();
+        }
+        assert!(!app.world.resource::<TouchGesture>().long_press_done);
+    }
+
+    #[test]
+    fn lifting_finger_drops_dragged_tool() {
+        let mut app = test_app();
+        *app.world.resource_mut::<MouseState>() = MouseState::Dragging;
+        let drag = app.world.spawn(Drag).id();
+        touch(&mut app, 0, TouchPhase::Started, 100., 100.);
+        app.update();
+        assert!(!app.world.entity(drag).contains::<DragDropRequest>());
+        touch(&mut app, 0, TouchPhase::Ended, 100., 100.);
+        app.update();
+
+        assert!(app.world.entity(drag).contains::<DragDropRequest>());
+        assert_eq!(
+            app.world.resource::<TouchCursor>().0,
+            Some(Vec2::new(100., 100.))
+        );
+    }
+
+    #[test]
+    fn lifting_after_pinch_does_not_drop() {
+        let mut app = test_app();
+        *app.world.resource_mut::<MouseState>() = MouseState::Dragging;
+        let drag = app.world.spawn(Drag).id();
+        touch(&mut app, 0, TouchPhase::Started, 100., 100.);
+        touch(&mut app, 1, TouchPhase::Started, 200., 100.);
+        app.update();
+        touch(&mut app, 0, TouchPhase::Ended, 100., 100.);
+        touch(&mut app, 1, TouchPhase::Ended, 200., 100.);
+        app.update();
+
+        assert!(!app.world.entity(drag).contains::<DragDropRequest>());
+    }
+}
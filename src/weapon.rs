@@ -0,0 +1,583 @@
+use std::time::Duration;
+
+use bevy::{math::vec2, prelude::*, sprite::Anchor};
+use bevy_ecs_ldtk::LevelIid;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::*;
+
+use crate::{
+    bullet::bullet_groups,
+    console::{Console, ConsoleCommand},
+    explosion::{ExplosionEvent, BULLET_IMPACT_EXPLOSION},
+    game::{GameRng, GameState, LevelState},
+    gameplay_events::{BulletHit, HitSource, PortalDamaged},
+    hp::{LastHitBy, Life},
+    physics::Team,
+    portal::Portal,
+    robot::{EngineDir, Robot, RobotTarget},
+    status_effect::{deal_damage, ApplyStatusEvent, StatusEffect, StatusEffects, StatusKind},
+};
+
+pub const NUZZLE_OFFSET: f32 = 8.;
+const NUZZLE_FLASH_DURATION: Duration = Duration::from_millis(100);
+// distance between weapons mounted on the same robot
+const MOUNT_SPACING: f32 = 4.;
+const BEAM_WIDTH: f32 = 1.5;
+const BEAM_DURATION: Duration = Duration::from_millis(150);
+const DEFAULT_ACCURACY: f32 = 1.;
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FireEvent>()
+            .insert_resource(AimAccuracy::default())
+            .add_systems(
+                Update,
+                (
+                    (aim_weapons, rotate_weapons, fire_weapons, fire_beams).chain(),
+                    fade_beams,
+                    set_accuracy,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WeaponKind {
+    /// Bullets flying straight until they hit something.
+    Projectile,
+    /// Hits the first robot or portal in line the moment it fires.
+    Beam,
+    /// Flies over everything and explodes where the target was.
+    Shell,
+}
+
+/// Stats of a weapon, shared by every robot mounting it.
+#[derive(Debug)]
+pub struct Weapon {
+    pub name: &'static str,
+    pub kind: WeaponKind,
+    pub cooldown: Duration,
+    /// The cooldown changes randomly by up to half of it either way after each shot.
+    pub variance: Duration,
+    pub range: f32,
+    /// Pixels per second, unused by beams.
+    pub projectile_speed: f32,
+    pub damage: f32,
+    /// Largest deviation of a shot in radians.
+    pub spread: f32,
+    /// Shots fired each time the cooldown ends.
+    pub burst: usize,
+    pub burst_interval: Duration,
+    /// Radians per second.
+    pub rotation_speed: f32,
+    /// Shells only.
+    pub splash_radius: f32,
+    /// Given to what the weapon hits.
+    pub effect: Option<StatusEffect>,
+    pub color: Color,
+}
+
+pub const CANON: Weapon = Weapon {
+    name: "canon",
+    kind: WeaponKind::Projectile,
+    cooldown: Duration::from_secs(1),
+    variance: Duration::from_millis(900),
+    range: 96.,
+    projectile_speed: 240.,
+    damage: 1.,
+    spread: 0.,
+    burst: 1,
+    burst_interval: Duration::ZERO,
+    rotation_speed: 3.,
+    splash_radius: 0.,
+    effect: None,
+    color: Color::WHITE,
+};
+
+pub const GATLING: Weapon = Weapon {
+    name: "gatling",
+    kind: WeaponKind::Projectile,
+    cooldown: Duration::from_millis(1600),
+    variance: Duration::from_millis(400),
+    range: 80.,
+    projectile_speed: 300.,
+    damage: 0.4,
+    spread: 0.15,
+    burst: 4,
+    burst_interval: Duration::from_millis(80),
+    rotation_speed: 5.,
+    splash_radius: 0.,
+    effect: None,
+    color: Color::rgb(0.8, 0.8, 1.),
+};
+
+pub const LASER: Weapon = Weapon {
+    name: "laser",
+    kind: WeaponKind::Beam,
+    cooldown: Duration::from_millis(1500),
+    variance: Duration::from_millis(300),
+    range: 112.,
+    projectile_speed: 0.,
+    damage: 1.2,
+    spread: 0.,
+    burst: 1,
+    burst_interval: Duration::ZERO,
+    rotation_speed: 2.,
+    splash_radius: 0.,
+    effect: Some(StatusEffect::new(StatusKind::Burn, 0.3, 2.)),
+    color: Color::rgb(1., 0.4, 0.4),
+};
+
+pub const MORTAR: Weapon = Weapon {
+    name: "mortar",
+    kind: WeaponKind::Shell,
+    cooldown: Duration::from_secs(3),
+    variance: Duration::from_secs(1),
+    range: 160.,
+    projectile_speed: 120.,
+    damage: 2.,
+    spread: 0.1,
+    burst: 1,
+    burst_interval: Duration::ZERO,
+    rotation_speed: 1.5,
+    splash_radius: 20.,
+    effect: None,
+    color: Color::rgb(0.6, 0.6, 0.6),
+};
+
+pub const WEAPONS: [&Weapon; 4] = [&CANON, &GATLING, &LASER, &MORTAR];
+
+impl Weapon {
+    pub fn by_name(name: &str) -> Option<&'static Weapon> {
+        WEAPONS.iter().copied().find(|weapon| weapon.name == name)
+    }
+
+    /// Point to shoot at from `from` to hit a target at `pos` moving at `velocity`, with
+    /// `accuracy` the share of the target's motion the shot leads by.
+    pub fn aim_point(&self, from: Vec2, pos: Vec2, velocity: Vec2, accuracy: f32) -> Vec2 {
+        if self.kind == WeaponKind::Beam {
+            return pos;
+        }
+        pos.lerp(
+            intercept_point(from, pos, velocity, self.projectile_speed),
+            accuracy,
+        )
+    }
+}
+
+/// Where a projectile leaving `from` at `speed` meets a target at `pos` moving at
+/// `velocity`, or `pos` when it can't catch up.
+pub fn intercept_point(from: Vec2, pos: Vec2, velocity: Vec2, speed: f32) -> Vec2 {
+    // |to_target + velocity * t| = speed * t
+    let to_target = pos - from;
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2. * to_target.dot(velocity);
+    let c = to_target.length_squared();
+    let time = if a.abs() < f32::EPSILON {
+        -c / b
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return pos;
+        }
+        let root = discriminant.sqrt();
+        let (t1, t2) = ((-b - root) / (2. * a), (-b + root) / (2. * a));
+        match (t1 > 0., t2 > 0.) {
+            (true, true) => t1.min(t2),
+            (true, false) => t1,
+            (false, true) => t2,
+            (false, false) => return pos,
+        }
+    };
+    if time.is_finite() && time > 0. {
+        pos + velocity * time
+    } else {
+        pos
+    }
+}
+
+/// How well each team leads moving targets, from 0 (shooting where the target is) to 1
+/// (shooting where it will be). Set from the `player_aim_accuracy` and `enemy_aim_accuracy`
+/// level fields.
+#[derive(Resource)]
+pub struct AimAccuracy {
+    pub player: f32,
+    pub enemy: f32,
+}
+
+impl AimAccuracy {
+    pub fn get(&self, team: Team) -> f32 {
+        match team {
+            Team::Player => self.player,
+            Team::Enemy => self.enemy,
+        }
+    }
+}
+
+impl Default for AimAccuracy {
+    fn default() -> Self {
+        Self {
+            player: DEFAULT_ACCURACY,
+            enemy: DEFAULT_ACCURACY,
+        }
+    }
+}
+
+/// Weapons named in a comma separated list, unknown names are skipped.
+pub fn parse_loadout(text: &str) -> Vec<&'static Weapon> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let weapon = Weapon::by_name(name);
+            if weapon.is_none() {
+                warn!("unknown weapon '{}'", name);
+            }
+            weapon
+        })
+        .collect()
+}
+
+/// One weapon on a robot, turning and cooling down on its own.
+pub struct WeaponMount {
+    pub weapon: &'static Weapon,
+    pub angle: f32,
+    target_dir: Vec2,
+    pub cooldown: Timer,
+    pub burst_left: usize,
+    pub burst_timer: Timer,
+    flash: Timer,
+}
+
+impl WeaponMount {
+    /// Mount that can fire right away.
+    pub fn ready(weapon: &'static Weapon) -> Self {
+        let mut cooldown = Timer::new(weapon.cooldown, TimerMode::Repeating);
+        cooldown.tick(weapon.cooldown);
+        let mut flash = Timer::new(NUZZLE_FLASH_DURATION, TimerMode::Once);
+        flash.set_elapsed(NUZZLE_FLASH_DURATION);
+        Self {
+            weapon,
+            angle: 0.,
+            target_dir: Vec2::ZERO,
+            cooldown,
+            burst_left: 0,
+            burst_timer: Timer::new(weapon.burst_interval, TimerMode::Once),
+            flash,
+        }
+    }
+
+    pub fn flashing(&self) -> bool {
+        !self.flash.finished()
+    }
+}
+
+/// Weapons of a robot, side by side on its turret. They are drawn from the robot
+/// entity, see `extract_robot_sprites`.
+#[derive(Component)]
+pub struct Weapons(pub Vec<WeaponMount>);
+
+impl Weapons {
+    /// Mounts ready to fire, a canon alone when the loadout is empty.
+    pub fn ready(loadout: &[&'static Weapon]) -> Self {
+        if loadout.is_empty() {
+            return Self(vec![WeaponMount::ready(&CANON)]);
+        }
+        Self(
+            loadout
+                .iter()
+                .map(|weapon| WeaponMount::ready(weapon))
+                .collect(),
+        )
+    }
+
+    /// Distance at which the robot picks its targets.
+    pub fn range(&self) -> f32 {
+        self.0
+            .iter()
+            .map(|mount| mount.weapon.range)
+            .fold(0., f32::max)
+    }
+}
+
+/// Position of the `index`th of `count` mounts relative to the robot.
+pub fn mount_offset(index: usize, count: usize) -> Vec2 {
+    vec2(0., (index as f32 - (count - 1) as f32 / 2.) * MOUNT_SPACING)
+}
+
+#[derive(Event)]
+pub struct FireEvent {
+    pub from_pos: Vec3,
+    pub to_target: Vec3,
+    pub team: Team,
+    pub shooter: Entity,
+    pub weapon: &'static Weapon,
+}
+
+#[derive(Component)]
+struct Beam(Timer);
+
+/// World position and velocity of the robot's target.
+fn target_motion(
+    robot_target: Option<&RobotTarget>,
+    q_other: &Query<(&GlobalTransform, Option<&Velocity>)>,
+) -> Option<(Vec2, Vec2)> {
+    let RobotTarget(e_other) = robot_target?;
+    let (other_gtr, velocity) = q_other.get(*e_other).ok()?;
+    Some((
+        other_gtr.translation().truncate(),
+        velocity.map_or(Vec2::ZERO, |velocity| velocity.linvel),
+    ))
+}
+
+fn aim_weapons(
+    mut q_robot: Query<
+        (
+            &GlobalTransform,
+            &EngineDir,
+            Option<&RobotTarget>,
+            &Team,
+            &mut Weapons,
+        ),
+        With<Robot>,
+    >,
+    q_other: Query<(&GlobalTransform, Option<&Velocity>)>,
+    accuracy: Res<AimAccuracy>,
+) {
+    for (robot_gtr, engine_dir, robot_target, team, mut weapons) in &mut q_robot {
+        let target = target_motion(robot_target, &q_other);
+        let count = weapons.0.len();
+        for (index, mount) in weapons.0.iter_mut().enumerate() {
+            let weapon = mount.weapon;
+            let mount_pos = robot_gtr.translation().truncate() + mount_offset(index, count);
+            mount.target_dir = match target {
+                Some((pos, velocity)) if pos.distance(mount_pos) <= weapon.range => {
+                    let lead = weapon.aim_point(mount_pos, pos, velocity, accuracy.get(*team));
+                    (lead - mount_pos).normalize_or_zero()
+                }
+                _ => engine_dir.0,
+            };
+        }
+    }
+}
+
+fn rotate_weapons(mut q_weapons: Query<&mut Weapons>, time: Res<Time>) {
+    for mut weapons in &mut q_weapons {
+        for mount in &mut weapons.0 {
+            if mount.target_dir == Vec2::ZERO {
+                continue;
+            }
+            let max_rotation = mount.weapon.rotation_speed * time.delta_seconds();
+            let angle = Vec2::from_angle(mount.angle).angle_between(mount.target_dir);
+            mount.angle += angle.clamp(-max_rotation, max_rotation);
+        }
+    }
+}
+
+fn fire_weapons(
+    mut q_robot: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut Weapons,
+            Option<&RobotTarget>,
+            &Team,
+            &StatusEffects,
+        ),
+        With<Robot>,
+    >,
+    q_other: Query<(&GlobalTransform, Option<&Velocity>)>,
+    accuracy: Res<AimAccuracy>,
+    time: Res<Time>,
+    mut ev_fire: EventWriter<FireEvent>,
+    q_level: Query<&GlobalTransform, With<LevelIid>>,
+    level_state: Res<State<LevelState>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if *level_state != LevelState::Playing {
+        return;
+    }
+    let Ok(level_gtr) = q_level.get_single() else {
+        return;
+    };
+    for (e_robot, robot_gtr, mut weapons, robot_target, team, effects) in &mut q_robot {
+        let target = target_motion(robot_target, &q_other);
+        let count = weapons.0.len();
+        for (index, mount) in weapons.0.iter_mut().enumerate() {
+            mount.flash.tick(time.delta());
+            if effects.stunned() {
+                continue;
+            }
+            mount.cooldown.tick(time.delta());
+            mount.burst_timer.tick(time.delta());
+            let weapon = mount.weapon;
+            let mount_pos = robot_gtr.translation().truncate() + mount_offset(index, count);
+            let curr_dir = Vec2::from_angle(mount.angle);
+            // the lead point, when the target is in range and the weapon turned to it
+            let in_sight = target.and_then(|(pos, velocity)| {
+                let lead = weapon.aim_point(mount_pos, pos, velocity, accuracy.get(*team));
+                let to_lead = (lead - mount_pos).normalize_or_zero();
+                (pos.distance(mount_pos) <= weapon.range
+                    && curr_dir.angle_between(to_lead).abs() < 0.1)
+                    .then_some(lead)
+            });
+            let fire = if mount.burst_left > 0 && mount.burst_timer.finished() {
+                mount.burst_left -= 1;
+                true
+            } else if mount.cooldown.finished() {
+                let variance = weapon.variance.as_secs_f32();
+                let cd = weapon.cooldown.as_secs_f32();
+                let ms = variance * rng.0.gen::<f32>() - variance / 2.;
+                mount
+                    .cooldown
+                    .set_duration(Duration::from_secs_f32(cd + ms));
+                mount.cooldown.reset();
+                if in_sight.is_some() {
+                    mount.burst_left = weapon.burst.saturating_sub(1);
+                }
+                true
+            } else {
+                false
+            };
+            let Some(target) = in_sight.filter(|_| fire) else {
+                continue;
+            };
+            mount.burst_timer.reset();
+            mount.flash.reset();
+            let nuzzle_pos = mount_pos + curr_dir * NUZZLE_OFFSET;
+            let spread = (rng.0.gen::<f32>() * 2. - 1.) * weapon.spread;
+            let aim = Vec2::from_angle(spread).rotate(target - nuzzle_pos);
+            let z = robot_gtr.translation().z;
+            ev_fire.send(FireEvent {
+                from_pos: nuzzle_pos.extend(z) - level_gtr.translation(),
+                to_target: (nuzzle_pos + aim).extend(z) - level_gtr.translation(),
+                team: *team,
+                shooter: e_robot,
+                weapon,
+            });
+        }
+    }
+}
+
+fn fire_beams(
+    mut cmd: Commands,
+    mut ev_fire: EventReader<FireEvent>,
+    rapier_context: Res<RapierContext>,
+    mut q_other: Query<(&mut Life, Option<&mut StatusEffects>, &Team, Has<Portal>)>,
+    q_level: Query<(Entity, &GlobalTransform), With<LevelIid>>,
+    mut ev_explosion: EventWriter<ExplosionEvent>,
+    mut ev_portal_damaged: EventWriter<PortalDamaged>,
+    mut ev_bullet_hit: EventWriter<BulletHit>,
+    mut ev_apply_status: EventWriter<ApplyStatusEvent>,
+) {
+    let Ok((e_level, level_gtr)) = q_level.get_single() else {
+        return;
+    };
+    let offset = level_gtr.translation().truncate();
+    for ev in ev_fire.read() {
+        if ev.weapon.kind != WeaponKind::Beam {
+            continue;
+        }
+        let from = ev.from_pos.truncate();
+        let dir = (ev.to_target.truncate() - from).normalize_or_zero();
+        let filter = QueryFilter {
+            groups: Some(bullet_groups(ev.team)),
+            ..Default::default()
+        };
+        let mut length = ev.weapon.range;
+        if let Some((other, toi)) =
+            rapier_context.cast_ray(from + offset, dir, ev.weapon.range, true, filter)
+        {
+            length = toi;
+            if let Ok((mut life, effects, team, is_portal)) = q_other.get_mut(other) {
+                let position = from + dir * toi;
+                let damage = deal_damage(&mut life, effects, ev.weapon.damage);
+                cmd.entity(other).try_insert(LastHitBy {
+                    shooter: ev.shooter,
+                    team: ev.team,
+                });
+                if let Some(effect) = ev.weapon.effect {
+                    ev_apply_status.send(ApplyStatusEvent {
+                        target: other,
+                        effect,
+                    });
+                }
+                ev_bullet_hit.send(BulletHit {
+                    target: other,
+                    shooter: ev.shooter,
+                    damage,
+                    position,
+                    source: HitSource::Beam,
+                });
+                if is_portal {
+                    ev_portal_damaged.send(PortalDamaged {
+                        portal: other,
+                        team: *team,
+                        damage,
+                        remaining_hp: life.curr_hp,
+                        shooter: ev.shooter,
+                    });
+                }
+                ev_explosion.send(ExplosionEvent {
+                    direction: dir,
+                    ..ExplosionEvent::new(BULLET_IMPACT_EXPLOSION, position)
+                });
+            }
+        }
+        let e_beam = cmd
+            .spawn((
+                Beam(Timer::new(BEAM_DURATION, TimerMode::Once)),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: ev.weapon.color,
+                        custom_size: Some(vec2(length, BEAM_WIDTH)),
+                        anchor: Anchor::CenterLeft,
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(from.extend(3.))
+                        .with_rotation(Quat::from_rotation_z(dir.y.atan2(dir.x))),
+                    ..Default::default()
+                },
+            ))
+            .id();
+        cmd.entity(e_level).add_child(e_beam);
+    }
+}
+
+fn fade_beams(
+    mut cmd: Commands,
+    mut q_beam: Query<(Entity, &mut Beam, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (e_beam, mut beam, mut sprite) in &mut q_beam {
+        beam.0.tick(time.delta());
+        if beam.0.finished() {
+            cmd.entity(e_beam).despawn_recursive();
+        } else {
+            sprite.color.set_a(beam.0.percent_left());
+        }
+    }
+}
+
+fn set_accuracy(
+    mut ev_command: EventReader<ConsoleCommand>,
+    mut accuracy: ResMut<AimAccuracy>,
+    mut console: ResMut<Console>,
+) {
+    for command in ev_command.read() {
+        if let ConsoleCommand::AimAccuracy {
+            team,
+            accuracy: value,
+        } = command
+        {
+            match team {
+                Team::Player => accuracy.player = *value,
+                Team::Enemy => accuracy.enemy = *value,
+            }
+            console.log(format!("{:?} aim accuracy set to {}", team, value));
+        }
+    }
+}